@@ -0,0 +1,69 @@
+#ifndef TM_SOLVER_H
+#define TM_SOLVER_H
+
+/* Generated with cbindgen from src/ffi.rs, don't edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// [`tm_next_question`] found a test to perform.
+#define TM_QUESTION 0
+
+// [`tm_next_question`] found the code.
+#define TM_SOLVED 1
+
+// [`tm_next_question`] got invalid arguments, e. g. more answers than the
+// strategy has questions.
+#define TM_INVALID -1
+
+// The optimal strategy for a puzzle. Create it with [`tm_solve`] and free it
+// with [`tm_free_tree`].
+typedef struct TmTree TmTree;
+
+// A test to perform, or the code once it's found.
+typedef struct TmQuestion {
+  uint8_t blue;
+  uint8_t yellow;
+  uint8_t purple;
+  // the zero-based index of the verifier to test the code on, i. e. 0 for
+  // verifier A. Unset once the code is found.
+  uint8_t verifier;
+  // the number of the verifier's criteria card.
+  uint8_t card;
+  // whether this test starts a new round, i. e. a new code.
+  bool new_round;
+} TmQuestion;
+
+// Construct the optimal strategy for a puzzle, given the numbers of its
+// `count` criteria cards. Returns null if the puzzle can't be solved.
+//
+// # Safety
+//
+// `cards` has to point to `count` readable values.
+struct TmTree *tm_solve(const uint32_t *cards, size_t count);
+
+// Free a strategy created by [`tm_solve`]. Does nothing for null.
+//
+// # Safety
+//
+// `tree` has to come from [`tm_solve`] and may not be used afterwards.
+void tm_free_tree(struct TmTree *tree);
+
+// Follow the strategy with the `count` answers given so far (true for a
+// checkmark) and write the next test to `question`. Returns [`TM_QUESTION`],
+// [`TM_SOLVED`] if the code is found or [`TM_INVALID`].
+//
+// # Safety
+//
+// `tree` has to come from [`tm_solve`], `answers` has to point to `count`
+// readable values (or be null if `count` is 0) and `question` has to be
+// writable.
+int32_t tm_next_question(const struct TmTree *tree,
+                         const bool *answers,
+                         size_t count,
+                         struct TmQuestion *question);
+
+#endif  /* TM_SOLVER_H */
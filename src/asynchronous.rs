@@ -0,0 +1,227 @@
+//! Running the solver from async code, e. g. a web server, without blocking
+//! its runtime. Only available with the `async` feature.
+//!
+//! The pipeline runs on a thread of its own, like with tokio's
+//! `spawn_blocking`, and wakes the task waiting for it, so this works with
+//! any runtime. [`SolverBuilder::solve_async`] is a future of the optimal
+//! strategy, [`SolverBuilder::updates`] a [`Stream`] of everything that
+//! happens on the way:
+//!
+//! ```
+//! # use std::future::Future;
+//! # use std::sync::Arc;
+//! # use std::task::{Context, Poll, Wake};
+//! # struct Unpark(std::thread::Thread);
+//! # impl Wake for Unpark {
+//! #     fn wake(self: Arc<Self>) {
+//! #         self.0.unpark();
+//! #     }
+//! # }
+//! # // the simplest possible runtime.
+//! # fn block_on<F: Future>(future: F) -> F::Output {
+//! #     let mut future = std::pin::pin!(future);
+//! #     let waker = Arc::new(Unpark(std::thread::current())).into();
+//! #     let mut cx = Context::from_waker(&waker);
+//! #     loop {
+//! #         if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+//! #             return output;
+//! #         }
+//! #         std::thread::park();
+//! #     }
+//! # }
+//! use tm_solver::asynchronous::Update;
+//! use tm_solver::solver::Solver;
+//!
+//! block_on(async {
+//!     let strategy = Solver::builder().rules(&[4, 9, 11, 14]).solve_async().await?;
+//!     assert_eq!(strategy.tree.leaf_count(), strategy.unique_solutions.len());
+//!
+//!     let mut updates = Solver::builder().rules(&[4, 9, 11, 14]).updates();
+//!     let mut phases = Vec::new();
+//!     while let Some(update) = updates.next().await {
+//!         match update {
+//!             Update::Phase(phase) => phases.push(phase),
+//!             Update::Progress(_) => {},
+//!             Update::Improved(improvement) => {
+//!                 assert!(improvement.strategy.tree.max_depth() >= strategy.tree.max_depth());
+//!             },
+//!             Update::Failed(e) => return Err(e),
+//!         }
+//!     }
+//!     assert_eq!(phases.last().unwrap(), "Construct optimal tree");
+//!     Ok(())
+//! })?;
+//! # Ok::<(), tm_solver::error::TmSolverError>(())
+//! ```
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+pub use futures_core::Stream;
+
+use crate::error::TmSolverError;
+use crate::progress::ProgressSink;
+use crate::solver::{Improvement, SolverBuilder, Strategy};
+
+/// What the items sent by the pipeline's thread and the task waiting for
+/// them share.
+struct Channel<T> {
+    items: VecDeque<T>,
+    /// whether the thread is done.
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+/// The end of a channel the pipeline's thread sends to.
+struct Sender<T>(Arc<Mutex<Channel<T>>>);
+
+impl<T> Sender<T> {
+
+    fn send(&self, item: T) {
+        let mut channel = self.0.lock().unwrap();
+        channel.items.push_back(item);
+        if let Some(waker) = channel.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Closes the channel once the thread is done, even if it panics.
+struct Closer<T>(Arc<Mutex<Channel<T>>>);
+
+impl<T> Drop for Closer<T> {
+    fn drop(&mut self) {
+        let mut channel = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        channel.closed = true;
+        if let Some(waker) = channel.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// The end of a channel the waiting task receives from.
+struct Receiver<T>(Arc<Mutex<Channel<T>>>);
+
+impl<T> Receiver<T> {
+
+    /// The next item, or None once the thread is done and all items are
+    /// received.
+    fn poll_recv(&self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let mut channel = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(item) = channel.items.pop_front() {
+            return Poll::Ready(Some(item));
+        }
+        if channel.closed {
+            return Poll::Ready(None);
+        }
+        channel.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+/// Run a task on a thread of its own, receiving what it sends.
+fn spawn<T: Send + 'static>(task: impl FnOnce(Sender<T>) + Send + 'static) -> Receiver<T> {
+    let channel = Arc::new(Mutex::new(Channel { items: VecDeque::new(), closed: false, waker: None }));
+    let sender = Sender(channel.clone());
+    let closer = Closer(channel.clone());
+    std::thread::spawn(move || {
+        let _closer = closer;
+        task(sender);
+    });
+    Receiver(channel)
+}
+
+/// Something that happened while solving a puzzle, see
+/// [`SolverBuilder::updates`].
+#[derive(Debug, Clone)]
+pub enum Update {
+    /// a new phase starts, see [`ProgressSink::start`].
+    Phase(String),
+    /// the current phase is done to the given percentage, see
+    /// [`ProgressSink::progress`].
+    Progress(u8),
+    /// a strategy better than the previous ones was found. The last one is
+    /// the optimal one.
+    Improved(Improvement),
+    /// the puzzle can't be solved.
+    Failed(TmSolverError),
+}
+
+/// Passes the progress of the pipeline on as updates.
+struct Forward(Sender<Update>);
+
+impl ProgressSink for Forward {
+    fn start(&self, phase: &str) {
+        self.0.send(Update::Phase(phase.to_string()));
+    }
+
+    fn progress(&self, percent: u8) {
+        self.0.send(Update::Progress(percent));
+    }
+
+    fn finish(&self) {}
+}
+
+/// The optimal strategy of a puzzle, found in the background, see
+/// [`SolverBuilder::solve_async`].
+pub struct Solving(Receiver<Result<Strategy, TmSolverError>>);
+
+impl Future for Solving {
+    type Output = Result<Strategy, TmSolverError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.0.poll_recv(cx).map(|found| found.expect("the solver panicked"))
+    }
+}
+
+/// The updates of a pipeline running in the background, see
+/// [`SolverBuilder::updates`]. Ends after the optimal strategy, or after an
+/// error.
+pub struct Updates(Receiver<Update>);
+
+impl Updates {
+
+    /// Wait for the next update, for those who don't want to depend on
+    /// `futures` for [`StreamExt::next`](https://docs.rs/futures/latest/futures/stream/trait.StreamExt.html#method.next).
+    pub async fn next(&mut self) -> Option<Update> {
+        std::future::poll_fn(|cx| self.0.poll_recv(cx)).await
+    }
+}
+
+impl Stream for Updates {
+    type Item = Update;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Update>> {
+        self.0.poll_recv(cx)
+    }
+}
+
+impl SolverBuilder {
+
+    /// Run the whole pipeline in the background, see
+    /// [`SolverBuilder::solve`]. Dropping the future doesn't stop it.
+    pub fn solve_async(&self) -> Solving {
+        let builder = self.clone();
+        Solving(spawn(move |sender| sender.send(builder.solve())))
+    }
+
+    /// Run the whole pipeline in the background, reporting its phases and
+    /// every strategy that beats the best one found so far, like
+    /// [`SolverBuilder::stream`]. This replaces the builder's
+    /// [`progress`](SolverBuilder::progress) sink. Dropping the stream
+    /// doesn't stop the pipeline.
+    pub fn updates(&self) -> Updates {
+        let builder = self.clone();
+        Updates(spawn(move |sender| {
+            let sender = Arc::new(Forward(sender));
+            let builder = builder.progress(sender.clone());
+            builder.improve(|found| sender.0.send(match found {
+                Ok(improvement) => Update::Improved(improvement),
+                Err(e) => Update::Failed(e),
+            }));
+        }))
+    }
+}
@@ -0,0 +1,117 @@
+//! Timing the solver on a bundled set of puzzles, so changes in the speed of
+//! the search show up right away.
+//!
+//! The puzzles come in three groups: easy ones, the hard ones that take the
+//! search the most nodes, and ones with six verifiers. Every puzzle is solved
+//! from scratch, and the number of nodes is that of the tree search, see
+//! [`SearchStats::calls`](tm_solver::construct_tree::SearchStats::calls). On
+//! a single thread, the default, it hardly changes from run to run, unlike
+//! the time, so it shows changes to the search itself. With `--json`, the
+//! results are printed as a single JSON object, e. g.
+//!
+//! ```text
+//! {"puzzles":[{"group":"easy","cards":[4,9,11,14],"max_tests":1,"nodes":3,"tests":4,"millis":0.4}, ...],
+//!  "total_millis":12.3}
+//! ```
+
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use tm_solver::construct_tree;
+use tm_solver::solver::Solver;
+use crate::cli::{BenchArgs, BenchGroup};
+use crate::failure::{report, Failure};
+use crate::json;
+
+/// The criteria cards of every bundled puzzle, by group.
+const CORPUS: &[(BenchGroup, &[usize])] = &[
+    (BenchGroup::Easy, &[4, 9, 11, 14]),
+    (BenchGroup::Easy, &[3, 10, 13, 17]),
+    (BenchGroup::Easy, &[5, 14, 17, 24]),
+    (BenchGroup::Easy, &[7, 13, 23, 25]),
+    (BenchGroup::Hard, &[9, 15, 17, 19]),
+    (BenchGroup::Hard, &[11, 18, 19, 25]),
+    (BenchGroup::Hard, &[14, 17, 19, 21]),
+    (BenchGroup::Hard, &[2, 3, 5, 13, 21]),
+    (BenchGroup::Hard, &[7, 12, 14, 18, 24]),
+    (BenchGroup::SixVerifiers, &[2, 7, 13, 18, 20, 25]),
+    (BenchGroup::SixVerifiers, &[5, 7, 9, 13, 21, 24]),
+    (BenchGroup::SixVerifiers, &[4, 5, 8, 10, 21, 24]),
+];
+
+/// How solving a puzzle of the corpus went.
+#[derive(Serialize)]
+struct Run {
+    group: &'static str,
+    cards: &'static [usize],
+    /// the number of tests the optimal strategy needs at most.
+    max_tests: u8,
+    /// the subtrees the search tried to construct.
+    nodes: usize,
+    /// the candidate tests the search considered.
+    tests: usize,
+    /// the fastest of all repetitions.
+    millis: f64,
+}
+
+#[derive(Serialize)]
+struct Output {
+    puzzles: Vec<Run>,
+    total_millis: f64,
+}
+
+/// The name of a group, as given on the command line.
+fn name(group: BenchGroup) -> &'static str {
+    match group {
+        BenchGroup::Easy => "easy",
+        BenchGroup::Hard => "hard",
+        BenchGroup::SixVerifiers => "six-verifiers",
+    }
+}
+
+/// Solve a puzzle of the corpus as often as requested.
+fn bench(group: BenchGroup, cards: &'static [usize], args: &BenchArgs) -> Result<Run, String> {
+    let mut fastest = Duration::MAX;
+    let (mut max_tests, mut nodes, mut tests) = (0, 0, 0);
+    for _ in 0..args.repeat {
+        let before = construct_tree::search_stats();
+        let start = Instant::now();
+        let strategy = Solver::builder().rules(cards).threads(args.threads).solve()
+            .map_err(|e| format!("Could not solve {:?}: {}", cards, e))?;
+        fastest = fastest.min(start.elapsed());
+        let after = construct_tree::search_stats();
+        max_tests = strategy.tree.max_depth();
+        (nodes, tests) = (after.calls - before.calls, after.tests - before.tests);
+    }
+    Ok(Run { group: name(group), cards, max_tests, nodes, tests, millis: fastest.as_secs_f64() * 1000.0 })
+}
+
+/// Solve the puzzles of the corpus and print how long each one took.
+pub fn run(args: BenchArgs) {
+    let mut puzzles = Vec::new();
+    if !json::enabled() {
+        println!("{:<14} {:<18} {:>5} {:>8} {:>10}", "Group", "Cards", "Tests", "Nodes", "Time");
+    }
+    for (group, cards) in CORPUS.iter().filter(|(g, _)| args.group.is_none_or(|only| only == *g)) {
+        let run = match bench(*group, cards, &args) {
+            Ok(run) => run,
+            Err(e) => {
+                report(Failure::Internal, &e);
+                continue;
+            },
+        };
+        if !json::enabled() {
+            let cards: Vec<String> = cards.iter().map(|c| c.to_string()).collect();
+            println!("{:<14} {:<18} {:>5} {:>8} {:>7.1} ms", run.group, cards.join(" "), run.max_tests, run.nodes,
+                run.millis);
+        }
+        puzzles.push(run);
+    }
+    let total_millis = puzzles.iter().map(|p| p.millis).sum();
+    match json::enabled() {
+        true => println!("{}", serde_json::to_string(&Output { puzzles, total_millis }).unwrap()),
+        false => println!("{:<14} {:<18} {:>5} {:>8} {:>7.1} ms", "total", "", "",
+            puzzles.iter().map(|p| p.nodes).sum::<usize>(), total_millis),
+    }
+}
@@ -0,0 +1,522 @@
+//! Command line interface of the solver.
+//!
+//! All subcommands and their options are defined here, the actual work is
+//! done in main.rs.
+
+use std::path::PathBuf;
+
+use clap::{builder::PossibleValuesParser, ArgAction, Args, Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
+
+use tm_solver::construct_tree::Answer;
+use tm_solver::error::TmSolverError;
+use tm_solver::hash::PuzzleHash;
+use tm_solver::rules::{Code, RULES};
+use tm_solver::i18n::LANGUAGES;
+use tm_solver::puzzle::Puzzle;
+use tm_solver::style::{SPINNERS, THEMES};
+
+/// A solver for the board game Turing Machine.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Don't use colors. Also respects the NO_COLOR environment variable.
+    #[arg(long, global = true)]
+    pub no_color: bool,
+    /// The color theme to use.
+    #[arg(long, global = true, env = "TM_SOLVER_THEME", default_value = "default",
+        value_parser = PossibleValuesParser::new(THEMES.map(|t| t.name)))]
+    pub theme: String,
+    /// Print codes as words, e. g. "blue 4, yellow 1, purple 3", instead of
+    /// colored digits.
+    #[arg(long, global = true)]
+    pub spell_out: bool,
+    /// Only print ASCII characters, e. g. + and - instead of checkmarks and
+    /// crosses, and no colors. Useful for simple terminals, logs or
+    /// forum posts.
+    #[arg(long, global = true)]
+    pub ascii: bool,
+    /// Don't animate the progress spinner. Also happens if stdout isn't a
+    /// terminal.
+    #[arg(long, global = true)]
+    pub no_spinner: bool,
+    /// The progress spinner to use.
+    #[arg(long, global = true, default_value = "braille",
+        value_parser = PossibleValuesParser::new(SPINNERS.map(|s| s.name)))]
+    pub spinner: String,
+    /// The language of the interactive prompts. Defaults to the system
+    /// language, if supported, or English.
+    #[arg(long, global = true, env = "TM_SOLVER_LANG",
+        value_parser = PossibleValuesParser::new(LANGUAGES.map(|l| l.name)))]
+    pub lang: Option<String>,
+    /// Print the unique solutions, the solution tree and the result as JSON
+    /// instead of the usual output. Guided sessions need --answers then.
+    #[arg(long, global = true)]
+    pub json: bool,
+    /// How to report errors. The exit code tells what went wrong in any
+    /// case: 1 for internal errors, 2 for invalid input, 3 for unsolvable
+    /// and 4 for improper puzzles.
+    #[arg(long, global = true, default_value = "text")]
+    pub errors: ErrorFormat,
+    /// Read JSON-RPC requests from stdin, one per line, and answer them on
+    /// stdout instead of running a command.
+    #[arg(long)]
+    pub rpc: bool,
+    /// How much diagnostic output to write to stderr. The verbosity of the
+    /// solve command raises it.
+    #[arg(long, global = true, env = "TM_SOLVER_LOG", default_value = "warn")]
+    pub log_level: LogLevel,
+}
+
+/// The levels of diagnostic output, from none at all to every decision of
+/// the search.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum LogLevel {
+    Off,
+    Error,
+    #[default]
+    Warn,
+    Info,
+    /// the time each step took and statistics about the search.
+    Debug,
+    /// every removed code and every test the search skipped.
+    Trace,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Guide you through solving a puzzle with the physical game.
+    Solve(SolveArgs),
+    /// Resume a guided session saved with the save command.
+    Load(LoadArgs),
+    /// Step through a session recorded with --log.
+    Replay(ReplayArgs),
+    /// List all supported criteria cards.
+    ListRules,
+    /// Generate a random puzzle.
+    Generate(GenerateArgs),
+    /// Play through the solver's strategy for a given secret code.
+    Simulate(SimulateArgs),
+    /// Print information about a puzzle and its optimal strategy.
+    Analyze(AnalyzeArgs),
+    /// Browse a puzzle's solution tree without playing a game.
+    Explore(AnalyzeArgs),
+    /// Practice on your own: the solver plays the machine and answers your
+    /// tests.
+    Practice(PracticeArgs),
+    /// Propose the single most informative next test, given the tests
+    /// performed so far.
+    Hint(PuzzleArgs),
+    /// Export a puzzle's optimal strategy for other programs.
+    Export(ExportArgs),
+    /// Solve a bundled set of puzzles and report how long each one took, to
+    /// spot changes in the speed of the search.
+    Bench(BenchArgs),
+    /// Download a puzzle and solve it.
+    #[cfg(feature = "network")]
+    Fetch(FetchArgs),
+    /// Serve a JSON API for front-ends driving the solver remotely.
+    #[cfg(feature = "server")]
+    Serve(ServeArgs),
+}
+
+/// Options shared by all commands working on a specific puzzle.
+#[derive(Debug, Default, Args)]
+pub struct PuzzleArgs {
+    /// The numbers of the puzzle's criteria cards, e. g. 4 9 11 14. To match
+    /// the verifier letters on the table, assign them explicitly, e. g.
+    /// A=4 B=9 C=11 D=14. A puzzle code printed by generate, e. g.
+    /// "#007 QSAK", stands for all cards with their letters.
+    #[arg(required = true, value_parser = parse_cards)]
+    pub rules: Vec<CardsArg>,
+    /// Rank candidate tests by looking two tests ahead while constructing
+    /// the strategy.
+    #[arg(long)]
+    pub lookahead: bool,
+    /// A test you already performed, given as code, verifier letter and
+    /// result, e. g. 241:A=y. Can be repeated.
+    #[arg(short, long, value_parser = parse_query)]
+    pub known: Vec<Query>,
+    /// Which code to use for a round if several codes work equally well.
+    #[arg(long, value_enum, default_value_t)]
+    pub round_codes: CodePreference,
+    /// A file to learn from: the tests of the optimal strategies stored in
+    /// it are tried first, which speeds up solving the same or similar
+    /// puzzles again, and this puzzle's strategy is added. Created if it
+    /// doesn't exist.
+    #[arg(long, env = "TM_SOLVER_HISTORY")]
+    pub history: Option<PathBuf>,
+    /// Roughly how many megabytes the construction of the strategy may use.
+    /// Beyond that, it prunes more aggressively, which keeps huge custom
+    /// puzzles from filling up the memory, but might miss the optimal
+    /// strategy.
+    #[arg(long, value_name = "MB")]
+    pub max_memory: Option<usize>,
+    /// Look for strategies with as few tests as possible first, then for
+    /// ones with a test more and so on. Finds short strategies fast and
+    /// takes less memory, but is slower if the optimal strategy needs many
+    /// more tests than that.
+    #[arg(long)]
+    pub deepening: bool,
+}
+
+/// How errors are reported, see the `failure` module.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ErrorFormat {
+    /// The message only, like all other output.
+    #[default]
+    Text,
+    /// A JSON object with the message, its kind and the exit code on stderr.
+    Json,
+}
+
+/// Which of several equally good codes to use for a round.
+#[derive(Debug, Default, Clone, Copy, ValueEnum)]
+pub enum CodePreference {
+    /// Whichever code the solver finds first.
+    #[default]
+    Any,
+    /// The code with the smallest digits, e. g. 112 rather than 341.
+    Smallest,
+    /// Codes repeating a digit, e. g. 111 or 225, which are easy to
+    /// remember.
+    Repeated,
+    /// Codes with digits in ascending order, e. g. 135.
+    Ascending,
+}
+
+impl PuzzleArgs {
+
+    /// The puzzle with its known tests, checking that it has a valid number
+    /// of distinct criteria cards. Letters are either assigned explicitly or
+    /// by the order of the rules.
+    pub fn puzzle(&self) -> Result<Puzzle, String> {
+        if self.rules.len() > 1 && self.rules.iter().any(|r| matches!(r, CardsArg::Code(_))) {
+            return Err("A puzzle code already stands for all criteria cards, so don't add any others.".to_string());
+        }
+        let rules: Vec<&RuleArg> = self.rules.iter().flat_map(|r| match r {
+            CardsArg::Card(card) => core::slice::from_ref(card),
+            CardsArg::Code(cards) => cards,
+        }).collect();
+        let explicit = rules.iter().filter(|r| r.letter.is_some()).count();
+        if explicit > 0 && explicit < rules.len() {
+            return Err("Either assign a letter to every criteria card or to none of them.".to_string());
+        }
+        let verifiers: Vec<(char, usize)> = rules.iter().enumerate()
+            .map(|(i, r)| (r.letter.unwrap_or((b'A' + i as u8) as char), r.rule as usize))
+            .collect();
+        let mut puzzle = Puzzle::with_letters(&verifiers).map_err(|e| e.to_string())?.lookahead(self.lookahead);
+        for k in &self.known {
+            puzzle = puzzle.known(k.code, k.letter, k.answer);
+        }
+        Ok(puzzle)
+    }
+}
+
+/// A criteria card, optionally with the letter of the verifier it belongs to.
+#[derive(Debug, Clone)]
+pub struct RuleArg {
+    pub letter: Option<char>,
+    pub rule: u8,
+}
+
+/// Criteria cards as given on the command line: a single card, or a puzzle
+/// code standing for all of them.
+#[derive(Debug, Clone)]
+pub enum CardsArg {
+    Card(RuleArg),
+    Code(Vec<RuleArg>),
+}
+
+/// Parse a criteria card, see [`parse_rule`], or a puzzle code, e. g.
+/// "#007 QSAK", see [`PuzzleHash::decode`].
+fn parse_cards(input: &str) -> Result<CardsArg, String> {
+    if !input.trim_start().starts_with('#') {
+        return parse_rule(input).map(CardsArg::Card);
+    }
+    let hash: PuzzleHash = input.parse().map_err(|e: TmSolverError| e.to_string())?;
+    let verifiers = hash.decode().map_err(|e| e.to_string())?;
+    Ok(CardsArg::Code(verifiers.into_iter()
+        .map(|(letter, card)| RuleArg { letter: Some(letter), rule: card as u8 })
+        .collect()))
+}
+
+/// Parse a criteria card given either as a number (e. g. 4) or with a
+/// verifier letter (e. g. A=4).
+fn parse_rule(input: &str) -> Result<RuleArg, String> {
+    let (letter, number) = match input.split_once('=') {
+        Some((l, n)) => {
+            let mut chars = l.trim().chars();
+            let letter = match (chars.next(), chars.next()) {
+                (Some(c @ ('A'..='F' | 'a'..='f')), None) => c.to_ascii_uppercase(),
+                _ => return Err(format!("{} is not a verifier letter: use A to F", l)),
+            };
+            (Some(letter), n.trim())
+        },
+        None => (None, input.trim()),
+    };
+    match number.parse::<u8>() {
+        Ok(rule) if rule > 0 && rule as usize <= RULES.len() => Ok(RuleArg { letter, rule }),
+        _ => Err(format!("{} is not a valid criteria card: use 1 to {}", number, RULES.len())),
+    }
+}
+
+#[derive(Debug, Default, Args)]
+pub struct SolveArgs {
+    #[command(flatten)]
+    pub puzzle: PuzzleArgs,
+    /// Print the unique solutions and the resulting solution tree. Repeat
+    /// for diagnostics on stderr: -vv also lists the removed codes and why
+    /// they were removed, -vvv adds statistics about the search and the time
+    /// each step took, -vvvv every test the search skipped. The same as
+    /// --log-level info, debug and trace, respectively.
+    #[arg(short, long, action = ArgAction::Count)]
+    pub verbose: u8,
+    /// Warn before starting if the strategy might need more questions than
+    /// this. The search skips longer strategies, so it finds out early.
+    #[arg(long, value_name = "N")]
+    pub max_questions: Option<u8>,
+    /// Don't start at all if the strategy might exceed --max-questions.
+    #[arg(long, requires = "max_questions")]
+    pub strict: bool,
+    #[command(flatten)]
+    pub tree: TreeArgs,
+    #[command(flatten)]
+    pub guide: GuideArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct AnalyzeArgs {
+    #[command(flatten)]
+    pub puzzle: PuzzleArgs,
+    #[command(flatten)]
+    pub tree: TreeArgs,
+}
+
+/// Options for printing solution trees.
+#[derive(Debug, Default, Args)]
+pub struct TreeArgs {
+    /// Only print the solution tree up to this many tests deep.
+    #[arg(long, value_name = "N")]
+    pub depth: Option<u8>,
+    /// When printing the solution tree to a terminal, pause after this many
+    /// lines. 0 never pauses.
+    #[arg(long, value_name = "LINES", default_value_t = 40)]
+    pub page_size: usize,
+}
+
+#[derive(Debug, Args)]
+pub struct LoadArgs {
+    /// The file the session was saved to.
+    pub file: PathBuf,
+    #[command(flatten)]
+    pub guide: GuideArgs,
+}
+
+/// Options for guided sessions.
+#[derive(Debug, Default, Args)]
+pub struct GuideArgs {
+    /// After every answer, print which codes are still possible.
+    #[arg(short, long)]
+    pub remaining: bool,
+    /// Explain every question by how many codes each answer would leave.
+    #[arg(short, long)]
+    pub explain: bool,
+    /// Record every round, question and answer to a transcript file.
+    #[arg(long, value_name = "FILE")]
+    pub log: Option<PathBuf>,
+    /// Use a full-screen interface instead of a line-based dialog.
+    #[cfg(feature = "tui")]
+    #[arg(long)]
+    pub tui: bool,
+    /// Answer the questions in the given order without asking, e. g.
+    /// --answers y n y, and print the resulting code.
+    #[arg(long, num_args = 1.., value_parser = parse_answer, conflicts_with = "answers_from")]
+    pub answers: Option<Vec<Answer>>,
+    /// Like --answers, but read the answers from a file, or from stdin if
+    /// FILE is -.
+    #[arg(long, value_name = "FILE")]
+    pub answers_from: Option<PathBuf>,
+}
+
+impl GuideArgs {
+
+    /// The answers to give without asking, if any were passed.
+    pub fn answers(&self) -> Result<Option<Vec<Answer>>, String> {
+        let Some(path) = &self.answers_from else {
+            return Ok(self.answers.clone());
+        };
+        let input = match path.to_str() {
+            Some("-") => std::io::read_to_string(std::io::stdin()),
+            _ => std::fs::read_to_string(path),
+        }.map_err(|e| format!("Could not read the answers: {}", e))?;
+        input.split_whitespace().map(parse_answer).collect::<Result<_, _>>().map(Some)
+    }
+}
+
+/// Parse an answer to a question, y or n.
+pub fn parse_answer(input: &str) -> Result<Answer, String> {
+    match input {
+        "y" | "yes" => Ok(Answer::Pass),
+        "n" | "no" => Ok(Answer::Fail),
+        _ => Err(format!("{} is not a valid answer: use y or n", input)),
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct ReplayArgs {
+    /// The transcript file written by --log.
+    pub file: PathBuf,
+    /// After every answer, print how many codes are still possible.
+    #[arg(short, long)]
+    pub remaining: bool,
+    /// Wait for Enter after every step.
+    #[arg(long)]
+    pub step: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct GenerateArgs {
+    /// The number of verifiers.
+    #[arg(short = 'n', long, default_value_t = 4, value_parser = clap::value_parser!(u8).range(4..=6))]
+    pub verifiers: u8,
+    /// Seed for the random number generator, for reproducible puzzles.
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Also print the secret code.
+    #[arg(long)]
+    pub reveal: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct SimulateArgs {
+    #[command(flatten)]
+    pub puzzle: PuzzleArgs,
+    /// The secret code, e. g. 241 or "b2 y4 p1". Simulates all possible codes if omitted.
+    #[arg(long, value_parser = parse_code)]
+    pub code: Option<Code>,
+}
+
+#[derive(Debug, Args)]
+pub struct PracticeArgs {
+    #[command(flatten)]
+    pub puzzle: PuzzleArgs,
+    /// Seed for the random number generator, for reproducible games.
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    #[command(flatten)]
+    pub puzzle: PuzzleArgs,
+    /// The format to export the strategy in.
+    #[arg(long, value_enum, default_value_t)]
+    pub format: ExportFormat,
+    /// Write the export to this file instead of stdout.
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct BenchArgs {
+    /// Only solve the puzzles of this group.
+    #[arg(long, value_enum)]
+    pub group: Option<BenchGroup>,
+    /// How many threads search for every strategy. The number of nodes
+    /// searched only stays about the same from run to run on a single
+    /// thread.
+    #[arg(long, default_value_t = 1)]
+    pub threads: usize,
+    /// Solve every puzzle this many times and report the fastest time.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub repeat: u32,
+}
+
+/// The groups of puzzles the bench command solves, see the `bench` module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BenchGroup {
+    /// puzzles with short strategies.
+    Easy,
+    /// puzzles that take the search the most nodes.
+    Hard,
+    /// puzzles with six verifiers, which have the most candidate tests.
+    SixVerifiers,
+}
+
+#[derive(Debug, Args)]
+pub struct FetchArgs {
+    /// The puzzle code, e. g. "#B45 4FJ", or daily for the daily challenge.
+    pub id: String,
+    /// Where to download puzzles from: a URL containing {id}, answering with
+    /// the criteria card numbers, e. g. {"rules":[4,9,11,14]}.
+    #[arg(long, env = "TM_SOLVER_PUZZLE_SOURCE")]
+    pub source: String,
+    #[command(flatten)]
+    pub guide: GuideArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct ServeArgs {
+    /// The address to listen on. Use 0.0.0.0 to accept other devices.
+    #[arg(long, default_value = "127.0.0.1")]
+    pub host: String,
+    /// The port to listen on.
+    #[arg(long, default_value_t = 8080)]
+    pub port: u16,
+    /// How many requests are handled at the same time.
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u16).range(1..))]
+    pub workers: u16,
+    /// How many games are kept at most. Starting another one forgets the
+    /// oldest.
+    #[arg(long, default_value_t = 1000, value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_games: u64,
+}
+
+/// The formats a strategy can be exported in.
+#[derive(Debug, Default, Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    /// The solution tree as nested JSON objects.
+    #[default]
+    Json,
+    /// A round-by-round guide for printing or pasting into a chat.
+    Markdown,
+    /// A table of every code and its categories, for spreadsheets.
+    Csv,
+}
+
+/// A test that has been performed with the physical game.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Query {
+    pub code: Code,
+    pub letter: char,
+    #[serde(rename = "passed")]
+    pub answer: Answer,
+}
+
+/// Parse a test given as code, verifier letter and result, e. g. 241:A=y.
+pub fn parse_query(input: &str) -> Result<Query, String> {
+    let error = || format!("{} is not a valid test: use code:letter=y or code:letter=n, e. g. 241:A=y", input);
+    let (code, rest) = input.split_once(':').ok_or_else(error)?;
+    let (letter, result) = rest.split_once('=').ok_or_else(error)?;
+    let code = parse_code(code)?;
+    let mut chars = letter.chars();
+    let letter = match (chars.next(), chars.next()) {
+        (Some(c @ ('A'..='F' | 'a'..='f')), None) => c.to_ascii_uppercase(),
+        _ => return Err(error()),
+    };
+    let answer = match result {
+        "y" => Answer::Pass,
+        "n" => Answer::Fail,
+        _ => return Err(error()),
+    };
+    Ok(Query { code, letter, answer })
+}
+
+/// Parse a code given as three digits from 1 to 5, e. g. 241, or as one digit
+/// per color, e. g. "b2 y4 p1".
+pub fn parse_code(input: &str) -> Result<Code, String> {
+    input.parse().map_err(|e: TmSolverError| e.to_string())
+}
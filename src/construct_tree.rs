@@ -5,19 +5,391 @@
 //! For the game Turing Machine, there's an additional constraint that three
 //! consecutive tests have to be performed with the same input code (this
 //! series of tests is called a round). The algorithm thus allows you to
-//! specify the number of tests per round.
-use std::{collections::{HashSet, HashMap}, cmp::Ordering};
+//! specify the number of tests per round. Games without this constraint can
+//! skip it by not passing a solution map to `optimal_tree`, which turns the
+//! search into a pure binary search over the feasible solutions.
+//! 
+//! Additionally, a hard cap on the total number of tests can be given, in
+//! which case trees that might exceed it are rejected. Trees are optimized
+//! for the worst case by default, or for the average number of tests.
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+#[cfg(feature = "std")]
+use core::sync::atomic::{AtomicBool, AtomicU8};
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, trace};
+
+use crate::collections::{HashMap, HashSet};
+use crate::history::TestOrder;
+use crate::progress::ProgressSink;
 
 /// A possible solution to the problem, including its test results (represented
 /// by the first element of the tuple).
 /// 
 /// A solution should be uniquely identifyable by its test results
-pub type Feasible<T> = (Vec<u8>, T);
+pub type Feasible<T> = (Results, T);
+
+/// The most verifiers a puzzle can have, and so the most test results of a
+/// solution.
+pub const MAX_VERIFIERS: usize = 6;
+
+/// The test results of a solution, one per verifier. Grouping codes creates
+/// one for every code, so they are stored inline rather than on the heap, up
+/// to [`MAX_VERIFIERS`] of them. Derefs to a slice of the results, and maps
+/// keyed by them can be looked up by slices as well.
+///
+/// ```
+/// use tm_solver::construct_tree::Results;
+///
+/// let mut results = Results::from([0, 2]);
+/// results.push(1);
+/// assert_eq!(results.len(), 3);
+/// assert_eq!(results.remove(0), 0);
+/// assert_eq!(results, [2, 1].into_iter().collect());
+/// assert_eq!(&results[..], &[2, 1]);
+/// ```
+#[derive(Clone, Copy, Default)]
+pub struct Results {
+    len: u8,
+    /// the results, followed by zeros.
+    results: [u8; MAX_VERIFIERS],
+}
+
+impl Results {
+
+    /// No results yet.
+    pub const fn new() -> Results {
+        Results { len: 0, results: [0; MAX_VERIFIERS] }
+    }
+
+    /// Append a result. Panics if there are [`MAX_VERIFIERS`] already.
+    pub fn push(&mut self, result: u8) {
+        assert!((self.len as usize) < MAX_VERIFIERS, "there are at most {} test results", MAX_VERIFIERS);
+        self.results[self.len as usize] = result;
+        self.len += 1;
+    }
+
+    /// Remove the result at `index`, moving the following ones forward.
+    pub fn remove(&mut self, index: usize) -> u8 {
+        let removed = self[index];
+        self.results.copy_within(index + 1.., index);
+        self.len -= 1;
+        self.results[self.len as usize] = 0;
+        removed
+    }
+
+    /// The results as a slice.
+    pub fn as_slice(&self) -> &[u8] {
+        &self.results[..self.len as usize]
+    }
+}
+
+impl Deref for Results {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl DerefMut for Results {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.results[..self.len as usize]
+    }
+}
+
+impl Borrow<[u8]> for Results {
+    fn borrow(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl PartialEq for Results {
+    fn eq(&self, other: &Results) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl Eq for Results {}
+
+/// Hashes like the slice of results, see [`Borrow`].
+impl Hash for Results {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_slice().hash(state);
+    }
+}
+
+impl PartialOrd for Results {
+    fn partial_cmp(&self, other: &Results) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Results {
+    fn cmp(&self, other: &Results) -> Ordering {
+        self.as_slice().cmp(other.as_slice())
+    }
+}
+
+impl fmt::Debug for Results {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_slice().fmt(f)
+    }
+}
+
+impl Extend<u8> for Results {
+    fn extend<I: IntoIterator<Item = u8>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|result| self.push(result));
+    }
+}
+
+impl FromIterator<u8> for Results {
+    fn from_iter<I: IntoIterator<Item = u8>>(iter: I) -> Results {
+        let mut results = Results::new();
+        results.extend(iter);
+        results
+    }
+}
+
+impl From<&[u8]> for Results {
+    fn from(results: &[u8]) -> Results {
+        results.iter().copied().collect()
+    }
+}
+
+impl<const N: usize> From<[u8; N]> for Results {
+    fn from(results: [u8; N]) -> Results {
+        results.into_iter().collect()
+    }
+}
 
 /// A representation of a test, consisting of its test number as well as its
 /// result.
 pub type Test = (usize, u8);
 
+/// The outcome of a test: whether the verifier yielded a checkmark.
+///
+/// ```
+/// use tm_solver::construct_tree::Answer;
+///
+/// assert_eq!(Answer::from(true), Answer::Pass);
+/// assert!(!bool::from(Answer::Fail));
+/// assert_eq!(!Answer::Pass, Answer::Fail);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "bool", into = "bool")]
+pub enum Answer {
+    /// the verifier yielded a checkmark.
+    Pass,
+    /// the verifier yielded a cross.
+    Fail,
+}
+
+impl Answer {
+
+    /// Whether the test was passed.
+    pub fn passed(self) -> bool {
+        self == Answer::Pass
+    }
+}
+
+impl From<bool> for Answer {
+    fn from(passed: bool) -> Self {
+        match passed {
+            true => Answer::Pass,
+            false => Answer::Fail,
+        }
+    }
+}
+
+impl From<Answer> for bool {
+    fn from(answer: Answer) -> Self {
+        answer.passed()
+    }
+}
+
+impl core::ops::Not for Answer {
+    type Output = Answer;
+
+    fn not(self) -> Answer {
+        match self {
+            Answer::Pass => Answer::Fail,
+            Answer::Fail => Answer::Pass,
+        }
+    }
+}
+
+/// What trees need to know about their tests. The search only ever creates
+/// trees of [`Test`]s, but trees can hold other tests as well, e. g. with
+/// named verifiers:
+///
+/// ```
+/// use tm_solver::construct_tree::{Answer, BinaryTree, Branch, TreeTest};
+///
+/// #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// struct Named(char);
+///
+/// impl TreeTest for Named {
+///     fn verifier(&self) -> usize {
+///         (self.0 as u8 - b'A') as usize
+///     }
+/// }
+///
+/// let tree = BinaryTree::Branch(Box::new(Branch {
+///     test: Named('B'),
+///     correct: BinaryTree::Leaf("241"),
+///     incorrect: BinaryTree::Leaf("352"),
+///     code: None,
+/// }));
+/// assert_eq!(tree.leaf_count(), 2);
+/// assert_eq!(tree.paths().last().unwrap().0, vec![(Named('B'), Answer::Fail)]);
+/// ```
+pub trait TreeTest: Copy + Eq + core::hash::Hash + core::fmt::Debug {
+
+    /// The index of the verifier the test is performed on.
+    fn verifier(&self) -> usize;
+}
+
+impl TreeTest for Test {
+    fn verifier(&self) -> usize {
+        self.0
+    }
+}
+
+/// Counters describing the work done by the search so far, for diagnostics.
+#[derive(Debug, Clone, Copy)]
+pub struct SearchStats {
+    /// how many (sub)trees the search tried to construct.
+    pub calls: usize,
+    /// how many candidate tests were considered.
+    pub tests: usize,
+    /// how many candidate tests were skipped because they can't beat the
+    /// best tree found so far.
+    pub pruned: usize,
+    /// how many combinations of subtrees were rejected because their round
+    /// has no common code.
+    pub rejected: usize,
+    /// roughly how many bytes the candidate trees took. They are only freed
+    /// once their search is done, so this is about the peak of the search.
+    pub memory: usize,
+}
+
+static CALLS: AtomicUsize = AtomicUsize::new(0);
+static TESTS: AtomicUsize = AtomicUsize::new(0);
+static PRUNED: AtomicUsize = AtomicUsize::new(0);
+static REJECTED: AtomicUsize = AtomicUsize::new(0);
+static MEMORY: AtomicUsize = AtomicUsize::new(0);
+
+/// The work done by all searches so far.
+pub fn search_stats() -> SearchStats {
+    SearchStats {
+        calls: CALLS.load(AtomicOrdering::Relaxed),
+        tests: TESTS.load(AtomicOrdering::Relaxed),
+        pruned: PRUNED.load(AtomicOrdering::Relaxed),
+        rejected: REJECTED.load(AtomicOrdering::Relaxed),
+        memory: MEMORY.load(AtomicOrdering::Relaxed),
+    }
+}
+
+/// Settings that control how a solution tree is constructed.
+pub struct TreeSettings<'a, T> {
+    /// an optional map from test results to all solutions yielding them. If
+    /// given, every round has to be performable with a single input from
+    /// that map, which is then stored in the round's first branch. Without
+    /// one, this constraint is skipped and no codes are assigned.
+    pub solution_map: Option<&'a HashMap<Results, Vec<T>>>,
+    /// the number of tests that have to be performed with the same input.
+    pub tests_per_round: u8,
+    /// an optional cap on the total number of tests. Trees that might need
+    /// more tests than that are rejected.
+    pub max_questions: Option<u8>,
+    /// whether to rank candidate tests by looking two tests ahead instead of
+    /// one. This is slower per node, but usually prunes the search better.
+    pub lookahead: bool,
+    /// what makes one tree better than another.
+    pub objective: Objective,
+    /// where to report how many of the root's candidate tests have been
+    /// tried, if anywhere.
+    pub progress: Option<&'a dyn ProgressSink>,
+    /// called with every tree that beats the best one found so far, while
+    /// the search goes on to prove that the last one is optimal, if given.
+    pub improved: Option<Improved<'a, T>>,
+    /// which tests at the top to try first, learned from earlier searches,
+    /// if known. Ties, and tests the order knows nothing about, are left to
+    /// the heuristics.
+    pub order: Option<&'a TestOrder>,
+    /// roughly how many bytes the candidate trees may take, see
+    /// [`SearchStats::memory`]. Beyond that, the search only keeps the best
+    /// tree for every subtree, like at the top. That keeps the memory in
+    /// check on huge puzzles, but might miss the optimal tree, since a worse
+    /// subtree might be the only one that fits its round. Subtrees left
+    /// without any tree that way are searched again without the cap, so a
+    /// tree is still found whenever there is one.
+    pub max_memory: Option<usize>,
+}
+
+/// Receives the trees that beat the best one found so far, see
+/// [`TreeSettings::improved`].
+pub type Improved<'a, T> = &'a (dyn Fn(&BinaryTree<T>) + Sync);
+
+/// What an optimal tree minimizes first. Ties are broken by the other
+/// measure.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Objective {
+    /// the number of tests needed in the worst case.
+    #[default]
+    WorstCase,
+    /// the number of tests needed on average, i. e. the
+    /// [`total_depth`](BinaryTree::total_depth). The search can't prune
+    /// subtrees by their depth then, so it's slower.
+    Average,
+}
+
+impl<T> Clone for TreeSettings<'_, T> {
+    fn clone(&self) -> Self { *self }
+}
+
+impl<T> Copy for TreeSettings<'_, T> {}
+
+impl<T> TreeSettings<'_, T> {
+
+    /// Settings for a pure binary search without any additional constraints.
+    pub fn new(tests_per_round: u8) -> Self {
+        TreeSettings {
+            solution_map: None,
+            tests_per_round,
+            max_questions: None,
+            lookahead: false,
+            objective: Objective::WorstCase,
+            progress: None,
+            improved: None,
+            order: None,
+            max_memory: None,
+        }
+    }
+
+    /// The same settings, but with the question budget reduced by `used`.
+    fn spend(&self, used: u8) -> Self {
+        TreeSettings {
+            max_questions: self.max_questions.map(|m| m.saturating_sub(used)),
+            // only the outermost tree reports its progress, and learns
+            // from others.
+            progress: None,
+            improved: None,
+            order: None,
+            ..*self
+        }
+    }
+}
+
 /// A binary tree used to navigate the solution space with given tests.
 /// 
 /// A tree consists of branches, which have two children, and leaves, which
@@ -28,16 +400,18 @@ pub type Test = (usize, u8);
 /// the result of that test is true or false, move on to the correct or
 /// incorrect child, respectively. Repeat until you reach a leaf, at which
 /// point you've found the correct combination.
-#[derive(Debug, Clone)]
-pub enum BinaryTree<T> {
+///
+/// The tests are [`Test`]s unless given otherwise, see [`TreeTest`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum BinaryTree<T, X = Test> {
     Leaf(T),
-    Branch(Box<Branch<T>>),
+    Branch(Box<Branch<T, X>>),
 }
 
-unsafe impl<T> Send for BinaryTree<T> {}
-unsafe impl<T> Sync for BinaryTree<T> {}
+unsafe impl<T, X> Send for BinaryTree<T, X> {}
+unsafe impl<T, X> Sync for BinaryTree<T, X> {}
 
-impl<T> BinaryTree<T> {
+impl<T, X: TreeTest> BinaryTree<T, X> {
 
     /// Return the maximum depth of the tree (leaves do not have depth).
     /// An optimal tree minimizes this value.
@@ -48,8 +422,19 @@ impl<T> BinaryTree<T> {
         }
     }
 
-    /// Return the total depth of the tree (leaves do not have depth).
-    /// An optimal tree minimizes this value.
+    /// Return the sum of the depths of all leaves, i. e. how many tests it
+    /// takes to find every solution once. Divided by the
+    /// [`leaf_count`](BinaryTree::leaf_count), this is the average number of
+    /// tests.
+    ///
+    /// ```
+    /// use tm_solver::construct_tree::{self, Results, TreeSettings};
+    ///
+    /// let entries: Vec<_> = (0..5u8).map(|n| (Results::from([n % 2, n / 2 % 2, n / 4]), n)).collect();
+    /// let tree = construct_tree::optimal_tree(&entries, TreeSettings::new(1)).unwrap();
+    /// assert_eq!(tree.max_depth(), 3);
+    /// assert_eq!(tree.total_depth(), tree.paths().map(|(path, _)| path.len()).sum::<usize>());
+    /// ```
     pub fn total_depth(&self) -> usize {
         match self {
             BinaryTree::Leaf(_) => 0,
@@ -57,19 +442,27 @@ impl<T> BinaryTree<T> {
         }
     }
 
-    /// Return the number of nodes within this tree. Pretty pointless, but I
-    /// only realized this after implementing it.
-    pub fn size(&self) -> u8 {
+    /// Return the number of nodes within this tree, i. e. branches and
+    /// leaves.
+    pub fn size(&self) -> usize {
         match self {
             BinaryTree::Leaf(_) => 1,
             BinaryTree::Branch(b) => 1 + b.correct.size() + b.incorrect.size(),
         }
     }
 
+    /// Return the number of leaves, i.e. solutions, within this tree.
+    pub fn leaf_count(&self) -> usize {
+        match self {
+            BinaryTree::Leaf(_) => 1,
+            BinaryTree::Branch(b) => b.correct.leaf_count() + b.incorrect.leaf_count(),
+        }
+    }
+
     /// Get the tests required to follow this tree sub_levels deep. This is
     /// important to make sure it's actually possible to perform all tests
     /// of a round using the same code.
-    pub fn get_tests(&self, sub_levels: u8) -> HashSet<Test> {
+    pub fn get_tests(&self, sub_levels: u8) -> HashSet<X> {
         match self {
             BinaryTree::Leaf(_) => HashSet::new(),
             BinaryTree::Branch(b) => b.get_tests(sub_levels),
@@ -78,24 +471,38 @@ impl<T> BinaryTree<T> {
 
 }
 
-impl<T: ToString> BinaryTree<T> {
+#[cfg(feature = "std")]
+impl<T, X: TreeTest> BinaryTree<T, X> {
 
-    /// print a visual representation of the tree.
-    pub fn print(&self, indent: u8) {
+    /// A visual representation of the tree, one line per node, connected
+    /// by box-drawing characters. Subtrees deeper than `max_depth` tests are
+    /// only summarized by their number of leaves. `leaf` formats the
+    /// solutions.
+    pub fn render(&self, max_depth: Option<u8>, leaf: &dyn Fn(&T) -> String) -> Vec<String> {
+        let mut lines = Vec::new();
+        self.render_rec("", "", 0, max_depth, leaf, &mut lines);
+        lines
+    }
+
+    /// `label` starts the line of this node, `prefix` the lines of its
+    /// children.
+    fn render_rec(&self, label: &str, prefix: &str, depth: u8, max_depth: Option<u8>,
+        leaf: &dyn Fn(&T) -> String, lines: &mut Vec<String>) {
         match self {
-            BinaryTree::Leaf(c) => print!("{}\n", c.to_string()),
+            BinaryTree::Leaf(c) => lines.push(format!("{}{}", label, leaf(c))),
+            BinaryTree::Branch(_) if max_depth.is_some_and(|m| depth >= m) => {
+                lines.push(format!("{}... ({} codes)", label, self.leaf_count()));
+            },
             BinaryTree::Branch(b) => {
-                print!("Test: {:?}\n", b.test);
-                for _ in 0..indent + 1 {
-                    print!("  ");
-                }
-                print!("\x1b[32m✓\x1b[0m: ");
-                b.correct.print(indent + 1);
-                for _ in 0..indent + 1 {
-                    print!("  ");
-                }
-                print!("\x1b[31m✗\x1b[0m: ");
-                b.incorrect.print(indent + 1);
+                lines.push(format!("{}Test: {:?}", label, b.test));
+                b.correct.render_rec(
+                    &format!("{}{} {} ", prefix, crate::style::symbol("├─", "|-"), crate::style::checkmark()),
+                    &format!("{}{}  ", prefix, crate::style::symbol("│", "|")),
+                    depth + 1, max_depth, leaf, lines);
+                b.incorrect.render_rec(
+                    &format!("{}{} {} ", prefix, crate::style::symbol("└─", "`-"), crate::style::cross()),
+                    &format!("{}   ", prefix),
+                    depth + 1, max_depth, leaf, lines);
             },
         }
     }
@@ -104,24 +511,47 @@ impl<T: ToString> BinaryTree<T> {
 
 /// A branch within the tree. See the description of BinaryTree for more
 /// details.
-#[derive(Debug, Clone)]
-pub struct Branch<T> {
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Branch<T, X = Test> {
     /// which test to perform.
-    pub test: Test,
+    pub test: X,
     /// the child tree containing all feasible solutions that pass the test.
-    pub correct: BinaryTree<T>,
+    pub correct: BinaryTree<T, X>,
     /// the child tree containing all feasible soluitons that do not pass the
     /// test.
-    pub incorrect: BinaryTree<T>,
+    pub incorrect: BinaryTree<T, X>,
     /// an optional code that can be used to perform all the tests required
     /// for this round.
     pub code: Option<T>,
 }
 
-impl<T> Branch<T> {
+impl<T, X: TreeTest> Branch<T, X> {
+
+    /// The child to move on to after the test yielded `answer`.
+    pub fn child(&self, answer: Answer) -> &BinaryTree<T, X> {
+        match answer {
+            Answer::Pass => &self.correct,
+            Answer::Fail => &self.incorrect,
+        }
+    }
+
+    /// All tests of the round starting at this branch, i. e. up to the next
+    /// branches providing a new code.
+    pub fn round_tests(&self) -> HashSet<X> {
+        let mut tests = HashSet::new();
+        tests.insert(self.test);
+        for child in [&self.correct, &self.incorrect] {
+            if let BinaryTree::Branch(b) = child {
+                if b.code.is_none() {
+                    tests.extend(b.round_tests());
+                }
+            }
+        }
+        tests
+    }
 
     /// see above
-    pub fn get_tests(&self, sub_levels: u8) -> HashSet<Test> {
+    pub fn get_tests(&self, sub_levels: u8) -> HashSet<X> {
         let mut tests = HashSet::new();
         tests.insert(self.test);
         if sub_levels > 0 {
@@ -142,52 +572,86 @@ impl<T> Branch<T> {
 
     /// see above
     pub fn total_depth(&self) -> usize {
-        1 + self.correct.total_depth() + self.incorrect.total_depth()
+        self.correct.total_depth() + self.incorrect.total_depth() + self.correct.leaf_count()
+            + self.incorrect.leaf_count()
     }
 }
 
 /// The result of performing a certain test and sorting the feasible solutions
-/// by whether they passed the test or not.
+/// by whether they passed the test or not. The solutions are given by their
+/// indices into the entries of the search, so splitting them doesn't copy
+/// their results.
 #[derive(Debug)]
-struct TestResult<T> {
+struct TestResult {
     test: Test,
-    correct: Vec<Feasible<T>>,
-    incorrect: Vec<Feasible<T>>,
+    correct: Vec<usize>,
+    incorrect: Vec<usize>,
 }
 
-impl<T: Clone> TestResult<T> {
+impl TestResult {
 
-    /// Perform a test on a set of feasible solutions and return the results.
-    fn from_test(entries: &Vec<Feasible<T>>, (i, v): Test) -> TestResult<T> {
-        let mut correct = Vec::new();
-        let mut incorrect = Vec::new();
-        for e in entries {
-            if e.0[i] == v {
-                correct.push(e.clone());
-            } else {
-                incorrect.push(e.clone());
-            }
-        }
+    /// Perform a test on the feasible solutions at `indices` and return the
+    /// results.
+    fn from_test<T>(entries: &[Feasible<T>], indices: &[usize], (i, v): Test) -> TestResult {
+        let (correct, incorrect) = indices.iter().partition(|k| entries[**k].0[i] == v);
         TestResult { test: (i, v), correct, incorrect }
     }
-}
-
-impl<T> TestResult<T> {
 
     /// A heuristic value determining how promising this test is to perform.
     /// 
     /// Under the hood, this simply tries to split the current test results as
     /// evenly as possible.
     fn estimated_value(&self) -> usize { self.correct.len().min(self.incorrect.len()) }
+
+    /// A two-level version of the heuristic above: the size of the largest
+    /// group of solutions that might remain after performing this test and
+    /// the best possible follow-up test. Unlike above, smaller is better.
+    /// 
+    /// `blocked` contains tests that may not be used as a follow-up.
+    fn lookahead_value<T>(&self, entries: &[Feasible<T>], tests: &[HashSet<u8>], blocked: &[Test]) -> usize {
+        largest_after_split(entries, &self.correct, tests, blocked)
+            .max(largest_after_split(entries, &self.incorrect, tests, blocked))
+    }
+}
+
+/// The size of the largest group of solutions at `indices` left over after
+/// performing the most even split possible with the non-blocked tests.
+fn largest_after_split<T>(entries: &[Feasible<T>], indices: &[usize], tests: &[HashSet<u8>], blocked: &[Test]) -> usize {
+    let mut best = indices.len();
+    for (i, s) in tests.iter().enumerate() {
+        if blocked.iter().any(|(j, _)| *j == i) {
+            continue;
+        }
+        for v in s {
+            let passed = indices.iter().filter(|k| entries[**k].0[i] == *v).count();
+            best = best.min(passed.max(indices.len() - passed));
+        }
+    }
+    best
+}
+
+/// A code for a round with the given tests: one that yields their results,
+/// and any of the `tests`' results for all other verifiers. None if there's
+/// no such code in the solution map.
+fn round_code<T: Clone>(solution_map: &HashMap<Results, Vec<T>>, tests: &[HashSet<u8>], round: &[Test]) -> Option<T> {
+    let mut results = tests.to_vec();
+    for (test, res) in round {
+        let mut set = HashSet::new();
+        set.insert(*res);
+        results[*test] = set;
+    }
+    get_permutations(&results).iter()
+        .find_map(|p| solution_map.get(p))
+        .map(|codes| codes[0].clone())
 }
 
-fn get_permutations(input: &Vec<HashSet<u8>>) -> Vec<Vec<u8>> {
-    let mut results = vec![Vec::new()];
-    for i in 0..input.len() {
+fn get_permutations(input: &[HashSet<u8>]) -> Vec<Results> {
+    let mut results = vec![Results::new()];
+    for set in input {
         let mut new_results = Vec::new();
         while let Some(r) = results.pop() {
-            for v in &input[i] {
-                let mut new = r.clone();
+            for v in set {
+                let mut new = r;
                 new.push(*v);
                 new_results.push(new);
             }
@@ -197,81 +661,471 @@ fn get_permutations(input: &Vec<HashSet<u8>>) -> Vec<Vec<u8>> {
     results
 }
 
-pub fn optimal_tree<T: Clone>(entries: &Vec<Feasible<T>>,
-    solution_map: &HashMap<Vec<u8>, Vec<T>>,
-    tests_per_round: u8) -> Option<BinaryTree<T>> {
+/// Construct an optimal solution tree for the given feasible solutions.
+/// Every test of the tree splits the solutions that reach it, i. e. none of
+/// its outcomes is implied by the previous ones.
+/// 
+/// Returns None if no tree satisfies the given settings.
+pub fn optimal_tree<T: Clone>(entries: &[Feasible<T>],
+    settings: TreeSettings<T>) -> Option<BinaryTree<T>> {
+    let indices: Vec<usize> = (0..entries.len()).collect();
+    let mut arena = Arena::new(settings);
+    let root = optimal_subtree(&mut arena, entries, &indices, settings, true)?;
+    Some(arena.tree(root, entries))
+}
+
+/// [`optimal_tree`] by iterative deepening: look for a tree as deep as an
+/// ideal one first, then for one test deeper and so on, up to the question
+/// budget. Every depth is a search of its own with the depth as its budget,
+/// which prunes it hard, so shallow trees are found fast, and only the
+/// memory of the current depth is taken. Puzzles whose optimal tree is much
+/// deeper than an ideal one search the shallow depths in vain, though.
+///
+/// ```
+/// use tm_solver::construct_tree::{self, Results, TreeSettings};
+///
+/// let entries: Vec<_> = (0..20u8).map(|n| (Results::from([n % 2, n % 3, n / 4 % 2, n / 8, n % 5]), n)).collect();
+/// let settings = TreeSettings::new(2);
+/// let tree = construct_tree::optimal_tree_deepening(&entries, settings).unwrap();
+/// assert_eq!(tree.max_depth(), construct_tree::optimal_tree(&entries, settings).unwrap().max_depth());
+/// ```
+pub fn optimal_tree_deepening<T: Clone>(entries: &[Feasible<T>], settings: TreeSettings<T>) -> Option<BinaryTree<T>> {
+    deepen(entries.len(), settings, |s| optimal_tree(entries, s))
+}
+
+/// Run a search for `size` entries with ever larger question budgets, see
+/// [`optimal_tree_deepening`], until it finds a tree.
+pub(crate) fn deepen<'a, T>(size: usize, settings: TreeSettings<'a, T>,
+    mut search: impl FnMut(TreeSettings<'a, T>) -> Option<BinaryTree<T>>) -> Option<BinaryTree<T>> {
+    if size == 0 {
+        return None;
+    }
+    // every test tells at least one solution apart from the others.
+    let deepest = u8::try_from(size - 1).unwrap_or(u8::MAX);
+    let deepest = settings.max_questions.map_or(deepest, |m| m.min(deepest));
+    (ideal_depth(size)..=deepest).find_map(|depth| {
+        debug!(depth, "looking for a tree of this depth");
+        search(TreeSettings { max_questions: Some(depth), ..settings })
+    })
+}
+
+/// [`optimal_tree`], with the candidate tests at the top of the tree split
+/// among `threads` threads. Each of them searches the subtrees of its tests on
+/// its own, but they share the best depth found so far to prune the search.
+/// The top of the search dominates the time it takes, so this is faster
+/// on puzzles with many verifiers.
+///
+/// ```
+/// use tm_solver::construct_tree::{self, Results, TreeSettings};
+///
+/// let entries: Vec<_> = (0..20u8).map(|n| (Results::from([n % 2, n % 3, n / 4 % 2, n / 8, n % 5]), n)).collect();
+/// let settings = TreeSettings::new(2);
+/// let tree = construct_tree::optimal_tree_parallel(&entries, settings, 4).unwrap();
+/// let sequential = construct_tree::optimal_tree(&entries, settings).unwrap();
+/// assert_eq!(tree.max_depth(), sequential.max_depth());
+/// assert_eq!(tree.leaf_count(), 20);
+/// ```
+#[cfg(feature = "std")]
+pub fn optimal_tree_parallel<T: Clone + Send + Sync>(entries: &[Feasible<T>],
+    settings: TreeSettings<T>, threads: usize) -> Option<BinaryTree<T>> {
+    let indices: Vec<usize> = (0..entries.len()).collect();
+    let (tests, optimal_depth) = prepare(entries, &indices, settings)?;
+    let shared = Shared {
+        threads: threads.max(1),
+        best_depth: AtomicU8::new(settings.max_questions.unwrap_or(u8::MAX)),
+        done: AtomicBool::new(false),
+        tried: AtomicUsize::new(0),
+        best_rank: std::sync::Mutex::new(None),
+    };
+    let (indices, tests, shared) = (&indices, &tests, &shared);
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..shared.threads).map(|index| scope.spawn(move || {
+            // the threads share the memory.
+            let mut arena = Arena::new(TreeSettings { max_memory: settings.max_memory.map(|m| m / shared.threads), ..settings });
+            let search = Search { entries, tests, optimal_depth, outermost: true, worker: Some((index, shared)) };
+            let trees = construct_trees_rec(&mut arena, &search, indices, settings, 0, None, &[]);
+            trees.last().map(|t| (rank(arena.depths(*t), settings.objective), arena.tree(*t, entries)))
+        })).collect();
+        workers.into_iter()
+            .filter_map(|w| w.join().expect("a search thread panicked"))
+            .min_by_key(|(rank, _)| *rank)
+            .map(|(_, tree)| tree)
+    })
+}
+
+/// What the threads of [`optimal_tree_parallel`] share.
+#[cfg(feature = "std")]
+struct Shared {
+    threads: usize,
+    /// the depth of the best tree found by any thread, or the question
+    /// budget. `u8::MAX` if there's neither.
+    best_depth: AtomicU8,
+    /// whether a thread found an ideal tree, so the others can stop.
+    done: AtomicBool,
+    /// how many tests at the top have been tried by all threads.
+    tried: AtomicUsize,
+    /// the rank of the best tree reported as an improvement. Threads hold the
+    /// lock while reporting, so improvements are reported in order.
+    best_rank: std::sync::Mutex<Option<(usize, usize)>>,
+}
+
+/// The test results appearing among the entries at `indices` and the depth
+/// of an ideal tree for them, see [`ideal_depth`], or None if there can't be
+/// a tree.
+fn prepare<T>(entries: &[Feasible<T>], indices: &[usize], settings: TreeSettings<T>)
+    -> Option<(Vec<HashSet<u8>>, u8)> {
 
-    if entries.is_empty() {
+    if indices.is_empty() {
         return None;
     }
 
+    // don't even try if the budget is too small.
+    if let Some(max) = settings.max_questions {
+        if ideal_depth(indices.len()) > max {
+            return None;
+        }
+    }
+
     // check which test results appear within the unique solutions.
-    let mut tests = vec![HashSet::<u8>::new(); entries[0].0.len()];
-    for i in 0..entries[0].0.len() {
-        for s in entries {
-            tests[i].insert(s.0[i]);
+    let mut tests = vec![HashSet::<u8>::new(); entries[indices[0]].0.len()];
+    for (i, t) in tests.iter_mut().enumerate() {
+        for k in indices {
+            t.insert(entries[*k].0[i]);
         }
     }
 
-    // what would be the ideal solution tree?
-    let size = entries.len();
-    let last_pot_2 = 1 << size.ilog2();
-    let deep = (size - last_pot_2) * 2;
-    let shallow = size - deep;
-    let total_size = shallow * last_pot_2 + deep * last_pot_2 * 2;
+    Some((tests, ideal_depth(indices.len())))
+}
+
+/// The maximum depth of an ideal tree for `size` solutions. n tests can
+/// distinguish at most 2^n solutions, so no tree for them is shallower, and
+/// a tree this deep is optimal in the worst case.
+fn ideal_depth(size: usize) -> u8 {
+    size.next_power_of_two().ilog2() as u8
+}
+
+/// The total depth of an ideal tree for `size` solutions, see
+/// [`BinaryTree::total_depth`]: all leaves are at most one level apart, so a
+/// tree this deep in total is optimal on average.
+fn ideal_total(size: usize) -> usize {
+    let full = size.ilog2() as usize;
+    size * full + 2 * (size - (1 << full))
+}
+
+/// [`optimal_tree`] for the entries at `indices`, with its nodes in `arena`.
+fn optimal_subtree<T: Clone>(arena: &mut Arena<T>, entries: &[Feasible<T>], indices: &[usize],
+    settings: TreeSettings<T>, outermost: bool) -> Option<NodeId> {
+    let (tests, optimal_depth) = prepare(entries, indices, settings)?;
 
     // recursively construct solution trees
-    let mut trees = construct_trees_rec(
-        entries,
-        &tests,
-        solution_map, 
+    let trees = construct_trees_rec(
+        arena,
+        &Search {
+            entries,
+            tests: &tests,
+            optimal_depth,
+            outermost,
+            #[cfg(feature = "std")]
+            worker: None,
+        },
+        indices,
+        settings,
         0,
-        None, 
-        tests_per_round, 
-        total_size,
-        &Vec::new()
+        None,
+        &[]
     );
-    if trees.is_empty() {
-        return None;
+    // the top level only keeps the best tree.
+    trees.last().copied()
+
+}
+
+/// How good a tree with the given maximum and total depth is by the given
+/// objective. Smaller is better.
+///
+/// ```
+/// use tm_solver::construct_tree::{rank, Objective};
+///
+/// assert!(rank((3, 20), Objective::WorstCase) < rank((4, 18), Objective::WorstCase));
+/// assert!(rank((3, 20), Objective::Average) > rank((4, 18), Objective::Average));
+/// ```
+pub fn rank((max, total): (u8, usize), objective: Objective) -> (usize, usize) {
+    match objective {
+        Objective::WorstCase => (max as usize, total),
+        Objective::Average => (total, max as usize),
     }
+}
+
+/// The index of a node in an [`Arena`].
+type NodeId = usize;
 
-    // order trees by quality
-    trees.sort_by(|a, b| {
-        match b.max_depth().cmp(&a.max_depth()) {
-            Ordering::Less => Ordering::Less,
-            Ordering::Equal => b.total_depth().cmp(&a.total_depth()),
-            Ordering::Greater => Ordering::Greater,
+/// A node of the candidate trees of a search, see [`Arena`].
+enum Node<T> {
+    /// the index of the solution among the entries of the search.
+    Leaf(usize),
+    Branch {
+        test: Test,
+        correct: NodeId,
+        incorrect: NodeId,
+        code: Option<T>,
+        /// the depths of the subtree, see [`BinaryTree::max_depth`] and
+        /// [`BinaryTree::total_depth`], and its number of leaves.
+        max_depth: u8,
+        total_depth: usize,
+        leaves: usize,
+        /// the tests of the subtree that might be part of the same round,
+        /// each with the depth it first appears at, see [`Arena::get_tests`].
+        tests: Vec<(Test, u8)>,
+    },
+}
+
+/// The nodes of all candidate trees of a search. Candidates refer to their
+/// subtrees by index, so combining two subtrees into a new branch neither
+/// copies them nor allocates more than a single node. Only the tree that
+/// wins is turned into a [`BinaryTree`].
+struct Arena<T> {
+    nodes: Vec<Node<T>>,
+    /// the number of tests per round. Branches only keep the tests this
+    /// close to them.
+    tests_per_round: u8,
+    /// roughly how many bytes the nodes take, and may take, see
+    /// [`TreeSettings::max_memory`].
+    memory: usize,
+    max_memory: Option<usize>,
+    /// how many candidate trees were dropped because of the memory cap.
+    dropped: usize,
+    /// the leaf of every entry, if there is one yet. Leaves never change, so
+    /// all trees share them.
+    leaves: Vec<Option<NodeId>>,
+}
+
+impl<T: Clone> Arena<T> {
+
+    fn new(settings: TreeSettings<'_, T>) -> Self {
+        Arena {
+            nodes: Vec::new(),
+            tests_per_round: settings.tests_per_round,
+            memory: 0,
+            max_memory: settings.max_memory,
+            dropped: 0,
+            leaves: Vec::new(),
         }
-    });
-    trees.pop()
+    }
 
+    /// The leaf of an entry.
+    fn leaf(&mut self, entry: usize) -> NodeId {
+        if let Some(Some(id)) = self.leaves.get(entry) {
+            return *id;
+        }
+        let id = self.push(Node::Leaf(entry));
+        if self.leaves.len() <= entry {
+            self.leaves.resize(entry + 1, None);
+        }
+        self.leaves[entry] = Some(id);
+        id
+    }
+
+    fn push(&mut self, node: Node<T>) -> NodeId {
+        let mut size = size_of::<Node<T>>();
+        if let Node::Branch { tests, .. } = &node {
+            size += tests.capacity() * size_of::<(Test, u8)>();
+        }
+        MEMORY.fetch_add(size, AtomicOrdering::Relaxed);
+        if self.max_memory.is_some_and(|max| self.memory <= max && self.memory + size > max) {
+            debug!(memory = self.memory + size, "candidate trees exceed the memory cap, only keeping the best ones");
+        }
+        self.memory += size;
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    /// Whether the nodes take more memory than they may, so the search
+    /// should only keep the best tree for every subtree.
+    fn frugal(&self) -> bool {
+        self.max_memory.is_some_and(|max| self.memory > max)
+    }
+
+    /// The maximum and total depth of a tree.
+    fn depths(&self, id: NodeId) -> (u8, usize) {
+        match &self.nodes[id] {
+            Node::Leaf(_) => (0, 0),
+            Node::Branch { max_depth, total_depth, .. } => (*max_depth, *total_depth),
+        }
+    }
+
+    /// The number of leaves of a tree.
+    fn leaves(&self, id: NodeId) -> usize {
+        match &self.nodes[id] {
+            Node::Leaf(_) => 1,
+            Node::Branch { leaves, .. } => *leaves,
+        }
+    }
+
+    /// A new branch with the given children. Its tests are collected from
+    /// theirs right away, so checking which subtrees fit the same round
+    /// doesn't have to walk them again and again.
+    fn branch(&mut self, test: Test, correct: NodeId, incorrect: NodeId, code: Option<T>) -> NodeId {
+        let (c, i) = (self.depths(correct), self.depths(incorrect));
+        let leaves = self.leaves(correct) + self.leaves(incorrect);
+        let mut tests = vec![(test, 0)];
+        for (t, depth) in self.tests(correct).iter().chain(self.tests(incorrect)) {
+            if depth + 1 >= self.tests_per_round {
+                continue;
+            }
+            match tests.iter_mut().find(|(u, _)| u == t) {
+                Some((_, d)) => *d = (*d).min(depth + 1),
+                None => tests.push((*t, depth + 1)),
+            }
+        }
+        self.push(Node::Branch {
+            test, correct, incorrect, code,
+            max_depth: 1 + c.0.max(i.0),
+            total_depth: c.1 + i.1 + leaves,
+            leaves,
+            tests,
+        })
+    }
+
+    /// The tests of a tree that might be part of the same round, with their
+    /// depths.
+    fn tests(&self, id: NodeId) -> &[(Test, u8)] {
+        match &self.nodes[id] {
+            Node::Leaf(_) => &[],
+            Node::Branch { tests, .. } => tests,
+        }
+    }
+
+    /// The tests of a tree up to `sub_levels` deep, see
+    /// [`BinaryTree::get_tests`]. Only works for fewer levels than there are
+    /// tests per round.
+    fn get_tests(&self, id: NodeId, sub_levels: u8) -> impl Iterator<Item = Test> + '_ {
+        self.tests(id).iter().filter(move |(_, depth)| *depth <= sub_levels).map(|(test, _)| *test)
+    }
+
+    /// Turn a tree into a [`BinaryTree`], with the solutions taken from the
+    /// entries of the search.
+    fn tree(&self, id: NodeId, entries: &[Feasible<T>]) -> BinaryTree<T> {
+        match &self.nodes[id] {
+            Node::Leaf(k) => BinaryTree::Leaf(entries[*k].1.clone()),
+            Node::Branch { test, correct, incorrect, code, .. } => BinaryTree::Branch(Box::new(Branch {
+                test: *test,
+                correct: self.tree(*correct, entries),
+                incorrect: self.tree(*incorrect, entries),
+                code: code.clone(),
+            })),
+        }
+    }
+}
+
+/// What all levels of a search below [`optimal_subtree`] share: the entries,
+/// which subtrees refer to by their indices, the test results appearing
+/// among them and the depth of an ideal tree for them.
+struct Search<'a, T> {
+    entries: &'a [Feasible<T>],
+    tests: &'a [HashSet<u8>],
+    optimal_depth: u8,
+    /// whether this is the search for the whole tree, rather than for a
+    /// round starting within it.
+    outermost: bool,
+    /// the index of the thread and what it shares with the others, if the
+    /// top of the search is split among threads.
+    #[cfg(feature = "std")]
+    worker: Option<(usize, &'a Shared)>,
+}
+
+impl<T> Search<'_, T> {
+
+    /// Whether the search at the top should skip the `i`th test, because
+    /// another thread takes care of it or found an ideal tree already.
+    /// Updates the depth bound with the best depth the other threads found.
+    #[cfg_attr(not(feature = "std"), allow(unused_variables))]
+    fn skip(&self, i: usize, best_depth: &mut Option<u8>) -> bool {
+        #[cfg(feature = "std")]
+        if let Some((index, shared)) = self.worker {
+            if shared.done.load(AtomicOrdering::Relaxed) || i % shared.threads != index {
+                return true;
+            }
+            let shared_depth = shared.best_depth.load(AtomicOrdering::Relaxed);
+            if shared_depth != u8::MAX && best_depth.is_none_or(|d| shared_depth < d) {
+                *best_depth = Some(shared_depth);
+            }
+        }
+        false
+    }
+
+    /// How many tests at the top have been tried before the `i`th one, by
+    /// all threads.
+    fn tried(&self, i: usize) -> usize {
+        #[cfg(feature = "std")]
+        if let Some((_, shared)) = self.worker {
+            return shared.tried.fetch_add(1, AtomicOrdering::Relaxed);
+        }
+        i
+    }
+
+    /// Tell the other threads about a new best tree at the top.
+    #[cfg_attr(not(feature = "std"), allow(unused_variables))]
+    fn found(&self, depth: Option<u8>, ideal: bool) {
+        #[cfg(feature = "std")]
+        if let Some((_, shared)) = self.worker {
+            if let Some(d) = depth {
+                shared.best_depth.fetch_min(d, AtomicOrdering::Relaxed);
+            }
+            if ideal {
+                shared.done.store(true, AtomicOrdering::Relaxed);
+            }
+        }
+    }
+
+    /// Report a tree as an improvement if it beats the best one reported so
+    /// far, by this search or any other thread.
+    fn improve(&self, best_rank: &mut Option<(usize, usize)>, rank: (usize, usize), report: impl FnOnce()) {
+        #[cfg(feature = "std")]
+        if let Some((_, shared)) = self.worker {
+            let mut best = shared.best_rank.lock().unwrap_or_else(|e| e.into_inner());
+            if best.is_none_or(|b| rank < b) {
+                *best = Some(rank);
+                report();
+            }
+            return;
+        }
+        if best_rank.is_none_or(|best| rank < best) {
+            *best_rank = Some(rank);
+            report();
+        }
+    }
 }
 
-fn construct_trees_rec<T: Clone>(entries: &Vec<Feasible<T>>,
-    tests: &Vec<HashSet<u8>>,
-    solution_map: &HashMap<Vec<u8>, Vec<T>>,
+/// Construct the candidate trees for the entries at `indices`.
+fn construct_trees_rec<T: Clone>(arena: &mut Arena<T>,
+    search: &Search<T>,
+    indices: &[usize],
+    settings: TreeSettings<T>,
     current_level: u8,
     abort_level: Option<u8>,
-    tests_per_round: u8,
-    optimal_depth: usize,
-    used_tests: &Vec<Test>) -> Vec<BinaryTree<T>> {
+    used_tests: &[Test]) -> Vec<NodeId> {
+
+    CALLS.fetch_add(1, AtomicOrdering::Relaxed);
+    let (entries, tests) = (search.entries, search.tests);
+    let dropped = arena.dropped;
 
     // identify leaves
-    if entries.len() == 1 {
-        return vec![BinaryTree::Leaf(entries[0].1.clone())];
+    if indices.len() == 1 {
+        return vec![arena.leaf(indices[0])];
     }
 
     // figure out possible tests.
-    let mut nodes: Vec<TestResult<T>> = Vec::new();
+    let mut nodes: Vec<TestResult> = Vec::new();
     tests.iter().enumerate().for_each(|(i, s)| {
         s.iter().for_each(|v| {
             let test = (i, *v);
             // ignore tests if the same test has been used in a previous attempt
             // this round
-            if used_tests.iter().find(|(j, _)| *j == i).is_some() {
+            if used_tests.iter().any(|(j, _)| *j == i) {
                 return;
             }
-            let res = TestResult::from_test(entries, test);
+            // a test all remaining solutions pass, or all fail, would just
+            // waste a question, so no tree ever asks one.
+            let res = TestResult::from_test(entries, indices, test);
             if res.correct.is_empty() || res.incorrect.is_empty() {
                 return;
             }
@@ -279,131 +1133,227 @@ fn construct_trees_rec<T: Clone>(entries: &Vec<Feasible<T>>,
         });
     });
 
+    TESTS.fetch_add(nodes.len(), AtomicOrdering::Relaxed);
+
     // heuristic: the more information we are guaranteed to get from a test,
     // the more promising it is.
     nodes.sort_by(|a, b| {
         b.estimated_value().cmp(&a.estimated_value())
     });
 
+    // if requested, look one test further ahead. The sort is stable, so the
+    // one-level heuristic still breaks ties.
+    let tests_per_round = settings.tests_per_round;
+    let round_ends = current_level % tests_per_round == tests_per_round - 1;
+    if settings.lookahead {
+        nodes.sort_by_cached_key(|node| {
+            let mut blocked = Vec::new();
+            if !round_ends {
+                blocked.extend_from_slice(used_tests);
+                blocked.push(node.test);
+            }
+            node.lookahead_value(entries, tests, &blocked)
+        });
+    }
+
+    // tests that led to optimal trees before come first.
+    if let (0, Some(order)) = (current_level, settings.order) {
+        nodes.sort_by_key(|node| core::cmp::Reverse(order.score(node.test)));
+    }
+
     // go through all possible tests and see what trees they yield.
     //let mut best_depth = None;
     let mut solutions = Vec::new();
-    let mut best_depth = None;
-    for node in nodes {
+    // the question budget acts as an upper bound on the depth right away.
+    let mut best_depth = match current_level == 0 {
+        true => settings.max_questions,
+        false => None,
+    };
+    // the best tree reported as an improvement so far.
+    let mut best_rank = None;
+    // the rank of the best tree so far, which is the only one kept at the
+    // top, or everywhere if memory is short. Below, worse trees might be the
+    // only ones that fit their round.
+    let mut incumbent = None;
+    // the code of every round checked so far, by its sorted tests. Rounds
+    // only start at the top, and different first tests often lead to the
+    // same round, so this saves most of the lookups.
+    let mut round_codes: HashMap<Vec<Test>, Option<T>> = HashMap::new();
+    let node_count = nodes.len();
+    for (i, node) in nodes.into_iter().enumerate() {
+        if current_level == 0 {
+            if search.skip(i, &mut best_depth) {
+                continue;
+            }
+            if let Some(progress) = settings.progress {
+                progress.progress((search.tried(i) * 100 / node_count) as u8);
+            }
+        }
 
         // if we are in the middle of a round, make sure to mark
         // used tests for the next level.
-        let next_splits = match current_level % tests_per_round == tests_per_round - 1 {
+        let next_splits = match round_ends {
             true => Vec::new(),
             false => {
-                let mut v = used_tests.clone();
-                v.push(node.test.clone());
+                let mut v = used_tests.to_vec();
+                v.push(node.test);
                 v
             },
         };
 
         // within n levels, we can distinguish up to 2^n different solutions,
-        // so we can abort if either solution is longer than that.
+        // so we can abort if either solution is longer than that. Below the
+        // top, only the bound of the top counts: a deeper subtree might be
+        // the only one that fits its round.
         let abort = match current_level == 0 {
             true => best_depth,
             false => abort_level,
         };
         if let Some(a) = abort {
-            let max_splits = 1 << (a - 1 - current_level);
+            let max_splits = 1usize.checked_shl((a - 1 - current_level) as u32)
+                .unwrap_or(usize::MAX);
             if node.correct.len() > max_splits || node.incorrect.len() > max_splits {
+                trace!(level = current_level, test = ?node.test, correct = node.correct.len(),
+                    incorrect = node.incorrect.len(), max_splits, "pruned test by the depth bound");
+                PRUNED.fetch_add(1, AtomicOrdering::Relaxed);
                 continue;
             }
         }
 
-        // construct possible correct and incorrect subtrees
-        let correct_trees = match current_level % tests_per_round == tests_per_round - 1 {
+        // construct possible correct subtrees first: without any, the
+        // incorrect ones aren't needed.
+        let correct_trees = match round_ends {
             false => construct_trees_rec(
+                arena,
+                search,
                 &node.correct,
-                &tests,
-                solution_map,
+                settings,
                 current_level + 1,
                 abort,
-                tests_per_round,
-                optimal_depth,
                 &next_splits),
-            true => match optimal_tree(&node.correct, solution_map, tests_per_round) {
+            true => match optimal_subtree(arena, entries, &node.correct, settings.spend(current_level + 1), false) {
                 Some(r) => vec![r],
                 None => Vec::new(),
             },
         };
-        let incorrect_trees = match current_level % tests_per_round == tests_per_round - 1 {
+        if correct_trees.is_empty() {
+            trace!(level = current_level, test = ?node.test, "skipped test without correct subtrees");
+            continue;
+        }
+        let incorrect_trees = match round_ends {
             false => construct_trees_rec(
+                arena,
+                search,
                 &node.incorrect,
-                &tests,
-                solution_map,
+                settings,
                 current_level + 1,
                 abort,
-                tests_per_round,
-                optimal_depth,
                 &next_splits),
-            true => match optimal_tree(&node.incorrect, solution_map, tests_per_round) {
+            true => match optimal_subtree(arena, entries, &node.incorrect, settings.spend(current_level + 1), false) {
                 Some(r) => vec![r],
                 None => Vec::new(),
             },
         };
 
-        // check the validity of each combination
-        let sub_levels = tests_per_round - (current_level % tests_per_round) - 1;
+        // check the validity of each combination, the depth first, since
+        // it's the cheapest check. The best depth only ever decreases, so a
+        // correct subtree too deep for it can be skipped with all of its
+        // combinations. The tests of the subtrees only have to agree as far
+        // as they still belong to this round, if they do at all.
+        let sub_levels = (tests_per_round - (current_level % tests_per_round) - 1).checked_sub(1);
         for correct_tree in &correct_trees {
+            let correct_depth = arena.depths(*correct_tree).0;
+            if best_depth.is_some_and(|d| d <= correct_depth) {
+                continue;
+            }
             'outer: for incorrect_tree in &incorrect_trees {
-                for (test_c, res_c) in correct_tree.get_tests(sub_levels) {
-                    for (test_i, res_i) in incorrect_tree.get_tests(sub_levels) {
+                let max_depth = 1 + correct_depth.max(arena.depths(*incorrect_tree).0);
+                if best_depth.is_some_and(|d| d < max_depth) {
+                    continue;
+                }
+                for (test_c, res_c) in sub_levels.into_iter().flat_map(|s| arena.get_tests(*correct_tree, s)) {
+                    for (test_i, res_i) in sub_levels.into_iter().flat_map(|s| arena.get_tests(*incorrect_tree, s)) {
                         if test_c == test_i && res_c != res_i {
+                            trace!(level = current_level, test = ?node.test, conflict = ?test_c,
+                                "rejected subtrees asking the same verifier for different results");
+                            REJECTED.fetch_add(1, AtomicOrdering::Relaxed);
                             continue 'outer;
                         }
                     }
                 }
-                let mut branch = Branch {
-                    test: node.test,
-                    correct: correct_tree.clone(),
-                    incorrect: incorrect_tree.clone(),
-                    code: None,
-                };
-                if let Some(d) = best_depth {
-                    if d < branch.max_depth() {
-                        continue;
+                let mut code = None;
+                if let (0, Some(solution_map)) = (current_level % tests_per_round, settings.solution_map) {
+                    let mut round = HashSet::new();
+                    round.insert(node.test);
+                    if tests_per_round > 1 {
+                        round.extend(arena.get_tests(*correct_tree, tests_per_round - 2));
+                        round.extend(arena.get_tests(*incorrect_tree, tests_per_round - 2));
                     }
+                    let mut round: Vec<Test> = round.into_iter().collect();
+                    round.sort_unstable();
+                    let found = round_codes.entry(round)
+                        .or_insert_with_key(|round| round_code(solution_map, tests, round));
+                    let Some(found) = found else {
+                        trace!(level = current_level, test = ?node.test, "rejected round without a common code");
+                        REJECTED.fetch_add(1, AtomicOrdering::Relaxed);
+                        continue 'outer;
+                    };
+                    code = Some(found.clone());
                 }
-                if current_level % tests_per_round == 0 {
-                    let mut results = tests.clone();
-                    for (test, res) in branch.get_tests(tests_per_round - 1) {
-                        let mut set = HashSet::new();
-                        set.insert(res);
-                        results[test] = set;
-                    }
-                    let permutations = get_permutations(&results);
-                    //println!("{:?}", permutations);
-                    let mut okay = false;
-                    for p in &permutations {
-                        if let Some(codes) = solution_map.get(p) {
-                            branch.code = Some(codes[0].clone());
-                            okay = true;
-                            break;
-                        }
+                let tree = arena.branch(node.test, *correct_tree, *incorrect_tree, code);
+                // a deeper tree might still be better on average, so only
+                // prune by the depth in the worst case. Below the top, a
+                // deeper tree might be the only one that fits its round.
+                if current_level == 0 && settings.objective == Objective::WorstCase {
+                    best_depth = Some(max_depth);
+                }
+                if let (0, Some(improved)) = (current_level, settings.improved) {
+                    let rank = rank(arena.depths(tree), settings.objective);
+                    search.improve(&mut best_rank, rank, || improved(&arena.tree(tree, entries)));
+                }
+                if current_level == 0 || arena.frugal() {
+                    let rank = rank(arena.depths(tree), settings.objective);
+                    let kept = incumbent.is_none_or(|best| rank <= best);
+                    if current_level > 0 {
+                        arena.dropped += if kept { solutions.len() } else { 1 };
                     }
-                    if !okay {
-                        continue 'outer;
+                    if kept {
+                        incumbent = Some(rank);
+                        solutions = vec![tree];
                     }
+                } else {
+                    solutions.push(tree);
                 }
-                let tree = BinaryTree::Branch(Box::new(branch));
-                best_depth = Some(tree.max_depth());
-                let total_depth = tree.total_depth();
-                solutions.push(tree);
 
                 // let's be greedy: if we've found an optimal tree, we don't
-                // have to keep looking for more.
-                if current_level == 0 && total_depth == optimal_depth {
-                    return solutions;
+                // have to keep looking for more. Only the outermost search
+                // may stop, though: the tree of a round starting below might
+                // not fit the round above it, while another one as good
+                // would have.
+                if current_level == 0 && search.outermost {
+                    let ideal = match settings.objective {
+                        Objective::WorstCase => max_depth == search.optimal_depth,
+                        Objective::Average => arena.depths(tree).1 == ideal_total(indices.len()),
+                    };
+                    search.found(best_depth, ideal);
+                    if ideal {
+                        return solutions;
+                    }
                 }
             }
         }
     }
 
-    // return all possible trees
+    // the trees dropped for the memory cap might have been the only ones to
+    // fit their rounds, so search again without it.
+    if solutions.is_empty() && arena.dropped > dropped {
+        debug!(level = current_level, entries = indices.len(), "no tree left under the memory cap, searching without it");
+        let max_memory = arena.max_memory.take();
+        let solutions = construct_trees_rec(arena, search, indices, settings, current_level, abort_level, used_tests);
+        arena.max_memory = max_memory;
+        return solutions;
+    }
+
+    // return all possible trees, or the best one at the top.
     solutions
 }
\ No newline at end of file
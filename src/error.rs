@@ -0,0 +1,65 @@
+//! Errors that can occur while setting up or solving a puzzle.
+
+use alloc::string::String;
+
+use thiserror::Error;
+
+use crate::rules::RULES;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum TmSolverError {
+    /// a puzzle needs 4 to 6 criteria cards.
+    #[error("A puzzle needs 4 to 6 criteria cards, but {0} {verb} given. Please input one card number per verifier.",
+        verb = if *.0 == 1 { "was" } else { "were" })]
+    RuleCount(usize),
+    /// a criteria card number that doesn't exist.
+    #[error("{0} is not a valid criteria card: use 1 to {max}", max = RULES.len())]
+    UnknownRule(usize),
+    /// every verifier needs a different criteria card.
+    #[error("Criteria card {0} is used more than once. Every verifier needs a different card.")]
+    DuplicateRule(usize),
+    /// every verifier needs a different letter.
+    #[error("Letter {0} is assigned to more than one criteria card.")]
+    DuplicateVerifier(char),
+    /// a known test refers to a verifier the puzzle doesn't have.
+    #[error("There is no verifier {0} in this puzzle.")]
+    UnknownVerifier(char),
+    /// a code that can't be parsed.
+    #[error("{0} is not a valid code: use three digits from 1 to 5, e. g. 241, or one digit per color, e. g. b2 y4 p1")]
+    InvalidCode(String),
+    /// a puzzle code in the official notation that can't be parsed.
+    #[error("{0} is not a valid puzzle code: use the notation of the book or app, e. g. #B45 4FJ")]
+    InvalidHash(String),
+    /// a puzzle code in the official notation that isn't one of the
+    /// solver's own, see [`PuzzleHash::decode`](crate::hash::PuzzleHash::decode).
+    #[error("{0} is not a puzzle code of the solver. Codes from the book or app refer to the official puzzle database, so the solver can't tell their criteria cards: input the card numbers shown in the app instead, e. g. A=4 B=9 C=11 D=14, or download the puzzle with fetch.")]
+    UnknownHash(String),
+    /// no code can be identified by the criteria cards.
+    #[error("This puzzle does not appear to be solvable. Please double-check your inputs.")]
+    Unsolvable,
+    /// the known tests rule out every code.
+    #[error("Your known test results don't match any possible code. Please double-check them.")]
+    InconsistentKnown,
+    /// no solution tree satisfies the settings, e. g. the question budget.
+    #[error("Could not construct a solution tree for this puzzle.")]
+    NoTree,
+    /// a round without tests, see
+    /// [`SolverBuilder::tests_per_round`](crate::solver::SolverBuilder::tests_per_round).
+    #[error("A round needs at least one test per code.")]
+    EmptyRound,
+    /// a file that can't be read, see the `format` module.
+    #[error("This file can't be read: {0}")]
+    InvalidFile(String),
+    /// a file with other content than expected, e. g. a strategy instead of
+    /// a saved game.
+    #[error("Expected a {expected} file, but this is a {found} file.")]
+    WrongKind { expected: &'static str, found: String },
+    /// a file written by a newer version of the solver, in a way this
+    /// version can't read.
+    #[error("This file was written by a newer version of the solver (format version {0}). Please update the solver to read it.")]
+    NewerFormat(u32),
+    /// a message that can't be turned into the solver's types, see the
+    /// `proto` module.
+    #[error("This message can't be used: {0}")]
+    InvalidMessage(String),
+}
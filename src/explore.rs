@@ -0,0 +1,153 @@
+//! Browsing a puzzle's solution tree without playing a game.
+//!
+//! The user follows the tree by answering its questions either way, goes
+//! back up or jumps straight to one of the solutions below.
+
+use tm_solver::construct_tree::{Answer, BinaryTree};
+use tm_solver::rules::{Code, RULE_DESCRIPTIONS};
+use tm_solver::session::Answered;
+use tm_solver::style;
+use crate::frontend::{Dialog, Effect, Effects};
+use crate::{round_code, split, Prepared};
+
+/// A walk through the solution tree of a puzzle.
+pub struct Explorer<'a> {
+    prepared: &'a Prepared,
+    /// the branches passed on the way to the current node.
+    history: Vec<Answered<'a>>,
+    tree: &'a BinaryTree<Code>,
+    /// how deep to print the tree, and how many lines at a time.
+    depth: Option<u8>,
+    page_size: usize,
+    finished: bool,
+}
+
+impl<'a> Explorer<'a> {
+
+    /// Start at the root of the puzzle's tree.
+    pub fn new(prepared: &'a Prepared, depth: Option<u8>, page_size: usize) -> Explorer<'a> {
+        Explorer { prepared, history: Vec::new(), tree: &prepared.tree, depth, page_size, finished: false }
+    }
+
+    /// Describe the current node.
+    fn show_node(&self, effects: &mut Effects) {
+        let letters = &self.prepared.letters;
+        effects.show("------");
+        let answers: Vec<Answer> = self.history.iter().map(|(_, _, a)| *a).collect();
+        effects.show(format!("Node {}, path: {}", self.prepared.tree.node_id(&answers).unwrap(),
+            format_path(&self.history, letters)));
+        match self.tree {
+            BinaryTree::Leaf(c) => effects.show(format!("Solution: {}", c.colored())),
+            BinaryTree::Branch(b) => {
+                match (&b.code, round_code(&self.history)) {
+                    (Some(code), _) => effects.show(format!("New round, use {}", code.colored())),
+                    (None, Some(code)) => effects.show(format!("Same round, keep {}", code.colored())),
+                    (None, None) => {},
+                }
+                let rule = self.prepared.rules[b.test.0];
+                effects.show(format!("Test {} (card {}: {})", letters[b.test.0], rule + 1, RULE_DESCRIPTIONS[rule]));
+                effects.show(split(b));
+            },
+        }
+    }
+
+    /// Follow a command. Returns whether it moved to another node.
+    fn follow(&mut self, input: &str, effects: &mut Effects) -> bool {
+        let tree = self.tree;
+        let mut words = input.split_whitespace();
+        match (words.next(), tree) {
+            (Some("y"), BinaryTree::Branch(b)) => {
+                self.history.push((tree, b.test, Answer::Pass));
+                self.tree = &b.correct;
+            },
+            (Some("n"), BinaryTree::Branch(b)) => {
+                self.history.push((tree, b.test, Answer::Fail));
+                self.tree = &b.incorrect;
+            },
+            (Some("y" | "n"), BinaryTree::Leaf(_)) => {
+                effects.show("This is a solution, there are no more tests.");
+                return false;
+            },
+            (Some("u"), _) => match self.history.pop() {
+                Some((previous, _, _)) => self.tree = previous,
+                None => {
+                    effects.show("Already at the top.");
+                    return false;
+                },
+            },
+            (Some("t"), _) => {
+                self.history.clear();
+                self.tree = &self.prepared.tree;
+            },
+            (Some("l"), _) => {
+                // the answers leading to each code from here.
+                let found: Vec<(Vec<Answer>, &Code)> = tree.paths()
+                    .map(|(path, code)| (path.into_iter().map(|(_, answer)| answer).collect(), code))
+                    .collect();
+                let Some(n) = words.next() else {
+                    for (i, (_, code)) in found.iter().enumerate() {
+                        effects.show(format!("  {}. {}", i + 1, code.colored()));
+                    }
+                    return false;
+                };
+                let Some((path, _)) = n.parse::<usize>().ok()
+                    .and_then(|n| n.checked_sub(1))
+                    .and_then(|n| found.get(n)) else {
+                    effects.show(format!("Please input a number from 1 to {}.", found.len()));
+                    return false;
+                };
+                for answer in path {
+                    let BinaryTree::Branch(b) = self.tree else {
+                        break;
+                    };
+                    self.history.push((self.tree, b.test, *answer));
+                    self.tree = b.child(*answer);
+                }
+            },
+            (Some("p"), _) => {
+                let lines = tree.render(self.depth, &|c| c.colored().to_string());
+                effects.push(Effect::Page(lines, self.page_size));
+                return false;
+            },
+            (Some("q"), _) => {
+                self.finished = true;
+                return false;
+            },
+            _ => {
+                effects.show("Unknown command.");
+                return false;
+            },
+        }
+        true
+    }
+}
+
+impl Dialog for Explorer<'_> {
+    fn start(&mut self, effects: &mut Effects) {
+        effects.show(format!("Commands: y/n to follow {}/{}, u to go up, t to go to the top, l to list the leaves below,",
+            style::checkmark(), style::cross()));
+        effects.show("l N to jump to leaf N, p to print the tree below, q to quit.");
+        self.show_node(effects);
+    }
+
+    fn input(&mut self, input: &str, effects: &mut Effects) {
+        if self.follow(input, effects) {
+            self.show_node(effects);
+        }
+    }
+
+    fn finished(&self) -> bool {
+        self.finished
+    }
+}
+
+/// Format the tests passed on the way to a node, e. g. "A ✓, B ✗".
+fn format_path(history: &[Answered], letters: &[char]) -> String {
+    if history.is_empty() {
+        return String::from("(start)");
+    }
+    history.iter()
+        .map(|(_, t, a)| format!("{} {}", letters[t.0], style::mark(*a)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,281 @@
+//! Export of solution trees for other programs, e. g. web front-ends or bots.
+//!
+//! [`strategy`] turns a solution tree into a self-contained description of
+//! the strategy, which serializes to JSON like this:
+//!
+//! ```text
+//! {"cards":[{"letter":"A","card":4,"description":"..."}, ...],
+//!  "tree":{"type":"test","id":0,"verifier":"D","card":14,"category":0,
+//!          "code":"241","new_round":true,"leaves":8,
+//!          "children":{"passed":{...},"failed":{"type":"solution","id":14,"code":"352"}}}}
+//! ```
+//!
+//! Unlike the serialized [`BinaryTree`], every test names its verifier and
+//! the code to use for it, so the tree can be followed without knowing about
+//! rounds. It also doesn't change along with the solver's internals, so
+//! strategies are stored as versioned files in this form, see
+//! [`format`](crate::format), and can be turned back into a tree with
+//! [`Strategy::to_tree`].
+//!
+//! [`Strategy::to_markdown`] prints the same strategy round by round instead,
+//! for people rather than programs, and [`code_table`] lists the categories
+//! of every code as CSV for spreadsheets.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+
+use crate::construct_tree::{BinaryTree, Branch, Feasible, Results};
+use crate::error::TmSolverError;
+use crate::format::{self, Versioned};
+use crate::rules::{Code, RULES, RULE_DESCRIPTIONS};
+use crate::space::CodeSpace;
+use crate::style;
+
+/// A criteria card of the exported puzzle.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Card {
+    /// the letter of the verifier it belongs to.
+    pub letter: char,
+    /// the number of the card.
+    pub card: usize,
+    pub description: String,
+}
+
+/// A node of the exported solution tree.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Node {
+    /// a test to perform.
+    Test {
+        /// the id of the node, see the [`traverse`](crate::traverse) module.
+        id: usize,
+        verifier: char,
+        /// the number of the verifier's criteria card.
+        card: usize,
+        /// the category of the card the test checks for.
+        category: u8,
+        /// the code to test, e. g. "241".
+        code: String,
+        /// whether this test starts a new round, i. e. a new code.
+        new_round: bool,
+        /// the number of solutions below this node.
+        leaves: usize,
+        children: Children,
+    },
+    /// a leaf: the code has been found.
+    Solution {
+        id: usize,
+        code: String,
+    },
+}
+
+/// The nodes to continue with after a test.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Children {
+    /// if the verifier yields a checkmark.
+    pub passed: Box<Node>,
+    /// if the verifier yields a cross.
+    pub failed: Box<Node>,
+}
+
+/// A puzzle and its solution tree, ready to be serialized.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Strategy {
+    pub cards: Vec<Card>,
+    pub tree: Node,
+}
+
+impl Versioned for Strategy {
+    const KIND: &'static str = "strategy";
+}
+
+impl Strategy {
+
+    /// The strategy as a single line of JSON, with the header of the
+    /// [`format`] module.
+    pub fn to_json(&self) -> String {
+        format::to_string(self)
+    }
+
+    /// Read a strategy written by [`to_json`](Strategy::to_json), by this or
+    /// any other version of the solver.
+    pub fn from_json(input: &str) -> Result<Strategy, TmSolverError> {
+        format::from_str(input)
+    }
+
+    /// The (zero-based) rule of each verifier.
+    pub fn rules(&self) -> Vec<usize> {
+        self.cards.iter().map(|c| c.card.saturating_sub(1)).collect()
+    }
+
+    /// The solution tree again, with the verifiers in the order of the
+    /// cards.
+    pub fn to_tree(&self) -> Result<BinaryTree<Code>, TmSolverError> {
+        let letters: Vec<char> = self.cards.iter().map(|c| c.letter).collect();
+        tree(&letters, &self.tree)
+    }
+
+    /// The strategy as a Markdown document: a table of the criteria cards,
+    /// followed by one section per round with the code to set up and the
+    /// questions to ask depending on the previous answers.
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::from("# Strategy\n\n| Verifier | Card | Criterion |\n|---|---|---|\n");
+        for c in &self.cards {
+            let _ = writeln!(out, "| {} | {} | {} |", c.letter, c.card, c.description);
+        }
+        if let Node::Solution { code, .. } = &self.tree {
+            let _ = writeln!(out, "\nThe code is **{}**, no tests needed.", code);
+            return out;
+        }
+        // rounds are numbered in the order they are first referenced.
+        let mut rounds = VecDeque::from([&self.tree]);
+        let mut number = 0;
+        while let Some(start) = rounds.pop_front() {
+            number += 1;
+            let Node::Test { code, leaves, .. } = start else {
+                continue;
+            };
+            let _ = writeln!(out, "\n## Round {}\n\nSet up code **{}** ({} codes left).\n", number, code, leaves);
+            let _ = writeln!(out, "- {}", question(start));
+            round_markdown(start, 1, number, &mut rounds, &mut out);
+        }
+        out
+    }
+}
+
+/// Describe the question asked at a test node.
+fn question(node: &Node) -> String {
+    match node {
+        Node::Test { verifier, card, .. } => format!("Ask verifier **{}** (card {}).", verifier, card),
+        Node::Solution { code, .. } => format!("The code is **{}**.", code),
+    }
+}
+
+/// Write the follow-ups of a test node within its round, indented by
+/// `depth` levels. Tests starting a new round are added to `rounds` and
+/// referred to by their number instead.
+fn round_markdown<'a>(node: &'a Node, depth: usize, round: usize, rounds: &mut VecDeque<&'a Node>, out: &mut String) {
+    let Node::Test { children, .. } = node else {
+        return;
+    };
+    let indent = "  ".repeat(depth);
+    // in ASCII mode, + and - would start nested lists, so use words instead.
+    for (symbol, child) in [(style::symbol("✓", "yes"), &children.passed), (style::symbol("✗", "no"), &children.failed)] {
+        match child.as_ref() {
+            Node::Test { new_round: true, .. } => {
+                rounds.push_back(child);
+                let _ = writeln!(out, "{}- {}: Continue with round {}.", indent, symbol, round + rounds.len());
+            },
+            _ => {
+                let _ = writeln!(out, "{}- {}: {}", indent, symbol, question(child));
+                round_markdown(child, depth + 1, round, rounds, out);
+            },
+        }
+    }
+}
+
+/// Prepare a solution tree for export. `letters` and `rules` are the letter
+/// and (zero-based) rule of each verifier.
+///
+/// ```
+/// use tm_solver::export;
+/// use tm_solver::solver::Solver;
+///
+/// let strategy = Solver::builder().rules(&[4, 9, 11, 14]).solve()?;
+/// let exported = export::strategy(&['A', 'B', 'C', 'D'], &strategy.rules, &strategy.tree);
+/// assert!(exported.to_json().contains(r#""cards":[{"letter":"A","card":4,"#));
+/// # Ok::<(), tm_solver::error::TmSolverError>(())
+/// ```
+pub fn strategy(letters: &[char], rules: &[usize], tree: &BinaryTree<Code>) -> Strategy {
+    Strategy {
+        cards: letters.iter().zip(rules)
+            .map(|(l, r)| Card { letter: *l, card: r + 1, description: RULE_DESCRIPTIONS[*r].to_string() })
+            .collect(),
+        tree: node(letters, rules, tree, 0, None),
+    }
+}
+
+/// Convert an exported (sub)tree back, given the letter of each verifier.
+fn tree(letters: &[char], node: &Node) -> Result<BinaryTree<Code>, TmSolverError> {
+    match node {
+        Node::Solution { code, .. } => Ok(BinaryTree::Leaf(code.parse()?)),
+        Node::Test { verifier, category, code, new_round, children, .. } => {
+            let i = letters.iter().position(|l| l == verifier).ok_or(TmSolverError::UnknownVerifier(*verifier))?;
+            Ok(BinaryTree::Branch(Box::new(Branch {
+                test: (i, *category),
+                correct: tree(letters, &children.passed)?,
+                incorrect: tree(letters, &children.failed)?,
+                code: match new_round {
+                    true => Some(code.parse()?),
+                    false => None,
+                },
+            })))
+        },
+    }
+}
+
+/// Convert a (sub)tree, given the id of its root and the code of the round
+/// it's part of.
+fn node(letters: &[char], rules: &[usize], tree: &BinaryTree<Code>, id: usize, round_code: Option<Code>) -> Node {
+    match tree {
+        BinaryTree::Leaf(c) => Node::Solution { id, code: c.to_string() },
+        BinaryTree::Branch(b) => {
+            let code = b.code.or(round_code).unwrap();
+            Node::Test {
+                id,
+                verifier: letters[b.test.0],
+                card: rules[b.test.0] + 1,
+                category: b.test.1,
+                code: code.to_string(),
+                new_round: b.code.is_some(),
+                leaves: tree.leaf_count(),
+                children: Children {
+                    passed: Box::new(node(letters, rules, &b.correct, id + 1, Some(code))),
+                    failed: Box::new(node(letters, rules, &b.incorrect, id + 1 + b.correct.size(), Some(code))),
+                },
+            }
+        },
+    }
+}
+
+/// List every code with the category each verifier puts it in as CSV, i. e.
+/// the result of [`categorize_codes`](crate::categorize_codes), along with
+/// its status:
+///
+/// - `unique`: one of the unique solutions.
+/// - `shared`: other codes yield the same results.
+/// - `excluded`: ruled out by tests performed beforehand.
+/// - `redundant`: doesn't need all verifiers, see
+///   [`find_super_unique`](crate::find_super_unique).
+/// - `invalid`: some criteria card can't be applied to it.
+///
+/// Categories that don't apply are left empty.
+pub fn code_table(letters: &[char], rules: &[usize], solutions: &HashMap<Results, Vec<Code>>,
+    unique_solutions: &[Feasible<Code>]) -> String {
+    let mut out = String::from("code,blue,yellow,purple");
+    for l in letters {
+        let _ = write!(out, ",{}", l);
+    }
+    out.push_str(",status\n");
+    for code in CodeSpace::standard().codes() {
+        let results: Vec<Option<u8>> = rules.iter().map(|r| RULES[*r](&code)).collect();
+        let status = match results.iter().copied().collect::<Option<Results>>() {
+            None => "invalid",
+            Some(_) if unique_solutions.iter().any(|(_, c)| *c == code) => "unique",
+            Some(r) => match solutions.get(&r) {
+                Some(same) if !same.contains(&code) => "redundant",
+                Some(same) if same.len() > 1 => "shared",
+                Some(_) => "excluded",
+                None => "redundant",
+            },
+        };
+        let _ = write!(out, "{},{},{},{}", code, code.blue, code.yellow, code.purple);
+        for r in results {
+            let _ = write!(out, ",{}", r.map(|r| r.to_string()).unwrap_or_default());
+        }
+        let _ = writeln!(out, ",{}", status);
+    }
+    out
+}
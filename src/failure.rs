@@ -0,0 +1,105 @@
+//! Exit codes and error output for scripts wrapping the solver.
+//!
+//! The process exits with
+//!
+//! - 0 if everything went well,
+//! - 1 on an internal error, e. g. if a file couldn't be written,
+//! - 2 on invalid input, e. g. an unknown criteria card. Clap uses this code
+//!   for invalid arguments as well.
+//! - 3 if the puzzle is unsolvable: no strategy fits the settings, e. g. the
+//!   question budget,
+//! - 4 if the puzzle is improper: its criteria cards don't identify any code
+//!   that requires all verifiers.
+//!
+//! With `--errors json`, errors are written to stderr as a single line, e. g.
+//!
+//! ```text
+//! {"error":"There is no verifier F in this puzzle.","exit_code":2,"kind":"invalid_input"}
+//! ```
+
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+use tm_solver::error::TmSolverError;
+use crate::json;
+
+/// Why the solver failed, see the module documentation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    Internal,
+    InvalidInput,
+    Unsolvable,
+    Improper,
+}
+
+impl Failure {
+
+    /// The exit code of the process.
+    pub fn code(self) -> u8 {
+        match self {
+            Failure::Internal => 1,
+            Failure::InvalidInput => 2,
+            Failure::Unsolvable => 3,
+            Failure::Improper => 4,
+        }
+    }
+
+    /// The name of the failure in JSON error output.
+    pub fn name(self) -> &'static str {
+        match self {
+            Failure::Internal => "internal",
+            Failure::InvalidInput => "invalid_input",
+            Failure::Unsolvable => "unsolvable_puzzle",
+            Failure::Improper => "improper_puzzle",
+        }
+    }
+
+    /// The failure an error of the library stands for.
+    pub fn of(error: &TmSolverError) -> Failure {
+        match error {
+            TmSolverError::Unsolvable => Failure::Improper,
+            TmSolverError::NoTree => Failure::Unsolvable,
+            TmSolverError::RuleCount(_)
+            | TmSolverError::UnknownRule(_)
+            | TmSolverError::DuplicateRule(_)
+            | TmSolverError::DuplicateVerifier(_)
+            | TmSolverError::UnknownVerifier(_)
+            | TmSolverError::InvalidCode(_)
+            | TmSolverError::InvalidHash(_)
+            | TmSolverError::UnknownHash(_)
+            | TmSolverError::InconsistentKnown
+            | TmSolverError::EmptyRound
+            | TmSolverError::InvalidFile(_)
+            | TmSolverError::WrongKind { .. }
+            | TmSolverError::NewerFormat(_)
+            | TmSolverError::InvalidMessage(_) => Failure::InvalidInput,
+        }
+    }
+}
+
+static JSON: AtomicBool = AtomicBool::new(false);
+static STATUS: AtomicU8 = AtomicU8::new(0);
+
+/// Turn JSON error output on or off.
+pub fn set_json(enabled: bool) {
+    JSON.store(enabled, Ordering::Relaxed);
+}
+
+/// Tell the user about an error, in JSON if requested, and remember to exit
+/// with its code.
+pub fn report(failure: Failure, message: &str) {
+    STATUS.store(failure.code(), Ordering::Relaxed);
+    if JSON.load(Ordering::Relaxed) {
+        let error = serde_json::json!({ "error": message, "kind": failure.name(), "exit_code": failure.code() });
+        eprintln!("{}", error);
+    } else if json::enabled() {
+        json::print_error(message);
+    } else {
+        println!("{}", message);
+    }
+}
+
+/// The exit code of the process: that of the last error reported, if any.
+pub fn status() -> ExitCode {
+    ExitCode::from(STATUS.load(Ordering::Relaxed))
+}
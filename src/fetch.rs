@@ -0,0 +1,50 @@
+//! Downloading puzzles to solve them right away. Only available with the
+//! `network` feature.
+//!
+//! The official site doesn't document an API, so puzzles are fetched from a
+//! configurable source instead: a URL containing `{id}`, which is replaced
+//! with the puzzle code (without `#` and spaces, e. g. `B454FJ`) or with
+//! `daily` for the daily challenge. The source has to answer with the
+//! puzzle's criteria card numbers in verifier order, e. g.
+//! `{"rules":[4,9,11,14]}`.
+
+use serde::Deserialize;
+
+use tm_solver::hash::PuzzleHash;
+use crate::cli::{CardsArg, FetchArgs, PuzzleArgs, RuleArg, SolveArgs};
+use crate::failure::{report, Failure};
+
+#[derive(Deserialize)]
+struct Puzzle {
+    rules: Vec<u8>,
+}
+
+/// Download the criteria cards of a puzzle, given its code or `daily`.
+fn download(source: &str, id: &str) -> Result<Vec<u8>, String> {
+    let id = match id {
+        "daily" => String::from("daily"),
+        _ => id.parse::<PuzzleHash>().map_err(|e| e.to_string())?.as_str().to_string(),
+    };
+    let url = source.replace("{id}", &id);
+    let error = |e: &dyn std::fmt::Display| format!("Could not download the puzzle: {}", e);
+    let body = ureq::get(&url).call()
+        .map_err(|e| error(&e))?
+        .into_string()
+        .map_err(|e| error(&e))?;
+    let puzzle: Puzzle = serde_json::from_str(&body)
+        .map_err(|e| format!("Could not read the puzzle: {}", e))?;
+    Ok(puzzle.rules)
+}
+
+/// Download a puzzle and start a guided session for it.
+pub fn run(args: FetchArgs) {
+    let rules = match download(&args.source, &args.id) {
+        Ok(r) => r,
+        Err(e) => return report(Failure::Internal, &e),
+    };
+    let puzzle = PuzzleArgs {
+        rules: rules.into_iter().map(|rule| CardsArg::Card(RuleArg { letter: None, rule })).collect(),
+        ..Default::default()
+    };
+    crate::solve(SolveArgs { puzzle, guide: args.guide, ..Default::default() });
+}
@@ -0,0 +1,124 @@
+//! A C interface for embedding the solver in other programs, enabled with the
+//! `ffi` feature. The matching header is `include/tm_solver.h`; regenerate it
+//! with `cbindgen --config cbindgen.toml --output include/tm_solver.h` after
+//! changing this module.
+//!
+//! ```c
+//! const unsigned int cards[] = {4, 9, 11, 14};
+//! TmTree *tree = tm_solve(cards, 4);
+//! bool answers[16];
+//! size_t count = 0;
+//! TmQuestion question;
+//! while (tm_next_question(tree, answers, count, &question) == TM_QUESTION) {
+//!     // test the code on verifier 'A' + question.verifier
+//!     answers[count++] = passed;
+//! }
+//! // question.blue, question.yellow and question.purple hold the code.
+//! tm_free_tree(tree);
+//! ```
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::{ptr, slice};
+
+use crate::construct_tree::{Answer, BinaryTree};
+use crate::solver::{Solver, Strategy};
+
+/// [`tm_next_question`] found a test to perform.
+pub const TM_QUESTION: i32 = 0;
+/// [`tm_next_question`] found the code.
+pub const TM_SOLVED: i32 = 1;
+/// [`tm_next_question`] got invalid arguments, e. g. more answers than the
+/// strategy has questions.
+pub const TM_INVALID: i32 = -1;
+
+/// The optimal strategy for a puzzle. Create it with [`tm_solve`] and free it
+/// with [`tm_free_tree`].
+pub struct TmTree(Strategy);
+
+/// A test to perform, or the code once it's found.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TmQuestion {
+    pub blue: u8,
+    pub yellow: u8,
+    pub purple: u8,
+    /// the zero-based index of the verifier to test the code on, i. e. 0 for
+    /// verifier A. Unset once the code is found.
+    pub verifier: u8,
+    /// the number of the verifier's criteria card.
+    pub card: u8,
+    /// whether this test starts a new round, i. e. a new code.
+    pub new_round: bool,
+}
+
+/// Construct the optimal strategy for a puzzle, given the numbers of its
+/// `count` criteria cards. Returns null if the puzzle can't be solved.
+///
+/// # Safety
+///
+/// `cards` has to point to `count` readable values.
+#[no_mangle]
+pub unsafe extern "C" fn tm_solve(cards: *const u32, count: usize) -> *mut TmTree {
+    if cards.is_null() {
+        return ptr::null_mut();
+    }
+    let cards: Vec<usize> = slice::from_raw_parts(cards, count).iter().map(|c| *c as usize).collect();
+    match Solver::builder().rules(&cards).solve() {
+        Ok(strategy) => Box::into_raw(Box::new(TmTree(strategy))),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Free a strategy created by [`tm_solve`]. Does nothing for null.
+///
+/// # Safety
+///
+/// `tree` has to come from [`tm_solve`] and may not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn tm_free_tree(tree: *mut TmTree) {
+    if !tree.is_null() {
+        drop(Box::from_raw(tree));
+    }
+}
+
+/// Follow the strategy with the `count` answers given so far (true for a
+/// checkmark) and write the next test to `question`. Returns [`TM_QUESTION`],
+/// [`TM_SOLVED`] if the code is found or [`TM_INVALID`].
+///
+/// # Safety
+///
+/// `tree` has to come from [`tm_solve`], `answers` has to point to `count`
+/// readable values (or be null if `count` is 0) and `question` has to be
+/// writable.
+#[no_mangle]
+pub unsafe extern "C" fn tm_next_question(tree: *const TmTree, answers: *const bool, count: usize,
+    question: *mut TmQuestion) -> i32 {
+    if tree.is_null() || question.is_null() || (answers.is_null() && count > 0) {
+        return TM_INVALID;
+    }
+    let answers: Vec<Answer> = match count {
+        0 => Vec::new(),
+        _ => slice::from_raw_parts(answers, count).iter().map(|&passed| passed.into()).collect(),
+    };
+    let strategy = &(*tree).0;
+    let Some((node, round_code)) = strategy.follow(&answers) else {
+        return TM_INVALID;
+    };
+    let (code, status, result) = match node {
+        BinaryTree::Leaf(c) => (*c, TM_SOLVED, TmQuestion::default()),
+        BinaryTree::Branch(b) => {
+            let Some(code) = b.code.or(round_code) else {
+                return TM_INVALID;
+            };
+            (code, TM_QUESTION, TmQuestion {
+                verifier: b.test.0 as u8,
+                card: strategy.rules[b.test.0] as u8 + 1,
+                new_round: b.code.is_some(),
+                ..TmQuestion::default()
+            })
+        },
+    };
+    *question = TmQuestion { blue: code.blue, yellow: code.yellow, purple: code.purple, ..result };
+    status
+}
@@ -0,0 +1,116 @@
+//! The files written by the solver, e. g. exported strategies and saved
+//! games.
+//!
+//! Every file is a JSON object starting with a small header: the magic
+//! [`MAGIC`], the version of the format and the kind of content, followed by
+//! the content itself:
+//!
+//! ```text
+//! {"format":"tm_solver","version":1,"kind":"strategy","cards":[...],"tree":{...}}
+//! ```
+//!
+//! Files are read forward-compatibly: fields a newer version added are
+//! ignored, and files of a newer version are only rejected if their content
+//! can't be read anymore. Files of an older version, including those written
+//! before there was a header, are upgraded with [`Versioned::upgrade`].
+//!
+//! ```
+//! use tm_solver::export;
+//! use tm_solver::format;
+//! use tm_solver::solver::Solver;
+//!
+//! let strategy = Solver::builder().rules(&[4, 9, 11, 14]).solve()?;
+//! let exported = export::strategy(&['A', 'B', 'C', 'D'], &strategy.rules, &strategy.tree);
+//! let file = format::to_string(&exported);
+//! assert!(file.starts_with(r#"{"format":"tm_solver","version":1,"kind":"strategy","#));
+//!
+//! let loaded: export::Strategy = format::from_str(&file)?;
+//! assert_eq!(loaded.to_tree()?.leaf_count(), strategy.tree.leaf_count());
+//! # Ok::<(), tm_solver::error::TmSolverError>(())
+//! ```
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::TmSolverError;
+
+/// The value of the `format` field of every file.
+pub const MAGIC: &str = "tm_solver";
+
+/// The version of the format written by this version of the solver.
+pub const VERSION: u32 = 1;
+
+/// Content that can be stored in a file.
+pub trait Versioned: Serialize + DeserializeOwned {
+
+    /// The value of the `kind` field, e. g. "strategy".
+    const KIND: &'static str;
+
+    /// Bring content written with an older `version` of the format up to
+    /// date. Version 0 stands for files written before there was a header.
+    /// Returns the content unchanged by default.
+    fn upgrade(_version: u32, content: Value) -> Result<Value, TmSolverError> {
+        Ok(content)
+    }
+}
+
+/// The header in front of the content's own fields. Content is always a
+/// struct, so its fields can be flattened into the file.
+#[derive(Serialize)]
+struct File<'a, T> {
+    format: &'static str,
+    version: u32,
+    kind: &'static str,
+    #[serde(flatten)]
+    content: &'a T,
+}
+
+impl<'a, T: Versioned> File<'a, T> {
+    fn new(content: &'a T) -> Self {
+        File { format: MAGIC, version: VERSION, kind: T::KIND, content }
+    }
+}
+
+/// The content along with the header, as a single line.
+pub fn to_string<T: Versioned>(content: &T) -> String {
+    serde_json::to_string(&File::new(content)).unwrap()
+}
+
+/// The content along with the header, indented for people to read.
+pub fn to_string_pretty<T: Versioned>(content: &T) -> String {
+    serde_json::to_string_pretty(&File::new(content)).unwrap()
+}
+
+/// Read content of the given kind, upgrading it if it was written by an
+/// older version.
+pub fn from_str<T: Versioned>(input: &str) -> Result<T, TmSolverError> {
+    let invalid = |e: serde_json::Error| TmSolverError::InvalidFile(e.to_string());
+    let Value::Object(mut content) = serde_json::from_str(input).map_err(invalid)? else {
+        return Err(TmSolverError::InvalidFile("expected a JSON object".to_string()));
+    };
+    let version = match content.remove("format") {
+        // written before there was a header.
+        None => 0,
+        Some(Value::String(magic)) if magic == MAGIC => {
+            let version = content.remove("version").and_then(|v| v.as_u64()).unwrap_or_default();
+            match content.remove("kind") {
+                Some(Value::String(kind)) if kind == T::KIND => {},
+                kind => return Err(TmSolverError::WrongKind {
+                    expected: T::KIND,
+                    found: kind.as_ref().and_then(Value::as_str).unwrap_or("nothing").to_string(),
+                }),
+            }
+            version as u32
+        },
+        Some(_) => return Err(TmSolverError::InvalidFile(format!("not a {} file", MAGIC))),
+    };
+    let content = match version < VERSION {
+        true => T::upgrade(version, Value::Object(content))?,
+        false => Value::Object(content),
+    };
+    serde_json::from_value(content).map_err(|e| match version > VERSION {
+        true => TmSolverError::NewerFormat(version),
+        false => invalid(e),
+    })
+}
@@ -0,0 +1,191 @@
+//! The thin layer between the interactive dialogs and the user.
+//!
+//! Dialogs such as the guided session never touch stdin or stdout
+//! themselves. They are state machines that take the user's input one line
+//! at a time and answer with [`Effect`]s: text to show, events to record in
+//! the transcript, games to save. A [`FrontEnd`] reads the input and shows
+//! the text, so a dialog can just as well be driven by a script, e. g. to
+//! test a whole session without a terminal.
+
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+
+use tm_solver::i18n::tr;
+use crate::save::SavedGame;
+use crate::transcript::{Event, Transcript};
+
+/// Something a dialog wants to happen in response to the user's input.
+pub enum Effect {
+    /// show one or more lines of text.
+    Show(String),
+    /// show lines of text a page at a time, if the user is at a terminal
+    /// (a page size of 0 shows all of them at once).
+    Page(Vec<String>, usize),
+    /// record an event in the transcript.
+    Record(Event),
+    /// save the game to the file at the given path.
+    Save(String, SavedGame),
+}
+
+/// The effects of a single step of a dialog, in order.
+#[derive(Default)]
+pub struct Effects(Vec<Effect>);
+
+impl Effects {
+
+    /// Show one or more lines of text.
+    pub fn show(&mut self, text: impl Into<String>) {
+        self.0.push(Effect::Show(text.into()));
+    }
+
+    /// Record an event in the transcript.
+    pub fn record(&mut self, event: Event) {
+        self.0.push(Effect::Record(event));
+    }
+
+    /// Any other effect.
+    pub fn push(&mut self, effect: Effect) {
+        self.0.push(effect);
+    }
+}
+
+/// An interactive dialog, driven one line of input at a time.
+pub trait Dialog {
+
+    /// What happens before the first input.
+    fn start(&mut self, effects: &mut Effects);
+
+    /// Handle a line of input, without its line break.
+    fn input(&mut self, input: &str, effects: &mut Effects);
+
+    /// What happens if there is no more input before the dialog is
+    /// finished. Nothing by default.
+    fn closed(&mut self, _effects: &mut Effects) {}
+
+    /// Whether the dialog is over.
+    fn finished(&self) -> bool;
+}
+
+/// Where the input comes from and the text goes to.
+pub trait FrontEnd {
+
+    /// Show one or more lines of text.
+    fn show(&mut self, text: &str);
+
+    /// Ask for input on the same line as the text.
+    fn prompt(&mut self, text: &str) {
+        self.show(text);
+    }
+
+    /// Read a line of input. Returns None once there is no more input.
+    fn read(&mut self) -> Option<String>;
+
+    /// Whether lines should be shown a page at a time.
+    fn paged(&self) -> bool {
+        false
+    }
+}
+
+/// The user at the terminal, reading from stdin and writing to stdout.
+pub struct Terminal;
+
+impl FrontEnd for Terminal {
+    fn show(&mut self, text: &str) {
+        println!("{}", text);
+    }
+
+    fn prompt(&mut self, text: &str) {
+        print!("{}", text);
+        let _ = std::io::stdout().flush();
+    }
+
+    fn read(&mut self) -> Option<String> {
+        let mut input = String::new();
+        match std::io::stdin().read_line(&mut input) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(input),
+        }
+    }
+
+    fn paged(&self) -> bool {
+        std::io::stdout().is_terminal() && std::io::stdin().is_terminal()
+    }
+}
+
+/// Show lines of text, pausing after every page if the front-end asks for
+/// it. Stops early if the user inputs q.
+pub fn page(front: &mut impl FrontEnd, lines: &[String], page_size: usize) {
+    let paged = page_size > 0 && front.paged();
+    for (i, line) in lines.iter().enumerate() {
+        if paged && i > 0 && i % page_size == 0 {
+            front.prompt(&format!("-- {} more lines, press Enter to continue or q to stop --", lines.len() - i));
+            match front.read() {
+                Some(input) if input.trim() != "q" => {},
+                _ => return,
+            }
+        }
+        front.show(line);
+    }
+}
+
+/// Carry out the effects of a dialog step.
+fn apply(effects: Effects, front: &mut impl FrontEnd, transcript: &mut Transcript) {
+    for effect in effects.0 {
+        match effect {
+            Effect::Show(text) => front.show(&text),
+            Effect::Page(lines, page_size) => page(front, &lines, page_size),
+            Effect::Record(event) => transcript.record(&event),
+            Effect::Save(file, saved) => match saved.save(Path::new(&file)) {
+                Ok(()) => front.show(&tr!(saved, file, file)),
+                Err(e) => front.show(&tr!(save_failed, e)),
+            },
+        }
+    }
+}
+
+/// Run a dialog until it is finished or the input runs out.
+pub fn run(dialog: &mut impl Dialog, front: &mut impl FrontEnd, transcript: &mut Transcript) {
+    let mut effects = Effects::default();
+    dialog.start(&mut effects);
+    apply(effects, front, transcript);
+    while !dialog.finished() {
+        let mut effects = Effects::default();
+        match front.read() {
+            Some(input) => dialog.input(input.trim_end_matches(['\r', '\n']), &mut effects),
+            None => {
+                dialog.closed(&mut effects);
+                apply(effects, front, transcript);
+                return;
+            },
+        }
+        apply(effects, front, transcript);
+    }
+}
+
+/// A front-end reading its input from a script and keeping the text shown,
+/// to test dialogs without a terminal.
+#[cfg(test)]
+pub struct Script {
+    input: std::collections::VecDeque<String>,
+    pub shown: Vec<String>,
+}
+
+#[cfg(test)]
+impl Script {
+
+    /// A script with the given lines of input.
+    pub fn new(input: &[&str]) -> Script {
+        Script { input: input.iter().map(|line| line.to_string()).collect(), shown: Vec::new() }
+    }
+}
+
+#[cfg(test)]
+impl FrontEnd for Script {
+    fn show(&mut self, text: &str) {
+        self.shown.extend(text.lines().map(String::from));
+    }
+
+    fn read(&mut self) -> Option<String> {
+        self.input.pop_front()
+    }
+}
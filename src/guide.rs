@@ -0,0 +1,697 @@
+//! The guided session: the solver tells the user which code to use and which
+//! verifier to ask, one question at a time, until the code is found.
+//!
+//! Besides answering, the user can go back, save the game, correct an
+//! earlier answer or ask the verifiers of a round in another order. If the
+//! answers contradict each other, or the solution fails its final check, the
+//! guide points out the answers that were probably mis-read.
+
+use std::collections::HashMap;
+
+use tm_solver::construct_tree::{rank, Answer, BinaryTree, Branch, Feasible, Test};
+use tm_solver::error::TmSolverError;
+use tm_solver::i18n::tr;
+use tm_solver::rules::{Code, RULE_DESCRIPTIONS};
+use tm_solver::session::{Answered, GameSession};
+use tm_solver::style;
+use tm_solver::RuleSet;
+use crate::cli::{self, GuideArgs};
+use crate::frontend::{Dialog, Effect, Effects};
+use crate::save::SavedGame;
+use crate::transcript::Event;
+use crate::{consistent, count_codes, final_check, prefer_round_codes, replay, round_code, round_started, split, Effort, Prepared};
+
+/// What the guide waits for.
+enum Prompt {
+    /// the answer to the current question, or another command.
+    Answer,
+    /// how many answers to take back after they contradicted each other.
+    Undo,
+    /// the verifier that failed the final check of the solution found, if
+    /// any.
+    Check(Code),
+    /// the answer to correct after a failed final check.
+    Fix,
+    Finished,
+}
+
+/// A guided session.
+pub struct Guide {
+    prepared: Prepared,
+    /// the answers given so far, starting at the root of the tree.
+    answers: Vec<Answer>,
+    /// list the remaining codes after every answer.
+    remaining: bool,
+    /// explain every question by how many codes either answer leaves.
+    explain: bool,
+    /// the effort of the strategy the session started with, before any
+    /// correction or reordering changed the tree.
+    optimum: Effort,
+    prompt: Prompt,
+}
+
+impl Guide {
+
+    /// A session for the puzzle, starting after the given answers.
+    pub fn new(prepared: Prepared, args: &GuideArgs, answers: &[Answer]) -> Guide {
+        Guide {
+            optimum: Effort::of(&prepared),
+            prepared,
+            answers: answers.to_vec(),
+            remaining: args.remaining,
+            explain: args.explain,
+            prompt: Prompt::Answer,
+        }
+    }
+
+    /// Follow the solution tree with the answers given so far.
+    fn session(&self) -> GameSession<'_> {
+        let mut session = GameSession::new(&self.prepared.tree, &self.prepared.unique_solutions);
+        for answer in &self.answers {
+            session.answer(*answer);
+        }
+        session
+    }
+
+    /// Start the session over, e. g. after the solution tree changed.
+    fn begin(&mut self, effects: &mut Effects) {
+        let (session, events) = replay(&self.prepared, &self.answers);
+        events.into_iter().for_each(|e| effects.record(e));
+        if session.answers().len() < self.answers.len() {
+            effects.show(tr!(more_answers));
+        }
+        if session.history().is_empty() {
+            let effort = Effort::of(&self.prepared);
+            effects.show(tr!(effort, effort.max_tests, format!("{:.1}", effort.average_tests),
+                effort.max_rounds, format!("{:.1}", effort.average_rounds)));
+        } else {
+            effects.show(tr!(resuming));
+            if let Some(q) = session.next_question().filter(|q| !q.new_round) {
+                effects.show(tr!(keep_combination, q.code.colored()));
+            }
+        }
+        self.answers = session.answers();
+        self.advance(effects);
+    }
+
+    /// Go on after the answers changed: ask the next question, or present the
+    /// solution.
+    fn advance(&mut self, effects: &mut Effects) {
+        let prepared = &self.prepared;
+        let letters = &prepared.letters;
+        let session = self.session();
+        let history = session.history();
+
+        // make sure the answers still make sense before going on.
+        let remaining = session.remaining();
+        if self.remaining && !history.is_empty() {
+            effects.show(remaining_codes(&remaining));
+        }
+        if remaining.is_empty() {
+            effects.show(tr!(contradiction));
+            show_suspects(prepared, history, &[], effects);
+            effects.show(tr!(give_again, history.len()));
+            self.prompt = Prompt::Undo;
+            return;
+        }
+
+        // a round ends when the tree provides a new code, or a solution.
+        let round_ended = session.next_question().is_none_or(|q| q.new_round);
+        if round_ended && !history.is_empty() {
+            effects.show("------");
+            effects.show(note_sheet_text(history, letters));
+        }
+
+        let b = match session.node() {
+            BinaryTree::Leaf(c) => {
+                effects.show(tr!(found));
+                effects.show(final_check(prepared, c));
+                effects.show(tr!(your_code, c.colored()));
+                effects.record(Event::Solution(*c));
+                effects.show(stats(&self.optimum, prepared, history));
+                effects.show(tr!(check_failed, style::checkmark()));
+                self.prompt = Prompt::Check(*c);
+                return;
+            },
+            BinaryTree::Branch(b) => b,
+        };
+
+        if let Some((round, code)) = round_started(b, history) {
+            effects.show("------");
+            effects.show(style::bold(&tr!(start_of_round, round)));
+            effects.show(tr!(use_combination, code.colored()));
+            effects.record(Event::Round(round, *code));
+        }
+        effects.show(tr!(question, style::highlight(&tr!(test, letters[b.test.0])), style::checkmark()));
+        let rule = prepared.rules[b.test.0];
+        effects.show(format!("  {}", tr!(criterion, rule + 1, RULE_DESCRIPTIONS[rule])));
+        if self.explain {
+            effects.show(split(b));
+        }
+        self.prompt = Prompt::Answer;
+    }
+
+    /// Correct an earlier answer as given by the user's input (e. g. " B n")
+    /// and go on from the corrected answers.
+    fn fix(&mut self, input: &str, effects: &mut Effects) {
+        let session = self.session();
+        match correct(&self.prepared, session.history(), input) {
+            Ok((corrected, answers, letter, answer)) => {
+                effects.show(tr!(fixed, tr!(test, letter)));
+                effects.record(Event::Correct(letter, answer));
+                self.prepared = corrected;
+                self.answers = answers;
+                self.begin(effects);
+            },
+            Err(e) => effects.show(e),
+        }
+    }
+
+    /// Handle the user's input while a question is asked.
+    fn answer(&mut self, input: &str, effects: &mut Effects) {
+        let letters = &self.prepared.letters;
+        let mut session = self.session();
+        let BinaryTree::Branch(b) = session.node() else {
+            return;
+        };
+        if let Some(file) = input.trim().strip_prefix("save") {
+            let file = match file.trim() {
+                "" => "tm_solver_save.json",
+                f => f,
+            };
+            let saved = SavedGame {
+                cards: letters.iter().zip(&self.prepared.rules).map(|(l, r)| (*l, *r as u8 + 1)).collect(),
+                known: self.prepared.known.clone(),
+                tree: self.prepared.tree.clone(),
+                answers: session.answers(),
+            };
+            return effects.push(Effect::Save(file.to_string(), saved));
+        }
+        if let Some(rest) = input.trim().strip_prefix("fix") {
+            return self.fix(rest, effects);
+        }
+        if let Some(rest) = input.trim().strip_prefix("ask") {
+            match reorder(&self.prepared, session.history(), b, rest) {
+                Ok((reordered, letter, answer)) => {
+                    let code = session.next_question().map(|q| q.code).unwrap();
+                    effects.show(tr!(reordered, tr!(test, letter), code.colored()));
+                    let mut answers = session.answers();
+                    answers.push(answer);
+                    self.prepared = reordered;
+                    self.answers = answers;
+                    self.begin(effects);
+                },
+                Err(e) => effects.show(e),
+            }
+            return;
+        }
+        // several answers at once, e. g. ynn, for the rest of the round.
+        let several = input.trim();
+        if several.len() > 1 && several.chars().all(|c| c == 'y' || c == 'n') {
+            let mut given = 0;
+            for c in several.chars() {
+                let Some(next) = session.next_question() else {
+                    break;
+                };
+                if given > 0 && next.new_round {
+                    break;
+                }
+                let answer = Answer::from(c == 'y');
+                effects.show(format!("  {}: {}", tr!(test, letters[next.test.0]), style::mark(answer)));
+                effects.record(Event::Answer(letters[next.test.0], answer));
+                session.answer(answer);
+                given += 1;
+            }
+            if given < several.len() {
+                effects.show(tr!(answers_ignored, given));
+            }
+            self.answers = session.answers();
+            return self.advance(effects);
+        }
+        match input.chars().next() {
+            Some(c @ ('y' | 'n')) => {
+                let answer = Answer::from(c == 'y');
+                effects.record(Event::Answer(letters[b.test.0], answer));
+                self.answers.push(answer);
+                self.advance(effects);
+            },
+            Some('b') => match self.answers.pop() {
+                Some(_) => {
+                    effects.show(tr!(going_back));
+                    effects.record(Event::Undo(1));
+                    self.advance(effects);
+                },
+                None => effects.show(tr!(nothing_to_undo)),
+            },
+            Some('r') => effects.show(remaining_codes(&session.remaining())),
+            Some('h') => effects.show(split(b)),
+            Some('s') => effects.show(note_sheet_text(session.history(), letters)),
+            Some('q') => {
+                effects.show(tr!(quit));
+                self.prompt = Prompt::Finished;
+            },
+            _ => effects.show(tr!(invalid_answer)),
+        }
+    }
+
+    /// Take back as many answers as the user asks for after a
+    /// contradiction.
+    fn undo(&mut self, input: &str, effects: &mut Effects) {
+        match input.trim().parse::<usize>() {
+            Ok(n) if n > 0 && n <= self.answers.len() => {
+                self.answers.truncate(self.answers.len() - n);
+                effects.record(Event::Undo(n));
+                self.advance(effects);
+            },
+            _ => effects.show(tr!(number_range, 1, self.answers.len())),
+        }
+    }
+
+    /// Let the user report a verifier that failed the final check of the
+    /// solution and point out the answers that were probably mis-read.
+    fn check(&mut self, code: Code, input: &str, effects: &mut Effects) {
+        let input = input.trim().to_ascii_uppercase();
+        if input.is_empty() {
+            self.prompt = Prompt::Finished;
+            return;
+        }
+        let Some(i) = self.prepared.letters.iter().position(|l| input == l.to_string()) else {
+            return effects.show(tr!(invalid_check));
+        };
+        let session = self.session();
+        let found = self.prepared.matrix.category(i, &code)
+            .is_some_and(|value| show_suspects(&self.prepared, session.history(), &[((i, value), Answer::Fail)], effects));
+        self.prompt = match found {
+            true => {
+                effects.show(tr!(fix_suspect));
+                Prompt::Fix
+            },
+            false => Prompt::Finished,
+        };
+    }
+}
+
+impl Dialog for Guide {
+    fn start(&mut self, effects: &mut Effects) {
+        self.begin(effects);
+    }
+
+    fn input(&mut self, input: &str, effects: &mut Effects) {
+        match self.prompt {
+            Prompt::Answer => self.answer(input, effects),
+            Prompt::Undo => self.undo(input, effects),
+            Prompt::Check(code) => self.check(code, input, effects),
+            Prompt::Fix => match input.trim().strip_prefix("fix") {
+                Some(rest) => self.fix(rest, effects),
+                None => self.prompt = Prompt::Finished,
+            },
+            Prompt::Finished => {},
+        }
+    }
+
+    fn closed(&mut self, effects: &mut Effects) {
+        if let Prompt::Answer | Prompt::Undo = self.prompt {
+            effects.show(tr!(no_more_input));
+        }
+        self.prompt = Prompt::Finished;
+    }
+
+    fn finished(&self) -> bool {
+        matches!(self.prompt, Prompt::Finished)
+    }
+}
+
+/// A round as written down on the game's note sheet: the code used and the
+/// result of each verifier, if it was queried.
+pub struct SheetRow<'a> {
+    pub code: &'a Code,
+    pub results: Vec<Option<Answer>>,
+}
+
+/// The rounds played so far, given the questions answered.
+pub fn note_sheet<'a>(history: &[Answered<'a>], verifiers: usize) -> Vec<SheetRow<'a>> {
+    let mut rows: Vec<SheetRow> = Vec::new();
+    for (node, (test, _), answer) in history {
+        if let BinaryTree::Branch(b) = node {
+            if let Some(code) = &b.code {
+                rows.push(SheetRow { code, results: vec![None; verifiers] });
+            }
+        }
+        if let Some(row) = rows.last_mut() {
+            row.results[*test] = Some(*answer);
+        }
+    }
+    rows
+}
+
+/// The rounds played so far like on the game's note sheet, so they can be
+/// copied onto it.
+fn note_sheet_text(history: &[Answered], letters: &[char]) -> String {
+    let sheet = note_sheet(history, letters.len());
+    let round = tr!(round);
+    let code = tr!(code);
+    // pad by hand, since colored codes are longer than they look.
+    let code_width = sheet.iter().map(|r| style::width(&r.code.colored().to_string()))
+        .chain([code.chars().count()])
+        .max().unwrap_or(0);
+    let pad = |text: &str, width: usize| format!("{}{}", text, " ".repeat(width.saturating_sub(style::width(text))));
+    let mut header = format!("{}  {}", round, pad(&code, code_width));
+    for l in letters {
+        header.push_str(&format!("  {}", l));
+    }
+    let mut lines = vec![style::bold(&header)];
+    for (i, row) in sheet.iter().enumerate() {
+        let mut line = format!("{}  {}", pad(&(i + 1).to_string(), round.chars().count()),
+            pad(&row.code.colored().to_string(), code_width));
+        for result in &row.results {
+            line.push_str(&match result {
+                Some(answer) => format!("  {}", style::mark(*answer)),
+                None => "   ".to_string(),
+            });
+        }
+        lines.push(line.trim_end().to_string());
+    }
+    lines.join("\n")
+}
+
+/// Find the answers that would make all answers match a code again if they
+/// were given the other way round. `extra` holds further test results that
+/// aren't in doubt.
+fn suspects(prepared: &Prepared, history: &[Answered], extra: &[(Test, Answer)]) -> Vec<usize> {
+    let answers: Vec<(Test, Answer)> = history.iter().map(|(_, t, a)| (*t, *a)).collect();
+    (0..answers.len()).filter(|i| {
+        let mut flipped = answers.clone();
+        flipped[*i].1 = !flipped[*i].1;
+        flipped.extend_from_slice(extra);
+        !consistent(&prepared.unique_solutions, &flipped).is_empty()
+    }).collect()
+}
+
+/// Point the user at the answers that were probably mis-read. Returns
+/// whether there are any.
+fn show_suspects(prepared: &Prepared, history: &[Answered], extra: &[(Test, Answer)], effects: &mut Effects) -> bool {
+    let suspects = suspects(prepared, history, extra);
+    if suspects.is_empty() {
+        effects.show(tr!(no_suspects));
+        return false;
+    }
+    let suspects: Vec<String> = suspects.iter().map(|i| {
+        let (_, (test, _), answer) = history[*i];
+        format!("{} ({} {} {})", tr!(test, prepared.letters[test]), style::mark(answer), style::arrow(),
+            style::mark(!answer))
+    }).collect();
+    effects.show(tr!(suspects, suspects.join(", ")));
+    true
+}
+
+/// Correct the answer to the last question about the given verifier, e. g.
+/// `B n`. All other answers are kept: the solution tree follows the questions
+/// asked so far with the corrected answers, and the current round goes on
+/// with its code and the verifiers not asked in it yet.
+///
+/// Returns the puzzle with the corrected solution tree and the answers to
+/// follow it with, as well as the verifier corrected and its answer. A later
+/// question the corrected answers already answer is left out.
+fn correct(prepared: &Prepared, history: &[Answered], input: &str)
+    -> Result<(Prepared, Vec<Answer>, char, Answer), String> {
+    let mut parts = input.split_whitespace();
+    let (Some(letter), Some(answer), None) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(tr!(invalid_fix));
+    };
+    let mut chars = letter.chars();
+    let (Some(letter), None) = (chars.next(), chars.next()) else {
+        return Err(tr!(invalid_fix));
+    };
+    let letter = letter.to_ascii_uppercase();
+    let answer = cli::parse_answer(answer).map_err(|_| tr!(invalid_fix))?;
+    let Some(index) = history.iter().rposition(|(_, (i, _), _)| prepared.letters[*i] == letter) else {
+        return Err(tr!(fix_not_asked, letter));
+    };
+
+    let answers: Vec<(Test, Answer)> = history.iter().enumerate()
+        .map(|(i, (_, test, a))| (*test, if i == index { answer } else { *a }))
+        .collect();
+    let entries = |answers: &[(Test, Answer)]| -> Vec<Feasible<Code>> {
+        consistent(&prepared.unique_solutions, answers).into_iter().cloned().collect()
+    };
+    let remaining = entries(&answers);
+    if remaining.is_empty() {
+        return Err(tr!(fix_contradiction));
+    }
+
+    // plan the rest of the round after the first `end` questions.
+    let mut new_rounds = NewRounds::new();
+    let mut plan = |end: usize, entries: &[Feasible<Code>]| {
+        let start = history[..end].iter()
+            .rposition(|(h, _, _)| matches!(h, BinaryTree::Branch(p) if p.code.is_some()))
+            .unwrap_or(0);
+        let asked: Vec<usize> = history[start..end].iter().map(|(_, (i, _), _)| *i).collect();
+        let verifiers: Vec<usize> = (0..prepared.letters.len()).filter(|i| !asked.contains(i)).collect();
+        let questions = prepared.solver.round_size().saturating_sub(asked.len() as u8);
+        let code = round_code(&history[..end]).ok_or_else(|| TmSolverError::NoTree.to_string())?;
+        plan_round(prepared, entries, code, &verifiers, questions, &mut new_rounds)
+            .ok_or_else(|| TmSolverError::NoTree.to_string())
+    };
+
+    // rebuild the path of the questions asked, from the last one up. The
+    // other answer of a question before the corrected one leads to the same
+    // subtree as before, as does the answer given at first to the corrected
+    // one; those of later questions are planned anew.
+    let mut tree = plan(history.len(), &remaining)?;
+    let mut kept = vec![true; history.len()];
+    for (j, (node, test, _)) in history.iter().enumerate().rev() {
+        let BinaryTree::Branch(b) = node else {
+            continue;
+        };
+        let (test, a) = (*test, answers[j].1);
+        let other = match j <= index {
+            true => Some(match !a {
+                Answer::Pass => b.correct.clone(),
+                Answer::Fail => b.incorrect.clone(),
+            }),
+            false => {
+                let other = entries(&[&answers[..j], &[(test, !a)]].concat());
+                match other.is_empty() {
+                    true => None,
+                    false => Some(plan(j + 1, &other)?),
+                }
+            },
+        };
+        let Some(other) = other else {
+            // the question needn't be asked anymore, so the next one starts
+            // its round if it did.
+            kept[j] = false;
+            if let BinaryTree::Branch(next) = &mut tree {
+                next.code = next.code.or(b.code);
+            }
+            continue;
+        };
+        let (correct, incorrect) = match a {
+            Answer::Pass => (tree, other),
+            Answer::Fail => (other, tree),
+        };
+        tree = BinaryTree::Branch(Box::new(Branch { test, correct, incorrect, code: b.code }));
+    }
+    let answers = answers.iter().zip(kept).filter(|(_, k)| *k).map(|((_, a), _)| *a).collect();
+    let corrected = Prepared {
+        letters: prepared.letters.clone(),
+        rules: prepared.rules.clone(),
+        matrix: prepared.matrix.clone(),
+        known: prepared.known.clone(),
+        removed: prepared.removed.clone(),
+        solutions: prepared.solutions.clone(),
+        unique_solutions: prepared.unique_solutions.clone(),
+        tree,
+        round_codes: prepared.round_codes,
+        solver: prepared.solver.clone(),
+    };
+    Ok((corrected, answers, letter, answer))
+}
+
+/// The optimal trees starting a new round, by the codes they tell apart.
+type NewRounds = HashMap<Vec<Code>, Option<BinaryTree<Code>>>;
+
+/// Plan the rest of a round using `code`, given the verifiers that can still
+/// be asked in it and how many questions are left. Ending the round early is
+/// considered as well; every following round gets an optimal tree of its own,
+/// which is only constructed once per set of remaining codes.
+fn plan_round(prepared: &Prepared, entries: &[Feasible<Code>], code: &Code, verifiers: &[usize], questions: u8,
+    new_rounds: &mut NewRounds) -> Option<BinaryTree<Code>> {
+    if entries.len() == 1 {
+        return Some(BinaryTree::Leaf(entries[0].1));
+    }
+    let objective = prepared.solver.optimizes();
+    let better = |tree: &BinaryTree<Code>, best: &Option<BinaryTree<Code>>| best.as_ref()
+        .is_none_or(|b| rank((tree.max_depth(), tree.total_depth()), objective)
+            < rank((b.max_depth(), b.total_depth()), objective));
+    let mut best = None;
+    for &i in verifiers.iter().filter(|_| questions > 0) {
+        let Some(value) = prepared.matrix.category(i, code) else {
+            continue;
+        };
+        let (correct, incorrect): (Vec<_>, Vec<_>) = entries.iter().cloned().partition(|(r, _)| r[i] == value);
+        if correct.is_empty() || incorrect.is_empty() {
+            continue;
+        }
+        let rest: Vec<usize> = verifiers.iter().copied().filter(|j| *j != i).collect();
+        let (Some(correct), Some(incorrect)) = (
+            plan_round(prepared, &correct, code, &rest, questions - 1, new_rounds),
+            plan_round(prepared, &incorrect, code, &rest, questions - 1, new_rounds),
+        ) else {
+            continue;
+        };
+        let tree = BinaryTree::Branch(Box::new(Branch { test: (i, value), correct, incorrect, code: None }));
+        if better(&tree, &best) {
+            best = Some(tree);
+        }
+    }
+
+    // only start a new round if that's actually better.
+    let new_round = new_rounds.entry(entries.iter().map(|(_, c)| *c).collect()).or_insert_with(|| {
+        let mut tree = prepared.solver.construct_among(&prepared.rules, &prepared.solutions, entries).ok()?;
+        prefer_round_codes(&mut tree, &prepared.rules, prepared.round_codes);
+        Some(tree)
+    });
+    match new_round {
+        Some(tree) if better(tree, &best) => Some(tree.clone()),
+        _ => best,
+    }
+}
+
+/// Ask another verifier of the current round first, as given by the user's
+/// input (e. g. " C y"), and plan the rest of the round from there. `branch`
+/// is the question the solver would have asked.
+///
+/// Returns the puzzle with the re-planned solution tree, as well as the
+/// verifier asked and its answer.
+fn reorder(prepared: &Prepared, history: &[Answered], branch: &Branch<Code>, input: &str)
+    -> Result<(Prepared, char, Answer), String> {
+    let mut parts = input.split_whitespace();
+    let (Some(letter), Some(answer), None) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(tr!(invalid_ask));
+    };
+    let mut chars = letter.chars();
+    let (Some(letter), None) = (chars.next(), chars.next()) else {
+        return Err(tr!(invalid_ask));
+    };
+    let letter = letter.to_ascii_uppercase();
+    let answer = cli::parse_answer(answer).map_err(|_| tr!(invalid_ask))?;
+
+    // the questions asked so far this round can't be asked again.
+    let (start, code) = match &branch.code {
+        Some(code) => (history.len(), code),
+        None => {
+            let start = history.iter()
+                .rposition(|(h, _, _)| matches!(h, BinaryTree::Branch(p) if p.code.is_some()))
+                .ok_or_else(|| tr!(invalid_ask))?;
+            (start, round_code(history).ok_or_else(|| tr!(invalid_ask))?)
+        },
+    };
+    let asked: Vec<usize> = history[start..].iter().map(|(_, (i, _), _)| *i).collect();
+    let index = prepared.letters.iter().position(|l| *l == letter)
+        .filter(|i| !asked.contains(i))
+        .ok_or_else(|| tr!(ask_unavailable, letter))?;
+    let value = prepared.matrix.category(index, code).ok_or_else(|| tr!(ask_unavailable, letter))?;
+
+    let answers: Vec<(Test, Answer)> = history.iter().map(|(_, t, a)| (*t, *a)).collect();
+    let (correct, incorrect): (Vec<_>, Vec<_>) = consistent(&prepared.unique_solutions, &answers)
+        .into_iter().cloned().partition(|(r, _)| r[index] == value);
+    if correct.is_empty() || incorrect.is_empty() {
+        return Err(tr!(ask_useless, letter));
+    }
+    let verifiers: Vec<usize> = (0..prepared.letters.len())
+        .filter(|i| *i != index && !asked.contains(i))
+        .collect();
+    let questions = prepared.solver.round_size().saturating_sub(asked.len() as u8 + 1);
+    let mut new_rounds = NewRounds::new();
+    let mut plan = |entries: &[Feasible<Code>]| plan_round(prepared, entries, code, &verifiers, questions,
+        &mut new_rounds)
+        .ok_or_else(|| TmSolverError::NoTree.to_string());
+    let replanned = BinaryTree::Branch(Box::new(Branch {
+        test: (index, value),
+        correct: plan(&correct)?,
+        incorrect: plan(&incorrect)?,
+        code: branch.code,
+    }));
+
+    // replace the current question by the re-planned one.
+    let mut tree = prepared.tree.clone();
+    let mut node = &mut tree;
+    for (_, _, answer) in history {
+        let BinaryTree::Branch(b) = node else {
+            break;
+        };
+        node = match answer {
+            Answer::Pass => &mut b.correct,
+            Answer::Fail => &mut b.incorrect,
+        };
+    }
+    *node = replanned;
+    let reordered = Prepared {
+        letters: prepared.letters.clone(),
+        rules: prepared.rules.clone(),
+        matrix: prepared.matrix.clone(),
+        known: prepared.known.clone(),
+        removed: prepared.removed.clone(),
+        solutions: prepared.solutions.clone(),
+        unique_solutions: prepared.unique_solutions.clone(),
+        tree,
+        round_codes: prepared.round_codes,
+        solver: prepared.solver.clone(),
+    };
+    Ok((reordered, letter, answer))
+}
+
+/// The codes that are still possible.
+fn remaining_codes(remaining: &[&Feasible<Code>]) -> String {
+    let codes: Vec<String> = remaining.iter().map(|(_, c)| c.colored().to_string()).collect();
+    tr!(codes_left, count_codes(codes.len()), codes.join(", "))
+}
+
+/// The questions and rounds a finished session took, compared to the
+/// given effort of the optimal strategy.
+fn stats(effort: &Effort, prepared: &Prepared, history: &[Answered]) -> String {
+    let rounds = note_sheet(history, prepared.letters.len()).len();
+    format!("{}\n{}", tr!(stats, history.len(), rounds), tr!(optimum, effort.max_tests,
+        format!("{:.1}", effort.average_tests), effort.max_rounds, format!("{:.1}", effort.average_rounds)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{CardsArg, PuzzleArgs, RuleArg};
+    use crate::frontend::{self, Script};
+    use crate::transcript::{self, Transcript};
+
+    #[test]
+    fn answer_undo_and_fix() {
+        let rules = [3, 9, 12, 17, 20].map(|rule| CardsArg::Card(RuleArg { letter: None, rule })).to_vec();
+        let prepared = crate::prepare_puzzle(&PuzzleArgs { rules, ..Default::default() }).unwrap();
+        let BinaryTree::Branch(root) = &prepared.tree else {
+            panic!("the puzzle needs questions");
+        };
+        let letter = prepared.letters[root.test.0];
+        let path = std::env::temp_dir().join(format!("tm_solver_guide_{}.txt", std::process::id()));
+        let mut transcript = Transcript::create(Some(&path)).unwrap();
+        let fix = format!("fix {} n", letter);
+        let mut script = Script::new(&["n", "b", "y", &fix]);
+        frontend::run(&mut Guide::new(prepared, &GuideArgs::default(), &[]), &mut script, &mut transcript);
+        drop(transcript);
+        let events: Vec<String> = transcript::read(&path).unwrap().iter()
+            .map(|e| e.to_string())
+            .filter(|e| !e.starts_with("round"))
+            .collect();
+        std::fs::remove_file(&path).unwrap();
+
+        let expected = [format!("answer {}=n", letter), "undo 1".to_string(), format!("answer {}=y", letter),
+            format!("correct {}=n", letter)];
+        assert!(events[0].starts_with("puzzle"));
+        assert_eq!(events[1..5], expected);
+        // the session goes on from the corrected answers.
+        assert_eq!(events[5], events[0]);
+        assert_eq!(events[6], format!("answer {}=n", letter));
+        for line in [tr!(going_back), tr!(fixed, tr!(test, letter)), tr!(no_more_input)] {
+            assert!(script.shown.contains(&line), "{} wasn't shown", line);
+        }
+    }
+}
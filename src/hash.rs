@@ -0,0 +1,164 @@
+//! Puzzle codes in the official notation, e. g. `#B45 4FJ`, as printed in the
+//! book and shown by the app.
+//!
+//! The codes of the book and app refer to an entry of the official puzzle
+//! database, so finding the criteria cards behind one needs that database,
+//! see the `fetch` command. The solver's own codes, e. g. for puzzles made
+//! with the `generate` command, use the same notation,
+//! but with seven characters instead of six, and describe the puzzle on
+//! their own: for every verifier letter from A to F, the number of its
+//! criteria card, or 0 if there is no such verifier, as a number in base
+//! 26, written with six characters of Crockford's base 32 alphabet and a
+//! check character.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::TmSolverError;
+use crate::rules::RULES;
+use crate::solver;
+
+/// The digits of the solver's own codes, see the [module](self) docs.
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// The verifier letters a code has room for.
+const LETTERS: usize = 6;
+
+/// The number of characters of the solver's own codes without the check
+/// character.
+const DIGITS: usize = 6;
+
+/// A puzzle code in the official notation. Spaces, the leading `#` and the
+/// case don't matter.
+///
+/// ```
+/// use tm_solver::hash::PuzzleHash;
+///
+/// let hash: PuzzleHash = "#b45 4fj".parse()?;
+/// assert_eq!(hash, "B454FJ".parse()?);
+/// assert_eq!(hash.to_string(), "#B45 4FJ");
+/// # Ok::<(), tm_solver::error::TmSolverError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PuzzleHash(String);
+
+impl PuzzleHash {
+
+    /// The code without the `#` and spaces, e. g. `B454FJ`.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The solver's own code of a puzzle with the given verifier letters
+    /// and criteria card numbers, in any order. The app only opens the
+    /// puzzles of its database, so these codes are for sharing puzzles among
+    /// users of the solver, see [`PuzzleHash::decode`].
+    ///
+    /// ```
+    /// use tm_solver::hash::PuzzleHash;
+    ///
+    /// let hash = PuzzleHash::encode(&[('B', 9), ('A', 4), ('C', 11), ('D', 14)])?;
+    /// assert_eq!(hash.to_string(), "#007 QSAK");
+    /// assert_eq!(hash.decode()?, [('A', 4), ('B', 9), ('C', 11), ('D', 14)]);
+    /// # Ok::<(), tm_solver::error::TmSolverError>(())
+    /// ```
+    pub fn encode(verifiers: &[(char, usize)]) -> Result<PuzzleHash, TmSolverError> {
+        let cards: Vec<usize> = verifiers.iter().map(|(_, c)| *c).collect();
+        solver::validate(&cards)?;
+        let mut slots = [0; LETTERS];
+        for (letter, card) in verifiers {
+            let slot = (letter.to_ascii_uppercase() as usize).wrapping_sub('A' as usize);
+            match slots.get_mut(slot) {
+                Some(s @ 0) => *s = *card as u64,
+                Some(_) => return Err(TmSolverError::DuplicateVerifier(letter.to_ascii_uppercase())),
+                None => return Err(TmSolverError::UnknownVerifier(*letter)),
+            }
+        }
+        let mut value = slots.iter().rev().fold(0, |value, card| value * (RULES.len() as u64 + 1) + card);
+        let mut digits = [0; DIGITS];
+        for digit in digits.iter_mut().rev() {
+            *digit = (value % 32) as u32;
+            value /= 32;
+        }
+        let code = digits.iter().chain([checksum(&digits)].iter())
+            .map(|d| ALPHABET[*d as usize] as char)
+            .collect();
+        Ok(PuzzleHash(code))
+    }
+
+    /// The verifier letters and criteria card numbers of one of the
+    /// solver's own codes, ordered by letter. The codes of the book and app
+    /// can't be decoded without the official puzzle database, so they yield
+    /// [`TmSolverError::UnknownHash`].
+    ///
+    /// ```
+    /// use tm_solver::error::TmSolverError;
+    /// use tm_solver::hash::PuzzleHash;
+    ///
+    /// let hash: PuzzleHash = "#007 QSAK".parse()?;
+    /// assert_eq!(hash.decode()?, [('A', 4), ('B', 9), ('C', 11), ('D', 14)]);
+    ///
+    /// let official: PuzzleHash = "#B45 4FJ".parse()?;
+    /// assert!(matches!(official.decode(), Err(TmSolverError::UnknownHash(_))));
+    /// # Ok::<(), tm_solver::error::TmSolverError>(())
+    /// ```
+    pub fn decode(&self) -> Result<Vec<(char, usize)>, TmSolverError> {
+        let unknown = || TmSolverError::UnknownHash(self.to_string());
+        let digits: Vec<u32> = self.0.bytes()
+            .map(|c| ALPHABET.iter().position(|a| *a == c).map(|d| d as u32))
+            .collect::<Option<_>>()
+            .ok_or_else(unknown)?;
+        let [number @ .., check] = &digits[..] else {
+            return Err(unknown());
+        };
+        if number.len() != DIGITS || checksum(number) != *check {
+            return Err(unknown());
+        }
+        let mut value = number.iter().fold(0u64, |value, d| value * 32 + *d as u64);
+        let mut verifiers = Vec::new();
+        for letter in (b'A'..).take(LETTERS) {
+            let card = (value % (RULES.len() as u64 + 1)) as usize;
+            value /= RULES.len() as u64 + 1;
+            if card > 0 {
+                verifiers.push((letter as char, card));
+            }
+        }
+        if value > 0 {
+            return Err(unknown());
+        }
+        let cards: Vec<usize> = verifiers.iter().map(|(_, c)| *c).collect();
+        solver::validate(&cards).map_err(|_| unknown())?;
+        Ok(verifiers)
+    }
+}
+
+/// The check character of the digits of one of the solver's own codes.
+fn checksum(digits: &[u32]) -> u32 {
+    digits.iter().enumerate().map(|(i, d)| (i as u32 + 1) * d).sum::<u32>() % 31
+}
+
+impl FromStr for PuzzleHash {
+    type Err = TmSolverError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let trimmed = input.trim();
+        let code: String = trimmed.strip_prefix('#').unwrap_or(trimmed)
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| c.to_ascii_uppercase())
+            .collect();
+        if !(4..=8).contains(&code.len()) || !code.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(TmSolverError::InvalidHash(input.to_string()));
+        }
+        Ok(PuzzleHash(code))
+    }
+}
+
+impl fmt::Display for PuzzleHash {
+
+    /// The code as printed in the book, e. g. #B45 4FJ.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (first, rest) = self.0.split_at(3);
+        write!(f, "#{} {}", first, rest)
+    }
+}
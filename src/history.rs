@@ -0,0 +1,137 @@
+//! Learning which tests to try first from earlier searches.
+//!
+//! The search tries the most promising tests first, since the first good
+//! tree it finds bounds all others. Its heuristics don't know anything about
+//! the rules, though, while the optimal trees of earlier puzzles do: a
+//! [`TestHistory`] counts the first tests of optimal trees by their criteria
+//! card, and orders the first tests of later searches of the same or similar
+//! puzzles by those counts, see [`SolverBuilder::history`]. The top of the
+//! search takes most of the time, and below it, the heuristics know better
+//! than the history of other trees:
+//!
+//! ```
+//! use tm_solver::history::TestHistory;
+//! use tm_solver::solver::Solver;
+//!
+//! let mut history = TestHistory::default();
+//! let first = Solver::builder().rules(&[4, 9, 11, 14]).solve()?;
+//! history.record(&first.rules, &first.tree);
+//!
+//! let again = Solver::builder().rules(&[4, 9, 11, 14]).history(history.clone()).solve()?;
+//! assert_eq!(again.tree.max_depth(), first.tree.max_depth());
+//! let first_test = first.tree.get_tests(0).into_iter().next().unwrap();
+//! assert_eq!(history.order(&first.rules).score(first_test), (1, 0));
+//! # Ok::<(), tm_solver::error::TmSolverError>(())
+//! ```
+//!
+//! With the `std` feature, a history can be stored in a file of the
+//! [`format`](crate::format) module.
+//!
+//! [`SolverBuilder::history`]: crate::solver::SolverBuilder::history
+
+use alloc::vec::Vec;
+
+use serde::{Deserialize, Serialize};
+
+use crate::collections::HashMap;
+use crate::construct_tree::{BinaryTree, Test};
+
+/// How often every test was the first of the optimal trees of earlier
+/// puzzles, see the module documentation.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TestHistory {
+    puzzles: Vec<Record>,
+}
+
+/// The first tests of the optimal trees of one combination of rules.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Record {
+    /// the (zero-based) rules, in the order of the verifiers.
+    rules: Vec<usize>,
+    uses: Vec<Use>,
+}
+
+/// How often a test was the first of the optimal trees of a combination of
+/// rules.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Use {
+    /// the (zero-based) rule of the verifier.
+    rule: usize,
+    result: u8,
+    count: u32,
+}
+
+impl TestHistory {
+
+    /// Count the first test of the optimal tree of a puzzle with the given
+    /// (zero-based) rules.
+    pub fn record<T>(&mut self, rules: &[usize], tree: &BinaryTree<T>) {
+        let BinaryTree::Branch(b) = tree else {
+            return;
+        };
+        let (verifier, result) = b.test;
+        let rule = rules[verifier];
+        let i = match self.puzzles.iter().position(|p| p.rules == rules) {
+            Some(i) => i,
+            None => {
+                self.puzzles.push(Record { rules: rules.to_vec(), uses: Vec::new() });
+                self.puzzles.len() - 1
+            },
+        };
+        let uses = &mut self.puzzles[i].uses;
+        match uses.iter_mut().find(|u| u.rule == rule && u.result == result) {
+            Some(u) => u.count += 1,
+            None => uses.push(Use { rule, result, count: 1 }),
+        }
+    }
+
+    /// The number of puzzles recorded.
+    pub fn len(&self) -> usize {
+        self.puzzles.len()
+    }
+
+    /// Whether no puzzle has been recorded yet.
+    pub fn is_empty(&self) -> bool {
+        self.puzzles.is_empty()
+    }
+
+    /// How to order the first tests of a puzzle with the given (zero-based)
+    /// rules.
+    pub fn order(&self, rules: &[usize]) -> TestOrder {
+        let mut scores: HashMap<Test, (u32, u32)> = HashMap::new();
+        for puzzle in &self.puzzles {
+            let same = puzzle.rules == rules;
+            for u in &puzzle.uses {
+                for (verifier, _) in rules.iter().enumerate().filter(|(_, r)| **r == u.rule) {
+                    let score = scores.entry((verifier, u.result)).or_default();
+                    match same {
+                        true => score.0 += u.count,
+                        false => score.1 += u.count,
+                    }
+                }
+            }
+        }
+        TestOrder { scores }
+    }
+}
+
+#[cfg(feature = "std")]
+impl crate::format::Versioned for TestHistory {
+    const KIND: &'static str = "test_history";
+}
+
+/// Which first tests of a puzzle to try first, see [`TestHistory::order`].
+#[derive(Debug, Clone, Default)]
+pub struct TestOrder {
+    scores: HashMap<Test, (u32, u32)>,
+}
+
+impl TestOrder {
+
+    /// How often a test was the first of the optimal trees of the same
+    /// puzzle and of others sharing its rule. Tests with higher scores are tried first,
+    /// those of the same puzzle before all others.
+    pub fn score(&self, test: Test) -> (u32, u32) {
+        self.scores.get(&test).copied().unwrap_or_default()
+    }
+}
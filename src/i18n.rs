@@ -0,0 +1,326 @@
+//! Translations of the interactive prompts.
+//!
+//! Every language is a catalog of message templates, in which `{}` stands
+//! for an argument filled in with the [`tr`] macro, e. g.
+//! `tr!(start_of_round, 2)`. The language is selected with the --lang option
+//! or the TM_SOLVER_LANG environment variable and otherwise taken from the
+//! system locale, falling back to English.
+//!
+//! Only the guided session, practice games and the setup wizard are
+//! translated. The answers and commands are always given as y, n, back, fix,
+//! guess, save etc., so they work the same in every language.
+
+use std::fmt::Display;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The messages of one language.
+pub struct Language {
+    /// the language code, e. g. "en".
+    pub name: &'static str,
+    pub effort: &'static str,
+    pub start_of_round: &'static str,
+    pub use_combination: &'static str,
+    pub keep_combination: &'static str,
+    pub test: &'static str,
+    pub question: &'static str,
+    pub criterion: &'static str,
+    pub invalid_answer: &'static str,
+    pub answers_ignored: &'static str,
+    pub going_back: &'static str,
+    pub quit: &'static str,
+    pub nothing_to_undo: &'static str,
+    pub saved: &'static str,
+    pub save_failed: &'static str,
+    pub resuming: &'static str,
+    pub fixed: &'static str,
+    pub invalid_fix: &'static str,
+    pub fix_not_asked: &'static str,
+    pub fix_contradiction: &'static str,
+    pub reordered: &'static str,
+    pub invalid_ask: &'static str,
+    pub ask_unavailable: &'static str,
+    pub ask_useless: &'static str,
+    pub one_code: &'static str,
+    pub codes: &'static str,
+    pub codes_left: &'static str,
+    pub contradiction: &'static str,
+    pub give_again: &'static str,
+    pub suspects: &'static str,
+    pub no_suspects: &'static str,
+    pub check_failed: &'static str,
+    pub invalid_check: &'static str,
+    pub fix_suspect: &'static str,
+    pub number_range: &'static str,
+    pub no_more_input: &'static str,
+    pub found: &'static str,
+    pub final_check: &'static str,
+    pub your_code: &'static str,
+    pub stats: &'static str,
+    pub optimum: &'static str,
+    pub more_answers: &'static str,
+    pub not_enough_answers: &'static str,
+    pub spelled_code: &'static str,
+    pub round: &'static str,
+    pub code: &'static str,
+    pub wizard_start: &'static str,
+    pub ask_verifiers: &'static str,
+    pub ask_rule: &'static str,
+    pub rule_used: &'static str,
+    pub no_match: &'static str,
+    pub practice_start: &'static str,
+    pub practice_rules: &'static str,
+    pub practice_round: &'static str,
+    pub already_tested: &'static str,
+    pub invalid_test: &'static str,
+    pub no_verifier: &'static str,
+    pub correct_code: &'static str,
+    pub wrong_code: &'static str,
+    pub practice_stats: &'static str,
+    pub solver_stats: &'static str,
+    pub code_was: &'static str,
+}
+
+/// All available languages. The first one is the default.
+pub const LANGUAGES: [Language; 3] = [
+    Language {
+        name: "en",
+        effort: "Questions needed: at most {}, {} on average. Rounds needed: at most {}, {} on average.",
+        start_of_round: "Start of round {}",
+        use_combination: "Use the following combination: {}",
+        keep_combination: "Keep using the combination {} for this round.",
+        test: "Test {}",
+        question: "Does {} yield a {} ? (y/n, or another key for more commands)",
+        criterion: "(card {}: {})",
+        invalid_answer: "Please input y or n (or the answers for the rest of the round at once, e. g. ynn). You can also input b to undo the last answer, r to show the remaining codes, h for a hint, s to show the note sheet, ask LETTER y/n to ask another verifier first, fix LETTER y/n to correct an earlier answer, save [FILE] to save the game or q to quit.",
+        answers_ignored: "Only the first {} answers belong to this round, ignoring the rest.",
+        going_back: "Going back to the previous question.",
+        quit: "Quitting. Unless you saved the game, your answers are lost.",
+        nothing_to_undo: "There is no answer to undo yet.",
+        saved: "Saved the game to {}. Resume it with: tm_solver load {}",
+        save_failed: "Could not save the game: {}",
+        resuming: "Resuming the game where you left off.",
+        fixed: "Correcting the answer for {}. Working out a new strategy from all answers, going on with the current round.",
+        invalid_fix: "To correct an answer, input fix, the verifier letter and the right answer, e. g. fix B n.",
+        fix_not_asked: "There was no question about verifier {} yet.",
+        fix_contradiction: "With this correction, your answers don't match any possible code.",
+        reordered: "Asked {} first. Planning the rest of the round with {} anew.",
+        invalid_ask: "To ask another verifier of this round first, input ask, its letter and its answer, e. g. ask C y.",
+        ask_unavailable: "Verifier {} can't be asked in this round anymore.",
+        ask_useless: "With this combination, verifier {} can't tell the remaining codes apart.",
+        one_code: "1 code",
+        codes: "{} codes",
+        codes_left: "{} left: {}",
+        contradiction: "Your answers don't match any possible code, so one of them was probably mis-read.",
+        give_again: "How many of your last answers do you want to give again? (1-{})",
+        suspects: "Your answers would match a code again if one of these was different: {}",
+        no_suspects: "No single answer explains this, so please double-check the criteria cards.",
+        check_failed: "If a verifier doesn't yield a {} for this combination after all, input its letter to find the answer that was probably mis-read. Otherwise, press Enter.",
+        invalid_check: "Please input the letter of a verifier, e. g. B, or press Enter.",
+        fix_suspect: "To correct one of them, input fix, its letter and the right answer, e. g. fix B n. Otherwise, press Enter.",
+        number_range: "Please input a number from {} to {}.",
+        no_more_input: "No more input, aborting.",
+        found: "Found a solution!",
+        final_check: "Before guessing, you can check the combination {} on every verifier. Each of them should yield a {} :",
+        your_code: "Your code is: {}",
+        stats: "Questions asked: {}, rounds played: {}.",
+        optimum: "Optimal strategy: at most {} questions ({} on average), at most {} rounds ({} on average).",
+        more_answers: "There are more answers than questions, ignoring the rest.",
+        not_enough_answers: "Not enough answers to find the code. Next, use {} on Test {}.",
+        spelled_code: "blue {}, yellow {}, purple {}",
+        round: "Round",
+        code: "Code",
+        wizard_start: "No puzzle given, let's set one up. (Run with --help for all options.)",
+        ask_verifiers: "How many verifiers does the puzzle have? (4-6)",
+        ask_rule: "Criteria card for verifier {} (number, or text to search):",
+        rule_used: "Criteria card {} is already used by another verifier.",
+        no_match: "No criteria card matches \"{}\".",
+        practice_start: "The machine picked a secret code. Test a code on a verifier by inputting both, e. g. 241 A.",
+        practice_rules: "Every code can be tested on up to {} verifiers per round. Input guess CODE once you know it, or q to give up.",
+        practice_round: "Round {}: {}",
+        already_tested: "You already tested verifier {} with {} this round.",
+        invalid_test: "Please input a code and a verifier letter, e. g. 241 A.",
+        no_verifier: "There is no verifier {} in this puzzle.",
+        correct_code: "Correct, the code is {}!",
+        wrong_code: "That's not the code.",
+        practice_stats: "You needed {} questions in {} rounds, with {} wrong guesses.",
+        solver_stats: "The solver needs {} questions in {} rounds for this code.",
+        code_was: "The code was {}.",
+    },
+    Language {
+        name: "de",
+        effort: "Benötigte Fragen: höchstens {}, im Schnitt {}. Benötigte Runden: höchstens {}, im Schnitt {}.",
+        start_of_round: "Beginn von Runde {}",
+        use_combination: "Verwende folgende Kombination: {}",
+        keep_combination: "Verwende in dieser Runde weiter die Kombination {}.",
+        test: "Prüfer {}",
+        question: "Zeigt {} ein {} ? (y/n, oder eine andere Taste für weitere Befehle)",
+        criterion: "(Karte {}: {})",
+        invalid_answer: "Bitte gib y oder n ein (oder die Antworten für den Rest der Runde auf einmal, z. B. ynn). Außerdem: b nimmt die letzte Antwort zurück, r zeigt die übrigen Codes, h gibt einen Hinweis, s zeigt den Notizzettel, ask BUCHSTABE y/n fragt zuerst einen anderen Prüfer, fix BUCHSTABE y/n korrigiert eine frühere Antwort, save [DATEI] speichert das Spiel und q beendet es.",
+        answers_ignored: "Nur die ersten {} Antworten gehören zu dieser Runde, der Rest wird ignoriert.",
+        going_back: "Zurück zur vorigen Frage.",
+        quit: "Programm wird beendet. Wenn du das Spiel nicht gespeichert hast, gehen deine Antworten verloren.",
+        nothing_to_undo: "Es gibt noch keine Antwort, die zurückgenommen werden kann.",
+        saved: "Spiel in {} gespeichert. Weiterspielen mit: tm_solver load {}",
+        save_failed: "Das Spiel konnte nicht gespeichert werden: {}",
+        resuming: "Das Spiel geht dort weiter, wo du aufgehört hast.",
+        fixed: "Die Antwort für {} wird korrigiert. Aus allen Antworten wird eine neue Strategie berechnet, die die laufende Runde fortsetzt.",
+        invalid_fix: "Gib zum Korrigieren fix, den Buchstaben des Prüfers und die richtige Antwort ein, z. B. fix B n.",
+        fix_not_asked: "Zu Prüfer {} gab es noch keine Frage.",
+        fix_contradiction: "Mit dieser Korrektur passen deine Antworten zu keinem möglichen Code.",
+        reordered: "{} wurde zuerst gefragt. Der Rest der Runde mit {} wird neu geplant.",
+        invalid_ask: "Um zuerst einen anderen Prüfer dieser Runde zu fragen, gib ask, seinen Buchstaben und seine Antwort ein, z. B. ask C y.",
+        ask_unavailable: "Prüfer {} kann in dieser Runde nicht mehr gefragt werden.",
+        ask_useless: "Mit dieser Kombination kann Prüfer {} die übrigen Codes nicht unterscheiden.",
+        one_code: "1 Code",
+        codes: "{} Codes",
+        codes_left: "{} übrig: {}",
+        contradiction: "Deine Antworten passen zu keinem möglichen Code, wahrscheinlich wurde eine falsch abgelesen.",
+        give_again: "Wie viele deiner letzten Antworten möchtest du neu geben? (1-{})",
+        suspects: "Deine Antworten würden wieder zu einem Code passen, wenn eine dieser anders wäre: {}",
+        no_suspects: "Keine einzelne Antwort erklärt das, bitte prüfe die Kriterienkarten noch einmal.",
+        check_failed: "Falls ein Prüfer für diese Kombination doch kein {} zeigt, gib seinen Buchstaben ein, um die wahrscheinlich falsch abgelesene Antwort zu finden. Sonst drücke Enter.",
+        invalid_check: "Bitte gib den Buchstaben eines Prüfers ein, z. B. B, oder drücke Enter.",
+        fix_suspect: "Um eine davon zu korrigieren, gib fix, ihren Buchstaben und die richtige Antwort ein, z. B. fix B n. Sonst drücke Enter.",
+        number_range: "Bitte gib eine Zahl von {} bis {} ein.",
+        no_more_input: "Keine Eingabe mehr, Abbruch.",
+        found: "Lösung gefunden!",
+        final_check: "Vor dem Raten kannst du die Kombination {} an jedem Prüfer testen. Jeder sollte ein {} zeigen:",
+        your_code: "Dein Code ist: {}",
+        stats: "Gestellte Fragen: {}, gespielte Runden: {}.",
+        optimum: "Optimale Strategie: höchstens {} Fragen (im Schnitt {}), höchstens {} Runden (im Schnitt {}).",
+        more_answers: "Es gibt mehr Antworten als Fragen, der Rest wird ignoriert.",
+        not_enough_answers: "Die Antworten reichen nicht, um den Code zu finden. Als Nächstes: {} an Prüfer {}.",
+        spelled_code: "blau {}, gelb {}, lila {}",
+        round: "Runde",
+        code: "Code",
+        wizard_start: "Kein Rätsel angegeben, richten wir eins ein. (Alle Optionen mit --help.)",
+        ask_verifiers: "Wie viele Prüfer hat das Rätsel? (4-6)",
+        ask_rule: "Kriterienkarte für Prüfer {} (Nummer, oder Text zum Suchen):",
+        rule_used: "Kriterienkarte {} wird schon von einem anderen Prüfer verwendet.",
+        no_match: "Keine Kriterienkarte passt zu \"{}\".",
+        practice_start: "Die Maschine hat einen geheimen Code gewählt. Teste einen Code an einem Verifizierer, indem du beides eingibst, z. B. 241 A.",
+        practice_rules: "Jeder Code kann pro Runde an bis zu {} Verifizierern getestet werden. Gib guess CODE ein, sobald du ihn kennst, oder q zum Aufgeben.",
+        practice_round: "Runde {}: {}",
+        already_tested: "Du hast Verifizierer {} in dieser Runde schon mit {} getestet.",
+        invalid_test: "Bitte gib einen Code und einen Verifizierer-Buchstaben ein, z. B. 241 A.",
+        no_verifier: "Dieses Rätsel hat keinen Verifizierer {}.",
+        correct_code: "Richtig, der Code ist {}!",
+        wrong_code: "Das ist nicht der Code.",
+        practice_stats: "Du hast {} Fragen in {} Runden gebraucht, mit {} falschen Tipps.",
+        solver_stats: "Der Löser braucht für diesen Code {} Fragen in {} Runden.",
+        code_was: "Der Code war {}.",
+    },
+    Language {
+        name: "fr",
+        effort: "Questions nécessaires : au plus {}, {} en moyenne. Manches nécessaires : au plus {}, {} en moyenne.",
+        start_of_round: "Début de la manche {}",
+        use_combination: "Utilisez la combinaison suivante : {}",
+        keep_combination: "Continuez avec la combinaison {} pour cette manche.",
+        test: "Vérificateur {}",
+        question: "Le {} donne-t-il un {} ? (y/n, ou une autre touche pour plus de commandes)",
+        criterion: "(carte {} : {})",
+        invalid_answer: "Veuillez saisir y ou n (ou les réponses pour le reste de la manche en une fois, p. ex. ynn). Vous pouvez aussi saisir b pour annuler la dernière réponse, r pour voir les codes restants, h pour un indice, s pour voir la feuille de notes, ask LETTRE y/n pour interroger d'abord un autre vérificateur, fix LETTRE y/n pour corriger une réponse précédente, save [FICHIER] pour sauvegarder ou q pour quitter.",
+        answers_ignored: "Seules les {} premières réponses concernent cette manche, le reste est ignoré.",
+        going_back: "Retour à la question précédente.",
+        quit: "Fin du programme. Si vous n'avez pas sauvegardé la partie, vos réponses sont perdues.",
+        nothing_to_undo: "Il n'y a pas encore de réponse à annuler.",
+        saved: "Partie sauvegardée dans {}. Reprenez-la avec : tm_solver load {}",
+        save_failed: "Impossible de sauvegarder la partie : {}",
+        resuming: "La partie reprend là où vous l'avez laissée.",
+        fixed: "Correction de la réponse pour le {}. Une nouvelle stratégie est calculée à partir de toutes les réponses, en poursuivant la manche en cours.",
+        invalid_fix: "Pour corriger une réponse, saisissez fix, la lettre du vérificateur et la bonne réponse, p. ex. fix B n.",
+        fix_not_asked: "Il n'y a pas encore eu de question sur le vérificateur {}.",
+        fix_contradiction: "Avec cette correction, vos réponses ne correspondent à aucun code possible.",
+        reordered: "Le {} a été interrogé en premier. Le reste de la manche avec {} est replanifié.",
+        invalid_ask: "Pour interroger d'abord un autre vérificateur de cette manche, saisissez ask, sa lettre et sa réponse, p. ex. ask C y.",
+        ask_unavailable: "Le vérificateur {} ne peut plus être interrogé dans cette manche.",
+        ask_useless: "Avec cette combinaison, le vérificateur {} ne peut pas distinguer les codes restants.",
+        one_code: "1 code",
+        codes: "{} codes",
+        codes_left: "{} restant(s) : {}",
+        contradiction: "Vos réponses ne correspondent à aucun code possible, l'une d'elles a sans doute été mal lue.",
+        give_again: "Combien de vos dernières réponses voulez-vous redonner ? (1-{})",
+        suspects: "Vos réponses correspondraient de nouveau à un code si l'une de celles-ci était différente : {}",
+        no_suspects: "Aucune réponse seule ne l'explique, veuillez vérifier les cartes critère.",
+        check_failed: "Si un vérificateur n'affiche finalement pas de {} pour cette combinaison, saisissez sa lettre pour trouver la réponse sans doute mal lue. Sinon, appuyez sur Entrée.",
+        invalid_check: "Veuillez saisir la lettre d'un vérificateur, p. ex. B, ou appuyer sur Entrée.",
+        fix_suspect: "Pour corriger l'une d'elles, saisissez fix, sa lettre et la bonne réponse, p. ex. fix B n. Sinon, appuyez sur Entrée.",
+        number_range: "Veuillez saisir un nombre de {} à {}.",
+        no_more_input: "Plus d'entrée, abandon.",
+        found: "Solution trouvée !",
+        final_check: "Avant de deviner, vous pouvez tester la combinaison {} sur chaque vérificateur. Chacun devrait donner un {} :",
+        your_code: "Votre code est : {}",
+        stats: "Questions posées : {}, manches jouées : {}.",
+        optimum: "Stratégie optimale : au plus {} questions ({} en moyenne), au plus {} manches ({} en moyenne).",
+        more_answers: "Il y a plus de réponses que de questions, le reste est ignoré.",
+        not_enough_answers: "Pas assez de réponses pour trouver le code. Ensuite : {} sur le vérificateur {}.",
+        spelled_code: "bleu {}, jaune {}, violet {}",
+        round: "Manche",
+        code: "Code",
+        wizard_start: "Aucune énigme donnée, configurons-en une. (Toutes les options avec --help.)",
+        ask_verifiers: "Combien de vérificateurs l'énigme a-t-elle ? (4-6)",
+        ask_rule: "Carte critère du vérificateur {} (numéro, ou texte à rechercher) :",
+        rule_used: "La carte critère {} est déjà utilisée par un autre vérificateur.",
+        no_match: "Aucune carte critère ne correspond à \"{}\".",
+        practice_start: "La machine a choisi un code secret. Testez un code sur un vérificateur en saisissant les deux, p. ex. 241 A.",
+        practice_rules: "Chaque code peut être testé sur jusqu'à {} vérificateurs par manche. Saisissez guess CODE dès que vous le connaissez, ou q pour abandonner.",
+        practice_round: "Manche {} : {}",
+        already_tested: "Vous avez déjà testé le vérificateur {} avec {} dans cette manche.",
+        invalid_test: "Veuillez saisir un code et la lettre d'un vérificateur, p. ex. 241 A.",
+        no_verifier: "Cette énigme n'a pas de vérificateur {}.",
+        correct_code: "Bravo, le code est {} !",
+        wrong_code: "Ce n'est pas le code.",
+        practice_stats: "Il vous a fallu {} questions en {} manches, avec {} mauvaises propositions.",
+        solver_stats: "Le solveur a besoin de {} questions en {} manches pour ce code.",
+        code_was: "Le code était {}.",
+    },
+];
+
+static LANGUAGE: AtomicUsize = AtomicUsize::new(0);
+
+/// Select the language with the given name. Returns false if there's no
+/// such language.
+pub fn set_language(name: &str) -> bool {
+    match LANGUAGES.iter().position(|l| l.name == name) {
+        Some(i) => {
+            LANGUAGE.store(i, Ordering::Relaxed);
+            true
+        },
+        None => false,
+    }
+}
+
+/// The language of the system locale, if there's a catalog for it.
+pub fn detect() -> Option<&'static str> {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+        .filter_map(|v| std::env::var(v).ok())
+        .find(|l| !l.is_empty())?;
+    LANGUAGES.iter().map(|l| l.name).find(|name| locale.starts_with(name))
+}
+
+/// The selected language.
+pub fn language() -> &'static Language {
+    &LANGUAGES[LANGUAGE.load(Ordering::Relaxed)]
+}
+
+/// Replace the placeholders of a template with the given arguments, in
+/// order.
+pub fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut parts = template.split("{}");
+    let mut text = parts.next().unwrap_or_default().to_string();
+    for (part, arg) in parts.zip(args.iter().map(|a| a.to_string()).chain(std::iter::repeat(String::new()))) {
+        text.push_str(&arg);
+        text.push_str(part);
+    }
+    text
+}
+
+/// Translate a message of the selected language, e. g.
+/// `tr!(your_code, code.colored())`.
+#[macro_export]
+macro_rules! tr {
+    ($key:ident $(, $arg:expr)* $(,)?) => {
+        $crate::i18n::fill($crate::i18n::language().$key, &[$(&$arg),*])
+    };
+}
+pub use crate::tr;
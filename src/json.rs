@@ -0,0 +1,95 @@
+//! Machine-readable output for tools wrapping the solver.
+//!
+//! With the --json flag, the decorative output (progress spinners, guided
+//! dialogs) is suppressed and a single JSON object is printed instead. It
+//! contains the puzzle's unique solutions, the solution tree and the result
+//! of following the tree with the given answers, e. g.
+//!
+//! ```text
+//! {"cards":[{"letter":"A","rule":3}, ...],
+//!  "unique_solutions":[{"code":{"blue":2,"yellow":4,"purple":1},"results":[0,1,0,2,1]}, ...],
+//!  "tree":{"Branch":{"test":[3,0],"code":{...},"correct":{...},"incorrect":{...}}},
+//!  "result":null,
+//!  "next":{"code":{...},"letter":"D"}}
+//! ```
+//!
+//! Errors are printed as `{"error":"..."}`.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::Serialize;
+
+use tm_solver::construct_tree::BinaryTree;
+use tm_solver::rules::Code;
+use crate::Prepared;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Turn JSON output on or off.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether JSON should be printed instead of the usual output.
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+#[derive(Serialize)]
+struct Card {
+    letter: char,
+    rule: usize,
+}
+
+#[derive(Serialize)]
+struct Solution<'a> {
+    code: &'a Code,
+    /// the category each verifier puts the code in.
+    results: &'a [u8],
+}
+
+/// The next test to perform, if the answers weren't enough to find the code.
+#[derive(Serialize)]
+struct Next<'a> {
+    code: &'a Code,
+    letter: char,
+}
+
+#[derive(Serialize)]
+struct Output<'a> {
+    cards: Vec<Card>,
+    unique_solutions: Vec<Solution<'a>>,
+    tree: &'a BinaryTree<Code>,
+    /// the code found, if any.
+    result: Option<&'a Code>,
+    next: Option<Next<'a>>,
+}
+
+/// Print a puzzle and the node of its solution tree reached so far.
+/// `round_code` is the code of the current round, if one has started.
+pub fn print_result(prepared: &Prepared, node: &BinaryTree<Code>, round_code: Option<&Code>) {
+    let (result, next) = match node {
+        BinaryTree::Leaf(c) => (Some(c), None),
+        BinaryTree::Branch(b) => {
+            let code = b.code.as_ref().or(round_code).unwrap();
+            (None, Some(Next { code, letter: prepared.letters[b.test.0] }))
+        },
+    };
+    let output = Output {
+        cards: prepared.letters.iter().zip(&prepared.rules)
+            .map(|(l, r)| Card { letter: *l, rule: r + 1 })
+            .collect(),
+        unique_solutions: prepared.unique_solutions.iter()
+            .map(|(results, code)| Solution { code, results })
+            .collect(),
+        tree: &prepared.tree,
+        result,
+        next,
+    };
+    println!("{}", serde_json::to_string(&output).unwrap());
+}
+
+/// Print an error message.
+pub fn print_error(message: &str) {
+    println!("{}", serde_json::json!({ "error": message }));
+}
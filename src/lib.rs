@@ -0,0 +1,458 @@
+//! A solver for the board game Turing Machine.
+//!
+//! A puzzle is given by its criteria cards, i. e. indices into [`rules::RULES`].
+//! Solving it takes these steps:
+//!
+//! 1. Create all possible codes with [`all_codes`], or those of another
+//!    [`space::CodeSpace`].
+//! 2. Remove the codes that don't require all verifiers with
+//!    [`find_super_unique`], since those can't be the solution.
+//! 3. Group the remaining codes by their test results with
+//!    [`categorize_codes`] and keep the ones that can be identified by them
+//!    with [`find_unique`].
+//! 4. Construct an optimal solution tree for those with
+//!    [`construct_tree::optimal_tree`].
+//!
+//! ```
+//! use tm_solver::construct_tree::{self, TreeSettings};
+//!
+//! // the first challenge in the manual, with zero-based rule indices.
+//! let rules = [3, 8, 10, 13];
+//! let mut codes = tm_solver::all_codes();
+//! for code in tm_solver::find_super_unique(&codes, &rules).keys() {
+//!     codes.remove(code);
+//! }
+//! let solutions = tm_solver::categorize_codes(&codes, &rules);
+//! let unique = tm_solver::find_unique(&solutions);
+//! let settings = TreeSettings { solution_map: Some(&solutions), ..TreeSettings::new(3) };
+//! let tree = construct_tree::optimal_tree(&unique, settings).unwrap();
+//! assert_eq!(tree.leaf_count(), unique.len());
+//! ```
+//!
+//! [`solver::Solver`] runs all of these steps at once, and a
+//! [`puzzle::Puzzle`] bundles everything that defines a puzzle. Steps 2 and 3
+//! work for other codes and rules as well, see [`RuleSet`], and are faster
+//! with the rules evaluated once, see [`matrix`]. If a rule is added or
+//! left out, [`add_rule`] and [`remove_rule`] update the groups of step 3
+//! instead of grouping all codes again. Searches of similar puzzles can
+//! learn from each other, see [`history`].
+//!
+//! The [`style`] and [`i18n`] modules control how codes and trees are
+//! printed, and [`export`] prepares trees for other programs, which are
+//! stored in the versioned files of [`format`]. Long steps can
+//! report their progress, see [`progress`], and async code can run them
+//! without blocking, see `asynchronous`. To play through a tree, see
+//! [`session`], to walk through it yourself, see [`traverse`], and to follow
+//! it for a known code, see [`simulate`].
+//!
+//! # Without std
+//!
+//! The deduction logic only needs an allocator: the rules, code spaces, the
+//! functions above, the tree search, [`solver::Solver`], game sessions and
+//! the C interface
+//! build without the standard library if the default features are turned
+//! off, e. g. for microcontrollers:
+//!
+//! ```text
+//! tm_solver = { version = "0.1", default-features = false }
+//! ```
+//!
+//! Everything that prints, reads the environment or talks to other programs
+//! needs the `std` feature. Maps and sets then come from `hashbrown`, see
+//! [`collections`].
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "async")]
+pub mod asynchronous;
+pub mod construct_tree;
+pub mod error;
+#[cfg(feature = "std")]
+pub mod export;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "std")]
+pub mod format;
+#[cfg(feature = "std")]
+pub mod hash;
+pub mod history;
+#[cfg(feature = "std")]
+pub mod i18n;
+pub mod matrix;
+pub mod progress;
+#[cfg(feature = "protobuf")]
+pub mod proto;
+pub mod puzzle;
+pub mod rules;
+pub mod session;
+pub mod simulate;
+pub mod solver;
+pub mod space;
+#[cfg(feature = "std")]
+pub mod style;
+pub mod traverse;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+/// The maps and sets used throughout the crate: those of the standard
+/// library, or of `hashbrown` without it.
+pub mod collections {
+    #[cfg(not(feature = "std"))]
+    pub use hashbrown::{HashMap, HashSet};
+    #[cfg(feature = "std")]
+    pub use std::collections::{HashMap, HashSet};
+}
+
+use alloc::vec::Vec;
+use core::hash::Hash;
+
+use collections::{HashMap, HashSet};
+
+use construct_tree::{Feasible, Results};
+use rules::{Code, CATEGORY_COUNTS, RULES};
+
+/// A set of rules sorting codes into categories, e. g. the criteria cards of
+/// a puzzle.
+///
+/// Slices, arrays and vectors of rule indices (zero-based indices into
+/// [`RULES`]) are rule sets for [`Code`]s, and so are slices, arrays and
+/// vectors of functions, for any type of code:
+///
+/// ```
+/// use tm_solver::RuleSet;
+///
+/// let parity = [|n: &u8| Some(n % 2), |n: &u8| Some((*n > 2) as u8)];
+/// assert_eq!(parity.rule_count(), 2);
+/// assert_eq!(parity.category(1, &3), Some(1));
+/// ```
+pub trait RuleSet<C> {
+
+    /// The number of rules.
+    fn rule_count(&self) -> usize;
+
+    /// The category rule `i` puts the code in, or None if the rule can't be
+    /// applied to it.
+    fn category(&self, i: usize, code: &C) -> Option<u8>;
+
+    /// How many categories rule `i` has, i. e. a bound on its results, if
+    /// known. Lets [`categorize_codes`] group codes in a table instead of a
+    /// map.
+    fn category_count(&self, _i: usize) -> Option<u8> {
+        None
+    }
+}
+
+impl RuleSet<Code> for [usize] {
+    fn rule_count(&self) -> usize {
+        self.len()
+    }
+
+    fn category(&self, i: usize, code: &Code) -> Option<u8> {
+        RULES[self[i]](code)
+    }
+
+    fn category_count(&self, i: usize) -> Option<u8> {
+        Some(CATEGORY_COUNTS[self[i]])
+    }
+}
+
+impl<C, F: Fn(&C) -> Option<u8>> RuleSet<C> for [F] {
+    fn rule_count(&self) -> usize {
+        self.len()
+    }
+
+    fn category(&self, i: usize, code: &C) -> Option<u8> {
+        self[i](code)
+    }
+}
+
+impl<C, R, const N: usize> RuleSet<C> for [R; N] where [R]: RuleSet<C> {
+    fn rule_count(&self) -> usize {
+        N
+    }
+
+    fn category(&self, i: usize, code: &C) -> Option<u8> {
+        self[..].category(i, code)
+    }
+
+    fn category_count(&self, i: usize) -> Option<u8> {
+        self[..].category_count(i)
+    }
+}
+
+impl<C, R> RuleSet<C> for Vec<R> where [R]: RuleSet<C> {
+    fn rule_count(&self) -> usize {
+        self.len()
+    }
+
+    fn category(&self, i: usize, code: &C) -> Option<u8> {
+        self[..].category(i, code)
+    }
+
+    fn category_count(&self, i: usize) -> Option<u8> {
+        self[..].category_count(i)
+    }
+}
+
+/// A rule set with one of its rules left out.
+struct Without<'a, R: ?Sized> {
+    rules: &'a R,
+    skipped: usize,
+}
+
+impl<C, R: RuleSet<C> + ?Sized> RuleSet<C> for Without<'_, R> {
+    fn rule_count(&self) -> usize {
+        self.rules.rule_count() - 1
+    }
+
+    fn category(&self, i: usize, code: &C) -> Option<u8> {
+        self.rules.category(self.index(i), code)
+    }
+
+    fn category_count(&self, i: usize) -> Option<u8> {
+        self.rules.category_count(self.index(i))
+    }
+}
+
+impl<R: ?Sized> Without<'_, R> {
+
+    /// The index of the `i`th rule that isn't left out.
+    fn index(&self, i: usize) -> usize {
+        if i < self.skipped { i } else { i + 1 }
+    }
+}
+
+/// Group codes by the results the given rules yield for them. Codes that
+/// some rule can't be applied to are left out. Panics with more than
+/// [`MAX_VERIFIERS`](construct_tree::MAX_VERIFIERS) rules.
+///
+/// ```
+/// let codes = [1u8, 2, 3, 4];
+/// let rules = [|n: &u8| Some(n % 2)];
+/// let solutions = tm_solver::categorize_codes(&codes, &rules);
+/// assert_eq!(solutions[&[0][..]], vec![2, 4]);
+/// assert_eq!(solutions[&[1][..]], vec![1, 3]);
+/// ```
+///
+/// Rule sets that know their [`category_count`](RuleSet::category_count)s,
+/// e. g. rule indices, group codes in a table instead, with the same result:
+///
+/// ```
+/// use tm_solver::rules::{Code, RULES};
+/// use tm_solver::space::CodeSpace;
+///
+/// let codes: Vec<Code> = CodeSpace::standard().codes().collect();
+/// let rules = [3, 8, 10, 13, 19];
+/// let functions = rules.map(|r| RULES[r]);
+/// assert_eq!(tm_solver::categorize_codes(&codes, &rules), tm_solver::categorize_codes(&codes, &functions));
+/// ```
+pub fn categorize_codes<'a, C, R>(codes: impl IntoIterator<Item = &'a C>, rules: &R) -> HashMap<Results, Vec<C>>
+where C: Clone + 'a, R: RuleSet<C> + ?Sized {
+    let rule_count = rules.rule_count();
+    let counts: Option<Vec<usize>> = (0..rule_count).map(|rule| rules.category_count(rule).map(usize::from)).collect();
+    if let Some(counts) = counts.filter(|c| c.iter().product::<usize>() <= TABLE_SIZE) {
+        return categorize_table(codes, rules, &counts);
+    }
+    let mut solutions: HashMap<Results, Vec<C>> = HashMap::new();
+    'codes: for code in codes {
+        let mut results = Results::new();
+        for rule in 0..rule_count {
+            let Some(category) = rules.category(rule, code) else {
+                continue 'codes;
+            };
+            results.push(category);
+        }
+        solutions.entry(results).or_default().push(code.clone());
+    }
+    solutions
+}
+
+/// The largest table [`categorize_codes`] groups codes in, enough for any
+/// six criteria cards.
+const TABLE_SIZE: usize = 4096;
+
+/// [`categorize_codes`] for rule sets whose category counts are known: every
+/// combination of results gets a slot in a table, so grouping a code is a
+/// single write.
+fn categorize_table<'a, C, R>(codes: impl IntoIterator<Item = &'a C>, rules: &R, counts: &[usize])
+    -> HashMap<Results, Vec<C>>
+where C: Clone + 'a, R: RuleSet<C> + ?Sized {
+    let mut table: Vec<Vec<C>> = (0..counts.iter().product()).map(|_| Vec::new()).collect();
+    'codes: for code in codes {
+        let mut slot = 0;
+        for (rule, count) in counts.iter().enumerate() {
+            let Some(category) = rules.category(rule, code) else {
+                continue 'codes;
+            };
+            slot = slot * count + usize::from(category);
+        }
+        table[slot].push(code.clone());
+    }
+    table.into_iter().enumerate().filter(|(_, codes)| !codes.is_empty()).map(|(mut slot, codes)| {
+        let mut results: Results = counts.iter().map(|_| 0).collect();
+        for (result, count) in results.iter_mut().zip(counts).rev() {
+            *result = (slot % count) as u8;
+            slot /= count;
+        }
+        (results, codes)
+    }).collect()
+}
+
+/// Group codes by their results with one more rule, given their groups
+/// without it: every group is split by the new rule's results, which are
+/// appended to the keys, so only the new rule is applied. Codes it can't be
+/// applied to are left out, like with [`categorize_codes`].
+///
+/// ```
+/// use tm_solver::matrix::RuleMatrix;
+/// use tm_solver::space::CodeSpace;
+/// use tm_solver::RuleSet;
+///
+/// let codes: Vec<_> = CodeSpace::standard().codes().collect();
+/// let matrix = RuleMatrix::new(&CodeSpace::standard(), &[3, 8, 10, 13]);
+/// let three = tm_solver::categorize_codes(&codes, &[3, 8, 10]);
+/// let four = tm_solver::add_rule(&three, |code| matrix.category(3, code));
+/// assert_eq!(four, tm_solver::categorize_codes(&codes, &matrix));
+/// ```
+pub fn add_rule<C: Clone>(solutions: &HashMap<Results, Vec<C>>, rule: impl Fn(&C) -> Option<u8>)
+    -> HashMap<Results, Vec<C>> {
+    let mut refined: HashMap<Results, Vec<C>> = HashMap::new();
+    for (results, codes) in solutions {
+        for code in codes {
+            let Some(category) = rule(code) else {
+                continue;
+            };
+            let mut key = *results;
+            key.push(category);
+            refined.entry(key).or_default().push(code.clone());
+        }
+    }
+    refined
+}
+
+/// Group codes by their results with the rule at index `skipped` left out,
+/// given their groups by all rules: groups whose results only differ by
+/// that rule are merged. Codes the rule can't be applied to aren't part of
+/// any group, so only those are grouped anew, from `codes`. Within the
+/// groups, the codes may be in another order than with
+/// [`categorize_codes`].
+///
+/// ```
+/// use tm_solver::space::CodeSpace;
+///
+/// let codes: Vec<_> = CodeSpace::standard().codes().collect();
+/// let all = tm_solver::categorize_codes(&codes, &[3, 8, 14, 13]);
+/// let mut merged = tm_solver::remove_rule(&all, &codes, &[3, 8, 14, 13], 2);
+/// merged.values_mut().for_each(|group| group.sort_by_key(|c| (c.blue, c.yellow, c.purple)));
+/// assert_eq!(merged, tm_solver::categorize_codes(&codes, &[3, 8, 13]));
+/// ```
+pub fn remove_rule<'a, C, R>(solutions: &HashMap<Results, Vec<C>>, codes: impl IntoIterator<Item = &'a C>, rules: &R,
+    skipped: usize) -> HashMap<Results, Vec<C>>
+where C: Clone + 'a, R: RuleSet<C> + ?Sized {
+    let inapplicable: Vec<&C> = codes.into_iter().filter(|code| rules.category(skipped, code).is_none()).collect();
+    let mut merged = categorize_codes(inapplicable, &Without { rules, skipped });
+    for (results, codes) in solutions {
+        let mut key = *results;
+        key.remove(skipped);
+        merged.entry(key).or_default().extend(codes.iter().cloned());
+    }
+    merged
+}
+
+/// Find the codes that are the only ones yielding their results, along with
+/// those results.
+///
+/// ```
+/// let codes = [1u8, 2, 3];
+/// let rules = [|n: &u8| Some(n % 2)];
+/// let unique = tm_solver::find_unique(&tm_solver::categorize_codes(&codes, &rules));
+/// assert_eq!(unique, vec![([0].into(), 2)]);
+/// ```
+pub fn find_unique<C: Clone>(solutions: &HashMap<Results, Vec<C>>) -> Vec<Feasible<C>> {
+    solutions.iter().filter_map(|(k, v)| {
+        if v.len() == 1 {
+            return Some((*k, v[0].clone()));
+        }
+        None
+    }).collect()
+}
+
+/// Create all possible 3-digit codes of the game, i. e. the codes of
+/// [`CodeSpace::standard`](space::CodeSpace::standard).
+pub fn all_codes() -> HashSet<Code> {
+    space::CodeSpace::standard().all()
+}
+
+/// Find all codes that would already be unique if one of the rules was left
+/// out. Those can't be the solution, since a puzzle always requires all of
+/// its verifiers.
+///
+/// Returns each of those codes along with the index of the (first) rule that
+/// isn't needed to identify it.
+///
+/// ```
+/// let codes = [1u8, 2, 3, 4];
+/// let rules = [|n: &u8| Some(n % 2), |n: &u8| Some((*n > 3) as u8)];
+/// let super_unique = tm_solver::find_super_unique(&codes, &rules);
+/// // 4 is the only code greater than 3, the first rule isn't needed for it.
+/// assert_eq!(super_unique.len(), 1);
+/// assert_eq!(super_unique[&4], 0);
+/// ```
+pub fn find_super_unique<'a, C, R>(codes: impl IntoIterator<Item = &'a C> + Clone, rules: &R) -> HashMap<C, usize>
+where C: Clone + Eq + Hash + 'a, R: RuleSet<C> + ?Sized {
+    let all = categorize_codes(codes.clone(), rules);
+    leave_out(&all, codes, rules, 0..rules.rule_count())
+}
+
+/// [`find_super_unique`], with the rules split among `threads` threads, each
+/// leaving out some of them. Only available with the `std` feature.
+///
+/// ```
+/// use tm_solver::space::CodeSpace;
+///
+/// let codes: Vec<_> = CodeSpace::standard().codes().collect();
+/// let rules = [3, 8, 10, 13, 19];
+/// let parallel = tm_solver::find_super_unique_parallel(&codes, &rules, 3);
+/// assert_eq!(parallel, tm_solver::find_super_unique(&codes, &rules));
+/// ```
+#[cfg(feature = "std")]
+pub fn find_super_unique_parallel<'a, C, R>(codes: impl IntoIterator<Item = &'a C>, rules: &R, threads: usize)
+    -> HashMap<C, usize>
+where C: Clone + Eq + Hash + Send + Sync + 'a, R: RuleSet<C> + Sync + ?Sized {
+    let codes: Vec<&C> = codes.into_iter().collect();
+    let all = categorize_codes(codes.iter().copied(), rules);
+    let threads = threads.max(1);
+    let (codes, all) = (&codes, &all);
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads).map(|index| scope.spawn(move || {
+            let skipped = (0..rules.rule_count()).filter(|i| i % threads == index);
+            leave_out(all, codes.iter().copied(), rules, skipped)
+        })).collect();
+        let mut super_unique: HashMap<C, usize> = HashMap::new();
+        for worker in workers {
+            for (code, i) in worker.join().expect("a thread looking for super unique codes panicked") {
+                let first = super_unique.entry(code).or_insert(i);
+                *first = (*first).min(i);
+            }
+        }
+        super_unique
+    })
+}
+
+/// The codes that are unique with one of the given rules left out, along
+/// with the first of those rules, given the groups by all rules.
+fn leave_out<'a, C, R>(all: &HashMap<Results, Vec<C>>, codes: impl IntoIterator<Item = &'a C> + Clone, rules: &R,
+    skipped: impl Iterator<Item = usize>) -> HashMap<C, usize>
+where C: Clone + Eq + Hash + 'a, R: RuleSet<C> + ?Sized {
+    let mut super_unique = HashMap::new();
+    for i in skipped {
+        let solutions = remove_rule(all, codes.clone(), rules, i);
+        let unique = find_unique(&solutions);
+        for u in unique {
+            super_unique.entry(u.1).or_insert(i);
+        }
+    }
+    super_unique
+}
@@ -1,236 +1,972 @@
-mod rules;
-mod construct_tree;
+mod bench;
+mod cli;
+mod explore;
+mod failure;
+#[cfg(feature = "network")]
+mod fetch;
+mod frontend;
+mod guide;
+mod json;
+mod practice;
+mod remote;
+mod rpc;
+mod save;
+#[cfg(feature = "server")]
+mod serve;
+mod transcript;
+#[cfg(feature = "tui")]
+mod tui;
+mod wizard;
 
 use std::collections::HashSet;
-use std::path::Iter;
 use std::{collections::HashMap, sync::Arc};
 use std::io::Write;
-use std::sync::{mpsc, Mutex};
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{mpsc, Mutex, MutexGuard};
 use std::thread;
 use std::time::Duration;
 
-use rules::{Code, RULES};
-use construct_tree::{BinaryTree, Feasible};
-
-const CHECKMARK: &'static str = "\x1b[32m✓\x1b[0m";
-const LOADING: [char; 6] = ['⠇', '⠋', '⠙', '⠸', '⠴', '⠦'];
-
-fn do_task<F: Send + 'static + FnOnce() -> T, T: Send + 'static>(message: &str, task: F) -> T {
-    print!("{} ", message);
-    let mut i = 0;
-    let (sender, receiver) = mpsc::channel();
-    thread::spawn(move || {
-        let res = task();
-        let _ = sender.send(res);
-    });
-    loop {
-        if let Ok(t) = receiver.try_recv() {
-            print!("{} \n", CHECKMARK);
-            return t;
-        }
-        print!("{} ", LOADING[i]);
-        let _ = std::io::stdout().flush();
-        i = (i + 1) % 6;
-        thread::sleep(Duration::from_millis(100));
-        print!("\x08\x08");
-    }
-}
-
-fn categorize_codes(codes: &HashSet<Code>, rules: &Vec<usize>) -> HashMap<Vec<u8>, Vec<Code>> {
-    let mut solutions: HashMap<Vec<u8>, Vec<Code>> = HashMap::new();
-    codes.iter().for_each(|code| {
-        let results: Vec<u8> = rules.iter().filter_map(|rule| {
-            RULES[*rule](&code)
-        }).collect();
-        if results.len() < rules.len() {
+use clap::Parser;
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use tracing::{info, warn, Level};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::fmt::format::FmtSpan;
+
+use tm_solver::{add_rule, all_codes, categorize_codes, construct_tree, export, find_super_unique, find_unique, i18n, rules, style, RuleSet};
+use tm_solver::error::TmSolverError;
+use tm_solver::format;
+use tm_solver::hash::PuzzleHash;
+use tm_solver::history::TestHistory;
+use tm_solver::matrix::RuleMatrix;
+use tm_solver::progress::ProgressSink;
+use tm_solver::puzzle::Puzzle;
+use tm_solver::session::{Answered, GameSession};
+use tm_solver::simulate;
+use tm_solver::solver::{Candidates, Solver, SolverBuilder};
+use tm_solver::space::CodeSpace;
+use cli::{AnalyzeArgs, CardsArg, Cli, CodePreference, Command, ErrorFormat, ExportArgs, ExportFormat, GenerateArgs, GuideArgs, LoadArgs, LogLevel, PracticeArgs, PuzzleArgs, Query, ReplayArgs, RuleArg, SimulateArgs, SolveArgs, TreeArgs};
+use rules::{Code, RULES, RULE_DESCRIPTIONS};
+use construct_tree::{Answer, BinaryTree, Branch, Feasible, Results, Test};
+use i18n::tr;
+use explore::Explorer;
+use failure::{report, Failure};
+use frontend::{FrontEnd, Terminal};
+use guide::Guide;
+use practice::Practice;
+use save::SavedGame;
+use transcript::{Event, Transcript};
+
+/// Shows the progress of the solver on the terminal, one line per phase
+/// with an animated spinner if animations are on.
+#[derive(Default)]
+struct TerminalProgress {
+    /// only used by the thread running the phases.
+    current: Mutex<Option<Phase>>,
+    /// the percentage of the current phase, reported by the search threads
+    /// without waiting for each other.
+    percent: Arc<AtomicU8>,
+}
+
+/// A phase being shown by [`TerminalProgress`].
+struct Phase {
+    message: String,
+    /// stops the animation when dropped.
+    stop: Option<mpsc::Sender<()>>,
+    animation: Option<thread::JoinHandle<()>>,
+}
+
+/// No percentage has been reported for the phase yet.
+const NO_PERCENT: u8 = u8::MAX;
+
+impl TerminalProgress {
+
+    /// The phase shown, even if showing another one panicked.
+    fn phase(&self) -> MutexGuard<'_, Option<Phase>> {
+        self.current.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl ProgressSink for TerminalProgress {
+    fn start(&self, phase: &str) {
+        if json::enabled() {
             return;
         }
-        match solutions.get_mut(&results) {
-            Some(cur) => cur.push(code.clone()),
-            None => {
-                solutions.insert(results, vec![code.clone()]);
+        let message = format!("{} ...", phase);
+        self.percent.store(NO_PERCENT, Ordering::Relaxed);
+        let Some(frames) = style::spinner() else {
+            // without animation, don't write any control characters.
+            print!("{} ", message);
+            let _ = std::io::stdout().flush();
+            *self.phase() = Some(Phase { message, stop: None, animation: None });
+            return;
+        };
+        let (stop, stopped) = mpsc::channel::<()>();
+        let (line, shown) = (message.clone(), Arc::clone(&self.percent));
+        // redraw the whole line with a carriage return, since backspaces
+        // aren't handled well by every terminal.
+        let animation = thread::spawn(move || {
+            let mut i = 0;
+            while let Err(mpsc::RecvTimeoutError::Timeout) = stopped.recv_timeout(Duration::from_millis(100)) {
+                match shown.load(Ordering::Relaxed) {
+                    NO_PERCENT => print!("\r{} {} ", line, frames[i]),
+                    p => print!("\r{} {} {:>3}% ", line, frames[i], p),
+                }
+                let _ = std::io::stdout().flush();
+                i = (i + 1) % frames.len();
+            }
+        });
+        *self.phase() = Some(Phase { message, stop: Some(stop), animation: Some(animation) });
+    }
+
+    fn progress(&self, percent: u8) {
+        self.percent.store(percent.min(100), Ordering::Relaxed);
+    }
+
+    fn finish(&self) {
+        let Some(mut phase) = self.phase().take() else {
+            return;
+        };
+        drop(phase.stop.take());
+        match phase.animation.take() {
+            Some(animation) => {
+                let _ = animation.join();
+                // overwrite the percentage, if any.
+                println!("\r{} {}      ", phase.message, style::checkmark());
             },
+            None => println!("{}", style::checkmark()),
         }
-    });
-    solutions
+    }
+}
+
+/// Write diagnostic output to stderr, as detailed as the log level or the
+/// verbosity of the solve command asks for.
+fn init_logging(level: LogLevel, verbose: u8) {
+    let from_verbose = match verbose {
+        0 | 1 => LogLevel::Off,
+        2 => LogLevel::Info,
+        3 => LogLevel::Debug,
+        _ => LogLevel::Trace,
+    };
+    let filter = match level.max(from_verbose) {
+        LogLevel::Off => LevelFilter::OFF,
+        LogLevel::Error => LevelFilter::ERROR,
+        LogLevel::Warn => LevelFilter::WARN,
+        LogLevel::Info => LevelFilter::INFO,
+        LogLevel::Debug => LevelFilter::DEBUG,
+        LogLevel::Trace => LevelFilter::TRACE,
+    };
+    // the time of each phase is reported when its span closes, so only
+    // leave out the timestamps.
+    tracing_subscriber::fmt()
+        .with_max_level(filter)
+        .with_writer(std::io::stderr)
+        .with_ansi(style::color())
+        .with_target(false)
+        .with_timer(())
+        .with_span_events(FmtSpan::CLOSE)
+        .init();
 }
 
-fn find_unique(solutions: &HashMap<Vec<u8>, Vec<Code>>) -> Vec<Feasible<Code>> {
-    solutions.iter().filter_map(|(k, v)| {
-        if v.len() == 1 {
-            return Some((k.clone(), v[0].clone()));
+/// Find the smallest set of verifiers that already identifies each code which
+/// doesn't need all of them.
+fn identifying_verifiers(matrix: &RuleMatrix) -> HashMap<Code, Vec<usize>> {
+    let codes = all_codes();
+    let verifiers = matrix.rule_count();
+    let mut masks: Vec<u32> = (1..(1u32 << verifiers) - 1).collect();
+    masks.sort_by_key(|m| m.count_ones());
+    // every subset's groups are those of the subset without its last
+    // verifier, split by that verifier's results.
+    let mut groups = HashMap::from([(0, categorize_codes(&codes, &[] as &[usize]))]);
+    let mut identifying = HashMap::new();
+    for mask in masks {
+        let last = mask.ilog2() as usize;
+        let solutions = add_rule(&groups[&(mask & !(1 << last))], |code| matrix.category(last, code));
+        let subset: Vec<usize> = (0..verifiers).filter(|i| mask & (1 << i) != 0).collect();
+        for (_, code) in find_unique(&solutions) {
+            identifying.entry(code).or_insert_with(|| subset.clone());
         }
-        None
-    }).collect()
+        groups.insert(mask, solutions);
+    }
+    identifying
+}
+
+/// The letter of the verifier performing a test, if the letters are assigned
+/// by order.
+fn test_letter(test: usize) -> char {
+    (b'A' + test as u8) as char
 }
 
-fn main() {
+/// Would the verifier of test i yield a checkmark for the code, if the given
+/// solution was the correct one?
+fn passes(matrix: &RuleMatrix, (results, _): &Feasible<Code>, code: &Code, i: usize) -> bool {
+    matrix.category(i, code) == Some(results[i])
+}
 
-    // what rules are used?
-    let input = std::env::args();
-    let mut verbose = false;
-    let rules: Vec<usize> = input.filter_map(|l| {
-        if l == "-v" {
-            verbose = true;
+/// A puzzle that has been run through all preprocessing steps.
+struct Prepared {
+    /// the verifier letter of each test.
+    letters: Vec<char>,
+    /// the (zero-based) rules used by the puzzle.
+    rules: Vec<usize>,
+    /// the results of the rules for every code.
+    matrix: RuleMatrix,
+    /// the tests the user performed on their own beforehand.
+    known: Vec<Query>,
+    /// the codes that were removed because they don't require all tests,
+    /// along with a test they don't require.
+    removed: HashMap<Code, usize>,
+    /// all remaining codes, grouped by their test results.
+    solutions: HashMap<Results, Vec<Code>>,
+    /// the codes that can be uniquely identified by their test results.
+    unique_solutions: Vec<Feasible<Code>>,
+    /// the optimal solution tree.
+    tree: BinaryTree<Code>,
+    /// which codes the tree uses for its rounds.
+    round_codes: CodePreference,
+    /// how the tree was constructed, without reporting the progress, to
+    /// construct new trees the same way.
+    solver: SolverBuilder,
+}
+
+/// Explain why a puzzle doesn't have any unique solutions: every code either
+/// doesn't need all verifiers or shares its results with other codes.
+fn explain_improper(letters: &[char], removed: &HashMap<Code, usize>, solutions: &HashMap<Results, Vec<Code>>) -> String {
+    const EXAMPLES: usize = 5;
+    let sorted = |codes: &mut Vec<&Code>| codes.sort_by_key(|c| (c.blue, c.yellow, c.purple));
+    let mut lines = vec![TmSolverError::Unsolvable.to_string()];
+    for (i, letter) in letters.iter().enumerate() {
+        let mut codes: Vec<&Code> = removed.iter().filter(|(_, r)| **r == i).map(|(c, _)| c).collect();
+        if codes.is_empty() {
+            continue;
         }
-        let rule = l.parse::<usize>();
-        if let Ok(r) = rule {
-            if r > 0 && r <= RULES.len() {
-                return Some(r - 1);
-            }
+        sorted(&mut codes);
+        let examples: Vec<String> = codes.iter().take(EXAMPLES).map(|c| c.colored().to_string()).collect();
+        lines.push(format!("Verifier {} is redundant for {}, e. g. {}.",
+            letter, count_codes(codes.len()), examples.join(", ")));
+    }
+    let mut groups: Vec<Vec<&Code>> = solutions.values().map(|g| g.iter().collect()).collect();
+    groups.iter_mut().for_each(sorted);
+    groups.sort_by_key(|g| g.first().map(|c| (c.blue, c.yellow, c.purple)));
+    if !groups.is_empty() {
+        lines.push(format!("{} groups of codes can't be told apart by the verifiers, e. g.:", groups.len()));
+        for group in groups.iter().take(EXAMPLES) {
+            let codes: Vec<String> = group.iter().map(|c| c.colored().to_string()).collect();
+            lines.push(format!("  {}", codes.join(", ")));
         }
-        //println!("{} could not be parsed as a valid rule number.", l);
-        None
-    }).collect();
-    let no_rules = rules.len();
-    if no_rules < 4 {
-        println!("Not enough input rules, aborting.");
-        return;
     }
-    let rules = Arc::new(Mutex::new(rules));
-
-    // create all possible 3-digit codes
-    let codes = do_task("Generating codes ...", || (0..125).map(|i| {
-        Code {
-            blue: i % 5 + 1,
-            yellow: (i / 5) % 5 + 1,
-            purple: (i / 25) + 1,
-        }
-    })).collect::<HashSet<Code>>();
-    let codes = Arc::new(Mutex::new(codes));
-
-    // remove codes that would be unique without all tests
-    let rc_r = Arc::clone(&rules);
-    let rc_c = Arc::clone(&codes);
-    let super_unique = do_task("Removing solutions that don't require all tests ...", move || {
-        let mut rules = rc_r.lock().unwrap();
-        let mut codes = rc_c.lock().unwrap();
-        let mut super_unique = HashSet::new();
-        for _i in 0..no_rules {
-            let temp = rules.remove(0);
-            let solutions = categorize_codes(&codes, &rules);
-            let unique = find_unique(&solutions);
-            for u in unique {
-                super_unique.insert(u.1);
+    lines.join("\n")
+}
+
+/// Run a puzzle through all preprocessing steps, showing the progress to the
+/// user.
+///
+/// Returns the puzzle along with the candidates, or None (after telling the
+/// user) if the puzzle can't be solved.
+fn find_candidates(args: &PuzzleArgs) -> Option<(Puzzle, Candidates)> {
+    let puzzle = match args.puzzle() {
+        Ok(p) => p,
+        Err(e) => {
+            report(Failure::InvalidInput, &e);
+            return None;
+        },
+    };
+    let letters = puzzle.letters();
+    let builder = match puzzle_builder(&puzzle) {
+        Ok(b) => b,
+        Err(e) => {
+            report(Failure::of(&e), &e.to_string());
+            return None;
+        },
+    };
+    match builder.candidates() {
+        Ok(candidates) => Some((puzzle, candidates)),
+        Err(TmSolverError::Unsolvable) => {
+            // the candidates are gone along with the error, but finding them
+            // again is quick compared to telling the user.
+            let rules: Vec<usize> = puzzle.cards().iter().map(|c| c - 1).collect();
+            let mut codes = all_codes();
+            let removed = find_super_unique(&codes, &rules);
+            for code in removed.keys() {
+                codes.remove(code);
+            }
+            report(Failure::Improper, &explain_improper(&letters, &removed, &categorize_codes(&codes, &rules)));
+            None
+        },
+        Err(e) => {
+            report(Failure::of(&e), &e.to_string());
+            None
+        },
+    }
+}
+
+/// A solver for the puzzle, reporting its progress to the user.
+fn puzzle_builder(puzzle: &Puzzle) -> Result<SolverBuilder, TmSolverError> {
+    Ok(puzzle.builder()?.progress(Arc::new(TerminalProgress::default())))
+}
+
+/// Run a puzzle through all preprocessing steps and construct its optimal
+/// solution tree, showing the progress to the user.
+///
+/// Returns None (after telling the user) if the puzzle can't be solved.
+fn prepare_puzzle(args: &PuzzleArgs) -> Option<Prepared> {
+    prepare_within(args, None)
+}
+
+/// A limit on the questions a strategy may need, see --max-questions.
+struct Budget {
+    questions: u8,
+    /// give up if no strategy fits, instead of going on with the best one.
+    strict: bool,
+}
+
+/// Like [`prepare_puzzle`], but the search only looks for strategies within
+/// the given budget, so it gives up early if there are none.
+fn prepare_within(args: &PuzzleArgs, budget: Option<Budget>) -> Option<Prepared> {
+    let (puzzle, candidates) = find_candidates(args)?;
+    let mut history = match args.history.as_deref().map(load_history).transpose() {
+        Ok(h) => h,
+        Err(e) => {
+            report(Failure::InvalidInput, &e);
+            return None;
+        },
+    };
+    let solver = match puzzle.builder() {
+        Ok(mut b) => {
+            if let Some(h) = &history {
+                b = b.history(h.clone());
             }
-            rules.push(temp);
-        }
-        for u in &super_unique {
-            codes.remove(u);
-        }
-        super_unique
-    });
-    if verbose {
-        for u in super_unique {
-            println!("Removed {}", u.to_string());
-        }
-    }
-
-    let rc_r = Arc::clone(&rules);
-    let rc_c = Arc::clone(&codes);
-    // check which results these codes yield after running the "program".
-    let solutions = do_task("Looking for unique solutions ...", move || {
-        let rules = rc_r.lock().unwrap();
-        let codes = rc_c.lock().unwrap();
-        categorize_codes(&codes, &rules)
-    });
-    
-    // only unique solutions are interesting
-    let unique_solutions = find_unique(&solutions);
-    if unique_solutions.len() == 0 {
-        println!("This puzzle does not appear to be solvable. Please double-check your inputs.");
+            if let Some(mb) = args.max_memory {
+                b = b.max_memory(mb.saturating_mul(1 << 20));
+            }
+            b.deepening(args.deepening)
+        },
+        Err(e) => {
+            report(Failure::of(&e), &e.to_string());
+            return None;
+        },
+    };
+    let construct = |solver: SolverBuilder| solver.progress(Arc::new(TerminalProgress::default()))
+        .construct(&candidates);
+    let tree = match budget {
+        Some(budget) => match construct(solver.clone().max_questions(budget.questions)) {
+            Err(TmSolverError::NoTree) => {
+                let message = format!("Even the best strategy needs more than your budget of {} questions.",
+                    budget.questions);
+                if budget.strict {
+                    report(Failure::Unsolvable, &message);
+                    return None;
+                }
+                if !json::enabled() {
+                    println!("{}", message);
+                }
+                construct(solver.clone())
+            },
+            tree => tree,
+        },
+        None => construct(solver.clone()),
+    };
+    let Candidates { rules, matrix, removed, solutions, unique_solutions } = candidates;
+    let mut tree = match tree {
+        Ok(t) => t,
+        Err(e) => {
+            report(Failure::of(&e), &e.to_string());
+            return None;
+        },
+    };
+    if let (Some(history), Some(path)) = (&mut history, &args.history) {
+        history.record(&rules, &tree);
+        // the strategy is fine either way, so only warn about it.
+        if let Err(e) = std::fs::write(path, format::to_string(history)) {
+            warn!(path = %path.display(), error = %e, "could not write the test history");
+        }
+    }
+    prefer_round_codes(&mut tree, &rules, args.round_codes);
+    Some(Prepared {
+        letters: puzzle.letters(),
+        rules,
+        matrix,
+        known: args.known.clone(),
+        removed,
+        solutions,
+        unique_solutions,
+        tree,
+        round_codes: args.round_codes,
+        solver,
+    })
+}
+
+/// The test history stored in a file, or an empty one if there is no such
+/// file yet.
+fn load_history(path: &Path) -> Result<TestHistory, String> {
+    match std::fs::read_to_string(path) {
+        Ok(file) => format::from_str(&file)
+            .map_err(|e| format!("Could not read the test history {}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(TestHistory::default()),
+        Err(e) => Err(format!("Could not read the test history {}: {}", path.display(), e)),
+    }
+}
+
+/// Replace the code of every round by the preferred one among all codes
+/// that work for the round's tests.
+fn prefer_round_codes(tree: &mut BinaryTree<Code>, rules: &[usize], preference: CodePreference) {
+    let BinaryTree::Branch(b) = tree else {
         return;
+    };
+    if b.code.is_some() {
+        let tests = b.round_tests();
+        let codes: Vec<Code> = CodeSpace::standard().codes()
+            .filter(|c| tests.iter().all(|(i, v)| RULES[rules[*i]](c) == Some(*v)))
+            .collect();
+        let digits = |c: &Code| [c.blue, c.yellow, c.purple];
+        let preferred = match preference {
+            CodePreference::Any => None,
+            CodePreference::Smallest => codes.first(),
+            CodePreference::Repeated => codes.iter()
+                .min_by_key(|c| digits(c).iter().collect::<HashSet<_>>().len()),
+            CodePreference::Ascending => codes.iter()
+                .find(|c| c.blue <= c.yellow && c.yellow <= c.purple),
+        };
+        if let Some(code) = preferred {
+            b.code = Some(*code);
+        }
     }
-    if verbose {
-        for i in 0..no_rules {
-            print!(" {} ", (i as u8 + 0x41) as char)
-        }
-        print!("\n");
-        unique_solutions.iter().for_each(|s| println!("{:?} -> {}", s.0, s.1.to_string()));
-    }
-
-    let tree = do_task("Construct optimal tree ...", move || {
-        construct_tree::optimal_tree(&unique_solutions, &solutions, 3)
-    });
-    let mut tree = tree.unwrap();
-    if verbose {
-        tree.print(0);
-    }
-
-    // construct an optimal solution tree
-   /*  println!("Construct optimal tree ...");
-    let (sender, receiver) = mpsc::channel();
-    thread::spawn(move || {
-        let tree = construct_tree::optimal_tree(&unique_solutions, &solutions, 3);
-        let _ = sender.send(tree.unwrap());
-    });
-
-    // just a cute little loading indicator while we wait for the main thread
-    // to finish.
-    let mut load_i = 0;
-    let mut tree = None;
-    loop {
-        print!("{} ", LOADING[load_i]);
-        let _ = std::io::stdout().flush();
-        load_i = (load_i + 1) % 6;
-        thread::sleep(Duration::from_millis(100));
-        print!("\x08\x08");
-        if let Ok(t) = receiver.try_recv() {
-            tree = Some(t);
-            break;
+    prefer_round_codes(&mut b.correct, rules, preference);
+    prefer_round_codes(&mut b.incorrect, rules, preference);
+}
+
+/// Format a number of codes, e. g. "1 code" or "3 codes".
+fn count_codes(n: usize) -> String {
+    match n {
+        1 => tr!(one_code),
+        n => tr!(codes, n),
+    }
+}
+
+/// Print a table of all unique solutions and their test results.
+fn print_solutions(unique_solutions: &[Feasible<Code>], letters: &[char]) {
+    for l in letters {
+        print!(" {} ", l)
+    }
+    println!();
+    unique_solutions.iter().for_each(|s| println!("{:?} -> {}", s.0, s.1.colored()));
+}
+
+/// Print a solution tree, pausing after every page if printing to a
+/// terminal.
+fn print_tree(tree: &BinaryTree<Code>, args: &TreeArgs) {
+    let lines = tree.render(args.depth, &|c| c.colored().to_string());
+    frontend::page(&mut Terminal, &lines, args.page_size);
+}
+
+/// How many tests and rounds following a solution tree takes.
+struct Effort {
+    max_tests: usize,
+    max_rounds: usize,
+    average_tests: f64,
+    average_rounds: f64,
+}
+
+impl Effort {
+
+    /// The effort of the puzzle's solution tree, over all possible codes.
+    fn of(prepared: &Prepared) -> Effort {
+        let mut effort = Effort { max_tests: 0, max_rounds: 0, average_tests: 0.0, average_rounds: 0.0 };
+        for solution in &prepared.unique_solutions {
+            let steps = simulate::simulate(&prepared.tree, solution).unwrap_or_default();
+            let rounds = simulate::rounds(&steps);
+            effort.max_tests = effort.max_tests.max(steps.len());
+            effort.max_rounds = effort.max_rounds.max(rounds);
+            effort.average_tests += steps.len() as f64;
+            effort.average_rounds += rounds as f64;
         }
+        let n = prepared.unique_solutions.len() as f64;
+        effort.average_tests /= n;
+        effort.average_rounds /= n;
+        effort
     }
-    let mut tree = &tree.unwrap();
-    if verbose {
-        tree.print(0);
-    }
-    println!("Done!"); */
-
-    // guide the user through performing the input checks.
-    let mut level = 0;
-    let mut current_code = None;
-    while let BinaryTree::Branch(b) = tree {
-        if level % 3 == 0 {
-            println!("------");
-            println!("\x1b[1mStart of round {}\x1b[0m", level / 3 + 1);
-            current_code = b.code.clone();
-            println!("Use the following combination: {}", current_code.unwrap().to_string());
-        }
-        let c = match b.test.0 {
-            0 => 'A',
-            1 => 'B',
-            2 => 'C',
-            3 => 'D',
-            4 => 'E',
-            5 => 'F',
-            _ => '?',
+}
+
+/// All unique solutions that are consistent with the given test results.
+fn consistent<'a>(unique_solutions: &'a [Feasible<Code>], answers: &[(Test, Answer)]) -> Vec<&'a Feasible<Code>> {
+    unique_solutions.iter()
+        .filter(|(results, _)| answers.iter().all(|((i, v), answer)| (results[*i] == *v) == answer.passed()))
+        .collect()
+}
+
+/// What every verifier should yield for the solution, so the user can check
+/// it once more before guessing.
+fn final_check(prepared: &Prepared, code: &Code) -> String {
+    let mut lines = vec![tr!(final_check, code.colored(), style::checkmark())];
+    for (letter, rule) in prepared.letters.iter().zip(&prepared.rules) {
+        lines.push(format!("  {} ({}) {}", letter, RULE_DESCRIPTIONS[*rule], style::checkmark()));
+    }
+    lines.join("\n")
+}
+
+/// Explain a question by how many codes either answer would leave.
+fn split(branch: &Branch<Code>) -> String {
+    let (correct, incorrect) = (branch.correct.leaf_count(), branch.incorrect.leaf_count());
+    format!("  {} {} {}, {} {} {}", style::checkmark(), style::arrow(), count_codes(correct),
+        style::cross(), style::arrow(), count_codes(incorrect))
+}
+
+/// The round a branch starts, if any, and the code to use for it. `history`
+/// holds the branches passed so far.
+fn round_started<'a>(branch: &'a Branch<Code>, history: &[Answered]) -> Option<(usize, &'a Code)> {
+    // a new round starts whenever the tree provides a new code.
+    let code = branch.code.as_ref()?;
+    let round = history.iter()
+        .filter(|(h, _, _)| matches!(h, BinaryTree::Branch(p) if p.code.is_some()))
+        .count() + 1;
+    Some((round, code))
+}
+
+/// Follow the solution tree with the given answers, ignoring those beyond
+/// its leaves. Returns the session along with the events its transcript
+/// starts with.
+fn replay<'a>(prepared: &'a Prepared, answers: &[Answer]) -> (GameSession<'a>, Vec<Event>) {
+    let cards = prepared.letters.iter().zip(&prepared.rules).map(|(l, r)| (*l, *r as u8 + 1)).collect();
+    let mut events = vec![Event::Puzzle(cards)];
+    events.extend(prepared.known.iter().cloned().map(Event::Known));
+    let mut session = GameSession::new(&prepared.tree, &prepared.unique_solutions);
+    for answer in answers {
+        let BinaryTree::Branch(b) = session.node() else {
+            break;
         };
-        println!("Does \x1b[47m Test {} \x1b[0m yield a {} ? (y/n)", c, CHECKMARK);
-        loop {
-            let mut input = String::new();
-            let _ = std::io::stdin().read_line(&mut input);
-            match input.chars().nth(0) {
-                Some('y') => {
-                    tree = b.correct;
-                    break;
-                },
-                Some('n') => {
-                    tree = b.incorrect;
-                    break;
+        if let Some((round, code)) = round_started(b, session.history()) {
+            events.push(Event::Round(round, *code));
+        }
+        events.push(Event::Answer(prepared.letters[b.test.0], *answer));
+        session.answer(*answer);
+    }
+    (session, events)
+}
+
+/// The code used in the current round, given the branches passed so far.
+fn round_code<'a>(history: &[Answered<'a>]) -> Option<&'a Code> {
+    history.iter().rev().find_map(|(h, _, _)| match h {
+        BinaryTree::Branch(p) => p.code.as_ref(),
+        BinaryTree::Leaf(_) => None,
+    })
+}
+
+/// Follow the solution tree with the given answers without asking the user
+/// and print the resulting code.
+fn answer_all(prepared: &Prepared, transcript: &mut Transcript, answers: &[Answer]) {
+    let (session, events) = replay(prepared, answers);
+    events.iter().for_each(|e| transcript.record(e));
+    if json::enabled() {
+        if let Some(c) = session.solution() {
+            transcript.record(&Event::Solution(*c));
+        }
+        return json::print_result(prepared, session.node(), session.round_code().as_ref());
+    }
+    if session.answers().len() < answers.len() {
+        println!("{}", tr!(more_answers));
+    }
+    if let Some(q) = session.next_question() {
+        return println!("{}", tr!(not_enough_answers, q.code.colored(), prepared.letters[q.test.0]));
+    }
+    if let Some(c) = session.solution() {
+        println!("{}", final_check(prepared, c));
+        println!("{}", tr!(your_code, c.colored()));
+        transcript.record(&Event::Solution(*c));
+    }
+}
+
+/// Guide the user through performing the input checks, starting after the
+/// given answers. Uses the full-screen interface if requested, or doesn't
+/// ask at all if the answers were passed on the command line.
+fn run_session(prepared: Prepared, args: &GuideArgs, transcript: &mut Transcript, answers: &[Answer]) {
+    match args.answers() {
+        Ok(Some(given)) => return answer_all(&prepared, transcript, &[answers, &given].concat()),
+        // there's nobody to ask in JSON mode.
+        Ok(None) if json::enabled() => return answer_all(&prepared, transcript, answers),
+        Ok(None) => {},
+        Err(e) => return report(Failure::InvalidInput, &e),
+    }
+    #[cfg(feature = "tui")]
+    if args.tui {
+        if let Err(e) = tui::run(&prepared, transcript, answers) {
+            report(Failure::Internal, &format!("The full-screen interface failed: {}", e));
+        }
+        return;
+    }
+    frontend::run(&mut Guide::new(prepared, args, answers), &mut Terminal, transcript);
+}
+
+/// Print as much information about a prepared puzzle as the verbosity level
+/// asks for.
+fn print_details(prepared: &Prepared, args: &SolveArgs) {
+    if tracing::enabled!(Level::INFO) {
+        let mut removed: Vec<(&Code, &usize)> = prepared.removed.iter().collect();
+        removed.sort_by_key(|(c, _)| (c.blue, c.yellow, c.purple));
+        let identifying = identifying_verifiers(&prepared.matrix);
+        for (code, i) in removed {
+            match identifying.get(code) {
+                Some(subset) => {
+                    let letters: Vec<String> = subset.iter().map(|j| prepared.letters[*j].to_string()).collect();
+                    let verifiers = match letters.len() {
+                        1 => format!("verifier {} already identifies", letters[0]),
+                        _ => format!("verifiers {} already identify", letters.join(", ")),
+                    };
+                    info!("Removed {}: {} it", code, verifiers);
                 },
-                _ => println!("Please input y or n."),
+                None => info!("Removed {}: already unique without verifier {}", code, prepared.letters[*i]),
             }
         }
-        level += 1;
     }
+    if args.verbose >= 1 {
+        print_solutions(&prepared.unique_solutions, &prepared.letters);
+        print_tree(&prepared.tree, &args.tree);
+    }
+}
+
+fn solve(args: SolveArgs) {
+    let mut transcript = match Transcript::create(args.guide.log.as_deref()) {
+        Ok(t) => t,
+        Err(e) => {
+            report(Failure::Internal, &format!("Could not create the transcript file: {}", e));
+            return;
+        },
+    };
+    let budget = args.max_questions.map(|questions| Budget { questions, strict: args.strict });
+    let Some(prepared) = prepare_within(&args.puzzle, budget) else {
+        return;
+    };
+    if !json::enabled() {
+        print_details(&prepared, &args);
+    }
+    run_session(prepared, &args.guide, &mut transcript, &[]);
+}
+
+fn load(args: LoadArgs) {
+    let saved = match SavedGame::load(&args.file) {
+        Ok(s) => s,
+        Err(e) => {
+            report(Failure::InvalidInput, &format!("Could not load the game: {}", e));
+            return;
+        },
+    };
+    let mut transcript = match Transcript::create(args.guide.log.as_deref()) {
+        Ok(t) => t,
+        Err(e) => {
+            report(Failure::Internal, &format!("Could not create the transcript file: {}", e));
+            return;
+        },
+    };
+    let puzzle = saved.puzzle();
+    let Some((puzzle, candidates)) = find_candidates(&puzzle) else {
+        return;
+    };
+    let solver = match puzzle.builder() {
+        Ok(b) => b,
+        Err(e) => {
+            report(Failure::of(&e), &e.to_string());
+            return;
+        },
+    };
+    let letters = puzzle.letters();
+    let prepared = Prepared {
+        letters,
+        rules: candidates.rules,
+        matrix: candidates.matrix,
+        known: saved.known,
+        removed: candidates.removed,
+        solutions: candidates.solutions,
+        unique_solutions: candidates.unique_solutions,
+        tree: saved.tree,
+        round_codes: CodePreference::default(),
+        solver,
+    };
+    run_session(prepared, &args.guide, &mut transcript, &saved.answers);
+}
+
+fn replay_transcript(args: ReplayArgs) {
+    let events = match transcript::read(&args.file) {
+        Ok(e) => e,
+        Err(e) => return report(Failure::InvalidInput, &format!("Could not read the transcript: {}", e)),
+    };
+    let Some(Event::Puzzle(cards)) = events.first() else {
+        return report(Failure::InvalidInput, "The transcript doesn't start with a puzzle.");
+    };
+    let puzzle = PuzzleArgs {
+        rules: cards.iter().map(|(l, r)| CardsArg::Card(RuleArg { letter: Some(*l), rule: *r })).collect(),
+        ..Default::default()
+    };
+    let Some((puzzle, candidates)) = find_candidates(&puzzle) else {
+        return;
+    };
+    let letters = puzzle.letters();
+
+    // print a test and remember it as code, verifier and result.
+    let perform = |tests: &mut Vec<(Code, usize, Answer)>, code: &Code, letter: char, answer: Answer| {
+        let mark = style::mark(answer);
+        let Some(i) = letters.iter().position(|l| *l == letter) else {
+            println!("  Test {}: {} (unknown verifier)", letter, mark);
+            return;
+        };
+        tests.push((*code, i, answer));
+        print!("  Test {}: {}", letter, mark);
+        if args.remaining {
+            let left = candidates.unique_solutions.iter()
+                .filter(|s| tests.iter().all(|(c, i, a)| passes(&candidates.matrix, s, c, *i) == a.passed()))
+                .count();
+            print!(" ({} left)", count_codes(left));
+        }
+        println!();
+    };
 
-    // done!
-    if let BinaryTree::Leaf(c) = tree {
-        println!("Found a solution!");
-        println!("Your code is: {}", c.to_string());
-    } else {
-        println!("Something went terribly wrong and I don't know what it is. Sorry!");
+    let mut tests = Vec::new();
+    let mut known = 0;
+    let mut round_code = None;
+    for event in &events {
+        if args.step && Terminal.read().is_none() {
+            return;
+        }
+        match event {
+            Event::Puzzle(cards) => {
+                // a session starts over after a correction.
+                let cards: Vec<String> = cards.iter().map(|(l, r)| format!("{}={}", l, r)).collect();
+                println!("{}", style::bold(&format!("Puzzle: {}", cards.join(" "))));
+                tests.clear();
+                known = 0;
+            },
+            Event::Known(q) => {
+                println!("Known test with {}:", q.code.colored());
+                perform(&mut tests, &q.code, q.letter, q.answer);
+                known = tests.len();
+            },
+            Event::Round(n, code) => {
+                println!("Round {}: use {}", n, code.colored());
+                round_code = Some(code);
+            },
+            Event::Answer(letter, answer) => match round_code {
+                Some(code) => perform(&mut tests, code, *letter, *answer),
+                None => println!("  Test {} was answered before any round started.", letter),
+            },
+            Event::Undo(n) => {
+                println!("  Took back {}.", if *n == 1 { "1 answer".to_string() } else { format!("{} answers", n) });
+                tests.truncate(tests.len().saturating_sub(*n).max(known));
+            },
+            Event::Correct(letter, answer) => {
+                println!("Corrected Test {} to {}, replaying the answers.", letter, style::mark(*answer));
+            },
+            Event::Solution(code) => println!("Solution: {}", code.colored()),
+        }
     }
+}
 
+fn list_rules() {
+    for (i, description) in RULE_DESCRIPTIONS.iter().enumerate() {
+        println!("{:>2}: {}", i + 1, description);
+    }
 }
 
+fn generate(args: GenerateArgs) {
+    let mut rng = match args.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    // most random combinations of rules are solvable, so just keep trying.
+    for _ in 0..1000 {
+        let mut rules = rand::seq::index::sample(&mut rng, RULES.len(), args.verifiers as usize)
+            .into_vec();
+        rules.sort();
+        let cards: Vec<usize> = rules.iter().map(|r| r + 1).collect();
+        let Ok(strategy) = Solver::builder().rules(&cards).solve() else {
+            continue;
+        };
+        println!("Criteria cards:");
+        for (i, r) in rules.iter().enumerate() {
+            println!("  {}: {:>2} ({})", test_letter(i), r + 1, RULE_DESCRIPTIONS[*r]);
+        }
+        let verifiers: Vec<(char, usize)> = rules.iter().enumerate().map(|(i, r)| (test_letter(i), r + 1)).collect();
+        if let Ok(hash) = PuzzleHash::encode(&verifiers) {
+            println!("Puzzle code: {} (share it with: tm_solver solve \"{}\")", hash, hash);
+        }
+        if args.reveal {
+            // the solutions come in no particular order, so sort them for the seed.
+            let mut codes: Vec<&Code> = strategy.unique_solutions.iter().map(|(_, c)| c).collect();
+            codes.sort_by_key(|c| (c.blue, c.yellow, c.purple));
+            println!("Secret code: {}", codes.choose(&mut rng).unwrap().colored());
+        }
+        return;
+    }
+    report(Failure::Internal, "Could not find a solvable puzzle, please try again.");
+}
+
+fn simulate(args: SimulateArgs) {
+    let Some(prepared) = prepare_puzzle(&args.puzzle) else {
+        return;
+    };
+    let unique_solutions = &prepared.unique_solutions;
+    let Some(code) = args.code else {
+        // simulate every possible code and summarize.
+        for solution @ (_, code) in unique_solutions {
+            let steps = simulate::simulate(&prepared.tree, solution).unwrap_or_default();
+            println!("{}: {} tests in {} rounds", code.colored(), steps.len(), simulate::rounds(&steps));
+        }
+        return;
+    };
+    let solution = unique_solutions.iter().find(|(_, c)| *c == code);
+    let Some(steps) = solution.and_then(|s| simulate::simulate(&prepared.tree, s)) else {
+        return report(Failure::InvalidInput, &format!("{} is not a possible solution to this puzzle.", code.colored()));
+    };
+    let mut round = 0;
+    for step in &steps {
+        if let (true, Some(code)) = (step.round > round, step.code) {
+            println!("Round {}: use {}", step.round, code.colored());
+        }
+        round = step.round;
+        println!("  Test {}: {}", prepared.letters[step.test.0], style::mark(step.answer));
+    }
+    println!("Found {} after {} tests in {} rounds.", code.colored(), steps.len(), round);
+}
+
+fn analyze(args: AnalyzeArgs) {
+    let Some(prepared) = prepare_puzzle(&args.puzzle) else {
+        return;
+    };
+    if json::enabled() {
+        return json::print_result(&prepared, &prepared.tree, None);
+    }
+    let unique_solutions = &prepared.unique_solutions;
+    println!("Codes requiring all verifiers: {}", CodeSpace::standard().len() - prepared.removed.len());
+    println!("Unique solutions: {}", unique_solutions.len());
+    print_solutions(unique_solutions, &prepared.letters);
+    let effort = Effort::of(&prepared);
+    println!("Optimal strategy: at most {} tests in {} rounds, {:.2} tests on average.",
+        effort.max_tests, effort.max_rounds, effort.average_tests);
+    print_tree(&prepared.tree, &args.tree);
+}
+
+fn export_strategy(args: ExportArgs) {
+    if args.output.is_none() {
+        // keep stdout clean for the export, like with --json.
+        json::set_enabled(true);
+    }
+    let Some(prepared) = prepare_puzzle(&args.puzzle) else {
+        return;
+    };
+    let strategy = || export::strategy(&prepared.letters, &prepared.rules, &prepared.tree);
+    let output = match args.format {
+        ExportFormat::Json => strategy().to_json(),
+        ExportFormat::Markdown => strategy().to_markdown(),
+        ExportFormat::Csv => export::code_table(&prepared.letters, &prepared.rules,
+            &prepared.solutions, &prepared.unique_solutions),
+    };
+    match &args.output {
+        Some(path) => match std::fs::write(path, output.trim_end().to_string() + "\n") {
+            Ok(()) => println!("Exported the strategy to {}.", path.display()),
+            Err(e) => report(Failure::Internal, &format!("Could not write the export: {}", e)),
+        },
+        None => println!("{}", output.trim_end()),
+    }
+}
+
+/// Let the user browse the solution tree of a puzzle.
+fn explore(args: AnalyzeArgs) {
+    let Some(prepared) = prepare_puzzle(&args.puzzle) else {
+        return;
+    };
+    let mut explorer = Explorer::new(&prepared, args.tree.depth, args.tree.page_size);
+    frontend::run(&mut explorer, &mut Terminal, &mut Transcript::default());
+}
+
+/// Secretly pick one of a puzzle's solutions and answer the user's tests
+/// like the machine would, until they guess the code.
+fn practice(args: PracticeArgs) {
+    let Some(prepared) = prepare_puzzle(&args.puzzle) else {
+        return;
+    };
+    let mut rng = match args.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    // the solutions come in no particular order, so sort them for the seed.
+    let mut candidates: Vec<&Feasible<Code>> = prepared.unique_solutions.iter().collect();
+    candidates.sort_by_key(|(_, c)| (c.blue, c.yellow, c.purple));
+    let secret = *candidates.choose(&mut rng).unwrap();
+    frontend::run(&mut Practice::new(&prepared, secret), &mut Terminal, &mut Transcript::default());
+}
+
+fn hint(args: PuzzleArgs) {
+    let Some((puzzle, candidates)) = find_candidates(&args) else {
+        return;
+    };
+    let letters = puzzle.letters();
+    let remaining = &candidates.unique_solutions;
+    if remaining.len() == 1 {
+        println!("Your code is: {}", remaining[0].1.colored());
+        return;
+    }
+    println!("{} left.", count_codes(remaining.len()));
+
+    // look for the test that splits the remaining codes as evenly as possible.
+    let codes: Vec<Code> = CodeSpace::standard().codes().collect();
+    let mut best = None;
+    for code in &codes {
+        for i in 0..letters.len() {
+            let passed = remaining.iter().filter(|s| passes(&candidates.matrix, s, code, i)).count();
+            let value = passed.min(remaining.len() - passed);
+            if best.is_none_or(|(v, _, _, _)| value > v) {
+                best = Some((value, code, i, passed));
+            }
+        }
+    }
+    let (_, code, i, passed) = best.unwrap();
+    println!("Best next test: use {} on verifier {} ({} {} {}, {} {} {}).",
+        code.colored(), letters[i],
+        style::checkmark(), style::arrow(), count_codes(passed),
+        style::cross(), style::arrow(), count_codes(remaining.len() - passed));
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    style::set_theme(&cli.theme);
+    i18n::set_language(cli.lang.as_deref().or(i18n::detect()).unwrap_or("en"));
+    json::set_enabled(cli.json);
+    failure::set_json(cli.errors == ErrorFormat::Json);
+    let ansi = style::enable_ansi();
+    style::set_color(ansi && !cli.no_color && !cli.ascii && style::detect_color());
+    style::set_ascii(cli.ascii);
+    style::set_spell_out(cli.spell_out);
+    style::set_spinner(&cli.spinner);
+    style::set_animate(!cli.no_spinner && style::detect_animate());
+    let verbose = match &cli.command {
+        Some(Command::Solve(args)) => args.verbose,
+        _ => 0,
+    };
+    init_logging(cli.log_level, verbose);
+    if cli.rpc {
+        rpc::run();
+        return failure::status();
+    }
+    match cli.command {
+        Some(Command::Solve(args)) => solve(args),
+        Some(Command::Load(args)) => load(args),
+        Some(Command::Replay(args)) => replay_transcript(args),
+        Some(Command::ListRules) => list_rules(),
+        Some(Command::Generate(args)) => generate(args),
+        Some(Command::Simulate(args)) => simulate(args),
+        Some(Command::Analyze(args)) => analyze(args),
+        Some(Command::Explore(args)) => explore(args),
+        Some(Command::Practice(args)) => practice(args),
+        Some(Command::Hint(args)) => hint(args),
+        Some(Command::Export(args)) => export_strategy(args),
+        Some(Command::Bench(args)) => bench::run(args),
+        #[cfg(feature = "network")]
+        Some(Command::Fetch(args)) => fetch::run(args),
+        #[cfg(feature = "server")]
+        Some(Command::Serve(args)) => serve::run(args),
+        None => {
+            if let Some(puzzle) = wizard::run(&mut Terminal) {
+                solve(SolveArgs { puzzle, ..Default::default() });
+            }
+        },
+    }
+    failure::status()
+}
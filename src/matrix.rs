@@ -0,0 +1,114 @@
+//! The results of a puzzle's rules for every code, evaluated once.
+//!
+//! Grouping codes, removing those that don't need all verifiers and checking
+//! known tests ask the same rules about the same codes over and over. A
+//! [`RuleMatrix`] evaluates every rule for every code of a [`CodeSpace`] up
+//! front into a dense table, and is a [`RuleSet`] itself, so it can take the
+//! place of the rule indices anywhere:
+//!
+//! ```
+//! use tm_solver::matrix::RuleMatrix;
+//! use tm_solver::rules::{Code, RULES};
+//! use tm_solver::space::CodeSpace;
+//! use tm_solver::RuleSet;
+//!
+//! let rules = [3, 8, 10, 13];
+//! let space = CodeSpace::standard();
+//! let matrix = RuleMatrix::new(&space, &rules);
+//! let code = Code { blue: 2, yellow: 4, purple: 1 };
+//! assert_eq!(matrix.category(1, &code), RULES[8](&code));
+//!
+//! let codes: Vec<Code> = space.codes().collect();
+//! assert_eq!(tm_solver::categorize_codes(&codes, &matrix), tm_solver::categorize_codes(&codes, &rules));
+//! ```
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::rules::{Code, CodeColumns, CATEGORY_COUNTS, RULES};
+use crate::space::CodeSpace;
+use crate::RuleSet;
+
+/// The category every rule of a puzzle puts every code of a space in.
+#[derive(Debug, Clone)]
+pub struct RuleMatrix {
+    /// the (zero-based) rules, i. e. the columns.
+    rules: Vec<usize>,
+    /// the lowest digit and the number of digits of blue, yellow and purple.
+    digits: [(u8, usize); 3],
+    /// one row per code, ordered by blue, then yellow, then purple, with
+    /// one entry per rule.
+    results: Vec<Option<u8>>,
+}
+
+impl RuleMatrix {
+
+    /// Evaluate the rules for all codes of the space. Codes the space's
+    /// filters leave out are evaluated as well, since they are cheap to
+    /// keep. Every rule is evaluated for all codes at once, see
+    /// [`CodeColumns`].
+    pub fn new(space: &CodeSpace, rules: &[usize]) -> RuleMatrix {
+        let digits = [0, 1, 2].map(|color| {
+            let range = space.digits(color);
+            (*range.start(), range.count())
+        });
+        let mut codes = CodeColumns::default();
+        for blue in space.digits(0) {
+            for yellow in space.digits(1) {
+                for purple in space.digits(2) {
+                    codes.push(Code { blue, yellow, purple });
+                }
+            }
+        }
+        let n = rules.len();
+        let mut results = vec![None; codes.len() * n];
+        let mut column = vec![None; codes.len()];
+        for (i, rule) in rules.iter().enumerate() {
+            codes.evaluate(*rule, &mut column);
+            for (row, result) in column.iter().enumerate() {
+                results[row * n + i] = *result;
+            }
+        }
+        RuleMatrix { rules: rules.to_vec(), digits, results }
+    }
+
+    /// The (zero-based) rules the matrix was evaluated for.
+    pub fn rules(&self) -> &[usize] {
+        &self.rules
+    }
+
+    /// The row of a code, if it is part of the matrix.
+    fn row(&self, code: &Code) -> Option<usize> {
+        let mut row = 0;
+        for ((low, count), digit) in self.digits.iter().zip([code.blue, code.yellow, code.purple]) {
+            let offset = digit.checked_sub(*low).map(usize::from).filter(|o| o < count)?;
+            row = row * count + offset;
+        }
+        Some(row)
+    }
+
+    /// The category of every rule for a code, or None if the code isn't
+    /// part of the matrix.
+    pub fn results(&self, code: &Code) -> Option<&[Option<u8>]> {
+        let n = self.rules.len();
+        self.row(code).map(|row| &self.results[row * n..(row + 1) * n])
+    }
+}
+
+impl RuleSet<Code> for RuleMatrix {
+    fn rule_count(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Looked up for codes of the matrix, evaluated for all others.
+    fn category(&self, i: usize, code: &Code) -> Option<u8> {
+        match self.row(code) {
+            Some(row) => self.results[row * self.rules.len() + i],
+            None => RULES[self.rules[i]](code),
+        }
+    }
+
+    fn category_count(&self, i: usize) -> Option<u8> {
+        Some(CATEGORY_COUNTS[self.rules[i]])
+    }
+}
@@ -0,0 +1,112 @@
+//! Practicing on your own: the solver secretly picks one of a puzzle's
+//! solutions and answers the user's tests like the machine would, until they
+//! guess the code.
+
+use tm_solver::construct_tree::Feasible;
+use tm_solver::rules::Code;
+use tm_solver::i18n::tr;
+use tm_solver::style;
+use crate::cli;
+use crate::frontend::{Dialog, Effects};
+use tm_solver::simulate;
+use crate::{passes, Prepared};
+
+/// A game against the machine.
+pub struct Practice<'a> {
+    prepared: &'a Prepared,
+    /// the results and code picked by the machine.
+    secret: &'a Feasible<Code>,
+    questions: usize,
+    rounds: usize,
+    wrong_guesses: usize,
+    /// the code of the current round and the verifiers tested with it.
+    round: Option<(Code, Vec<usize>)>,
+    finished: bool,
+}
+
+impl<'a> Practice<'a> {
+
+    /// A game with the given secret, one of the puzzle's unique solutions.
+    pub fn new(prepared: &'a Prepared, secret: &'a Feasible<Code>) -> Practice<'a> {
+        Practice { prepared, secret, questions: 0, rounds: 0, wrong_guesses: 0, round: None, finished: false }
+    }
+
+    /// Compare the user's guess to the secret code.
+    fn guess(&mut self, guess: &str, effects: &mut Effects) {
+        match cli::parse_code(guess) {
+            Ok(code) if code == self.secret.1 => {
+                effects.show(tr!(correct_code, self.secret.1.colored()));
+                effects.show(tr!(practice_stats, self.questions, self.rounds, self.wrong_guesses));
+                let steps = simulate::simulate(&self.prepared.tree, self.secret).unwrap_or_default();
+                effects.show(tr!(solver_stats, steps.len(), simulate::rounds(&steps)));
+                self.finished = true;
+            },
+            Ok(_) => {
+                effects.show(tr!(wrong_code));
+                self.wrong_guesses += 1;
+            },
+            Err(e) => effects.show(e),
+        }
+    }
+
+    /// Test a code on a verifier, e. g. `241 A`.
+    fn test(&mut self, input: &str, effects: &mut Effects) {
+        let letters = &self.prepared.letters;
+        let mut parts = input.split_whitespace();
+        let (Some(code), Some(letter), None) = (parts.next(), parts.next(), parts.next()) else {
+            return effects.show(tr!(invalid_test));
+        };
+        let code = match cli::parse_code(code) {
+            Ok(c) => c,
+            Err(e) => return effects.show(e),
+        };
+        let letter = letter.to_ascii_uppercase();
+        let Some(i) = letters.iter().position(|l| letter == l.to_string()) else {
+            return effects.show(tr!(no_verifier, letter));
+        };
+
+        // a new code, or one verifier more than a round allows, starts a new
+        // round.
+        match &mut self.round {
+            Some((c, asked)) if *c == code && asked.contains(&i) => {
+                return effects.show(tr!(already_tested, letters[i], code.colored()));
+            },
+            Some((c, asked)) if *c == code && asked.len() < self.prepared.solver.round_size() as usize => asked.push(i),
+            _ => {
+                self.rounds += 1;
+                effects.show(style::bold(&tr!(practice_round, self.rounds, code.colored())));
+                self.round = Some((code, vec![i]));
+            },
+        }
+        self.questions += 1;
+        let mark = match passes(&self.prepared.matrix, self.secret, &code, i) {
+            true => style::checkmark(),
+            false => style::cross(),
+        };
+        effects.show(format!("  {}: {}", tr!(test, letters[i]), mark));
+    }
+}
+
+impl Dialog for Practice<'_> {
+    fn start(&mut self, effects: &mut Effects) {
+        effects.show(tr!(practice_start));
+        effects.show(tr!(practice_rules, self.prepared.solver.round_size()));
+    }
+
+    fn input(&mut self, input: &str, effects: &mut Effects) {
+        let input = input.trim();
+        if input == "q" {
+            effects.show(tr!(code_was, self.secret.1.colored()));
+            self.finished = true;
+            return;
+        }
+        match input.strip_prefix("guess") {
+            Some(guess) => self.guess(guess.trim(), effects),
+            None => self.test(input, effects),
+        }
+    }
+
+    fn finished(&self) -> bool {
+        self.finished
+    }
+}
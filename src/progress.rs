@@ -0,0 +1,40 @@
+//! Progress reports for long-running steps, so that programs can keep their
+//! users informed in their own way, e. g. with a spinner or a progress bar.
+//!
+//! ```
+//! use std::sync::Mutex;
+//! use tm_solver::progress::ProgressSink;
+//! use tm_solver::solver::Solver;
+//!
+//! #[derive(Default)]
+//! struct Phases(Mutex<Vec<String>>);
+//!
+//! impl ProgressSink for Phases {
+//!     fn start(&self, phase: &str) {
+//!         self.0.lock().unwrap().push(phase.to_string());
+//!     }
+//!     fn finish(&self) {}
+//! }
+//!
+//! let phases = std::sync::Arc::new(Phases::default());
+//! Solver::builder().rules(&[4, 9, 11, 14]).progress(phases.clone()).solve()?;
+//! assert_eq!(phases.0.lock().unwrap().last().unwrap(), "Construct optimal tree");
+//! # Ok::<(), tm_solver::error::TmSolverError>(())
+//! ```
+
+/// Receives the progress of the solver. Phases don't overlap: every
+/// [`start`](ProgressSink::start) is followed by a
+/// [`finish`](ProgressSink::finish) before the next phase starts.
+pub trait ProgressSink: Sync + Send {
+
+    /// A new phase starts, e. g. "Generating codes".
+    fn start(&self, phase: &str);
+
+    /// The current phase is done to `percent` percent. Not every phase
+    /// reports this, and the percentage may jump, e. g. to 100 when an
+    /// optimal tree is found early.
+    fn progress(&self, _percent: u8) {}
+
+    /// The current phase is done.
+    fn finish(&self);
+}
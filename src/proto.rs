@@ -0,0 +1,155 @@
+//! Protocol Buffers messages for puzzles, solution trees and games, enabled
+//! with the `protobuf` feature, e. g. for gRPC services built around the
+//! solver.
+//!
+//! The messages are defined in `proto/tm_solver.proto` (package
+//! `tm_solver.v1`), which other languages can generate their types from. The
+//! Rust types below are generated from it by prost-build and checked in, so
+//! building the crate doesn't need `protoc`. Regenerate them whenever the
+//! definition changes.
+//!
+//! ```
+//! use prost::Message;
+//! use tm_solver::proto;
+//!
+//! let puzzle = proto::Puzzle { cards: vec![4, 9, 11, 14], known: Vec::new() };
+//! let strategy = puzzle.builder()?.solve()?;
+//! let bytes = proto::Strategy::from(&strategy).encode_to_vec();
+//!
+//! let decoded = proto::Strategy::decode(bytes.as_slice()).unwrap();
+//! let tree = decoded.tree.as_ref().unwrap().to_tree()?;
+//! assert_eq!(tree.leaf_count(), strategy.unique_solutions.len());
+//! # Ok::<(), tm_solver::error::TmSolverError>(())
+//! ```
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use crate::construct_tree::{self, BinaryTree};
+use crate::error::TmSolverError;
+use crate::rules;
+use crate::session;
+use crate::solver::{self, Solver, SolverBuilder};
+
+include!("proto/tm_solver.v1.rs");
+
+impl From<rules::Code> for Code {
+    fn from(code: rules::Code) -> Self {
+        Code { blue: code.blue.into(), yellow: code.yellow.into(), purple: code.purple.into() }
+    }
+}
+
+impl TryFrom<Code> for rules::Code {
+    type Error = TmSolverError;
+
+    fn try_from(code: Code) -> Result<Self, TmSolverError> {
+        let digit = |d: u32| u8::try_from(d)
+            .map_err(|_| TmSolverError::InvalidCode(format!("{}{}{}", code.blue, code.yellow, code.purple)));
+        Ok(rules::Code { blue: digit(code.blue)?, yellow: digit(code.yellow)?, purple: digit(code.purple)? })
+    }
+}
+
+impl From<construct_tree::Answer> for Answer {
+    fn from(answer: construct_tree::Answer) -> Self {
+        match answer {
+            construct_tree::Answer::Pass => Answer::Pass,
+            construct_tree::Answer::Fail => Answer::Fail,
+        }
+    }
+}
+
+impl TryFrom<Answer> for construct_tree::Answer {
+    type Error = TmSolverError;
+
+    fn try_from(answer: Answer) -> Result<Self, TmSolverError> {
+        match answer {
+            Answer::Pass => Ok(construct_tree::Answer::Pass),
+            Answer::Fail => Ok(construct_tree::Answer::Fail),
+            Answer::Unspecified => Err(TmSolverError::InvalidMessage("the answer is missing".to_string())),
+        }
+    }
+}
+
+impl Puzzle {
+
+    /// A solver for the puzzle, with its known tests.
+    pub fn builder(&self) -> Result<SolverBuilder, TmSolverError> {
+        let cards: Vec<usize> = self.cards.iter().map(|c| *c as usize).collect();
+        let mut builder = Solver::builder().rules(&cards);
+        for known in &self.known {
+            let code = known.code.ok_or_else(|| missing("code"))?.try_into()?;
+            builder = builder.known(code, known.verifier as usize, known.answer().try_into()?);
+        }
+        Ok(builder)
+    }
+}
+
+impl From<&BinaryTree<rules::Code>> for Tree {
+    fn from(tree: &BinaryTree<rules::Code>) -> Self {
+        let node = match tree {
+            BinaryTree::Leaf(c) => tree::Node::Leaf((*c).into()),
+            BinaryTree::Branch(b) => tree::Node::Branch(Box::new(Branch {
+                verifier: b.test.0 as u32,
+                category: b.test.1.into(),
+                correct: Some(Box::new((&b.correct).into())),
+                incorrect: Some(Box::new((&b.incorrect).into())),
+                code: b.code.map(Code::from),
+            })),
+        };
+        Tree { node: Some(node) }
+    }
+}
+
+impl Tree {
+
+    /// The solution tree again.
+    pub fn to_tree(&self) -> Result<BinaryTree<rules::Code>, TmSolverError> {
+        match self.node.as_ref().ok_or_else(|| missing("node"))? {
+            tree::Node::Leaf(c) => Ok(BinaryTree::Leaf((*c).try_into()?)),
+            tree::Node::Branch(b) => {
+                let category = u8::try_from(b.category)
+                    .map_err(|_| TmSolverError::InvalidMessage(format!("{} is not a category", b.category)))?;
+                Ok(BinaryTree::Branch(Box::new(construct_tree::Branch {
+                    test: (b.verifier as usize, category),
+                    correct: b.correct.as_ref().ok_or_else(|| missing("correct"))?.to_tree()?,
+                    incorrect: b.incorrect.as_ref().ok_or_else(|| missing("incorrect"))?.to_tree()?,
+                    code: b.code.map(rules::Code::try_from).transpose()?,
+                })))
+            },
+        }
+    }
+}
+
+impl From<&solver::Strategy> for Strategy {
+    fn from(strategy: &solver::Strategy) -> Self {
+        Strategy {
+            cards: strategy.rules.iter().map(|r| *r as u32 + 1).collect(),
+            unique_solutions: strategy.unique_solutions.iter().map(|(_, c)| (*c).into()).collect(),
+            tree: Some((&strategy.tree).into()),
+        }
+    }
+}
+
+impl From<session::Question> for Question {
+    fn from(question: session::Question) -> Self {
+        Question {
+            code: Some(question.code.into()),
+            verifier: question.test.0 as u32,
+            category: question.test.1.into(),
+            new_round: question.new_round,
+        }
+    }
+}
+
+impl From<session_event::Event> for SessionEvent {
+    fn from(event: session_event::Event) -> Self {
+        SessionEvent { event: Some(event) }
+    }
+}
+
+/// A required field that wasn't set.
+fn missing(field: &str) -> TmSolverError {
+    TmSolverError::InvalidMessage(format!("the field {} is missing", field))
+}
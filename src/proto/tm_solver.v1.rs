@@ -0,0 +1,153 @@
+// This file is @generated by prost-build.
+/// A three-digit code, with each digit from 1 to 5 in the game.
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct Code {
+    #[prost(uint32, tag = "1")]
+    pub blue: u32,
+    #[prost(uint32, tag = "2")]
+    pub yellow: u32,
+    #[prost(uint32, tag = "3")]
+    pub purple: u32,
+}
+/// A test performed before solving.
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct KnownTest {
+    #[prost(message, optional, tag = "1")]
+    pub code: ::core::option::Option<Code>,
+    /// the zero-based index of the verifier.
+    #[prost(uint32, tag = "2")]
+    pub verifier: u32,
+    #[prost(enumeration = "Answer", tag = "3")]
+    pub answer: i32,
+}
+/// A puzzle to solve.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Puzzle {
+    /// the number of each verifier's criteria card, from 1 to 25.
+    #[prost(uint32, repeated, tag = "1")]
+    pub cards: ::prost::alloc::vec::Vec<u32>,
+    #[prost(message, repeated, tag = "2")]
+    pub known: ::prost::alloc::vec::Vec<KnownTest>,
+}
+/// A solution tree: either the code that has been found, or a test to
+/// perform next.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Tree {
+    #[prost(oneof = "tree::Node", tags = "1, 2")]
+    pub node: ::core::option::Option<tree::Node>,
+}
+/// Nested message and enum types in `Tree`.
+pub mod tree {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Node {
+        #[prost(message, tag = "1")]
+        Leaf(super::Code),
+        #[prost(message, tag = "2")]
+        Branch(::prost::alloc::boxed::Box<super::Branch>),
+    }
+}
+/// A test within a solution tree.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Branch {
+    /// the zero-based index of the verifier to test.
+    #[prost(uint32, tag = "1")]
+    pub verifier: u32,
+    /// the category of the verifier's card the test checks for.
+    #[prost(uint32, tag = "2")]
+    pub category: u32,
+    /// the tree to follow if the verifier yields a checkmark.
+    #[prost(message, optional, boxed, tag = "3")]
+    pub correct: ::core::option::Option<::prost::alloc::boxed::Box<Tree>>,
+    /// the tree to follow if the verifier yields a cross.
+    #[prost(message, optional, boxed, tag = "4")]
+    pub incorrect: ::core::option::Option<::prost::alloc::boxed::Box<Tree>>,
+    /// set if this test starts a new round: the code to use for it.
+    #[prost(message, optional, tag = "5")]
+    pub code: ::core::option::Option<Code>,
+}
+/// The optimal strategy for a puzzle.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Strategy {
+    /// the number of each verifier's criteria card, from 1 to 25.
+    #[prost(uint32, repeated, tag = "1")]
+    pub cards: ::prost::alloc::vec::Vec<u32>,
+    /// the codes the tree can tell apart.
+    #[prost(message, repeated, tag = "2")]
+    pub unique_solutions: ::prost::alloc::vec::Vec<Code>,
+    #[prost(message, optional, tag = "3")]
+    pub tree: ::core::option::Option<Tree>,
+}
+/// A test to perform during a game.
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct Question {
+    #[prost(message, optional, tag = "1")]
+    pub code: ::core::option::Option<Code>,
+    /// the zero-based index of the verifier to test.
+    #[prost(uint32, tag = "2")]
+    pub verifier: u32,
+    /// the category of the verifier's card the test checks for.
+    #[prost(uint32, tag = "3")]
+    pub category: u32,
+    /// whether this test starts a new round, i. e. a new code.
+    #[prost(bool, tag = "4")]
+    pub new_round: bool,
+}
+/// Something that happened during a game.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SessionEvent {
+    #[prost(oneof = "session_event::Event", tags = "1, 2, 3, 4, 5")]
+    pub event: ::core::option::Option<session_event::Event>,
+}
+/// Nested message and enum types in `SessionEvent`.
+pub mod session_event {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Event {
+        /// the puzzle of the game.
+        #[prost(message, tag = "1")]
+        Puzzle(super::Puzzle),
+        /// a question was asked.
+        #[prost(message, tag = "2")]
+        Question(super::Question),
+        /// the current question was answered.
+        #[prost(enumeration = "super::Answer", tag = "3")]
+        Answer(i32),
+        /// the last answers were taken back.
+        #[prost(uint32, tag = "4")]
+        Undo(u32),
+        /// the code has been found.
+        #[prost(message, tag = "5")]
+        Solution(super::Code),
+    }
+}
+/// The result of a test.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum Answer {
+    Unspecified = 0,
+    /// the verifier yielded a checkmark.
+    Pass = 1,
+    /// the verifier yielded a cross.
+    Fail = 2,
+}
+impl Answer {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Self::Unspecified => "ANSWER_UNSPECIFIED",
+            Self::Pass => "ANSWER_PASS",
+            Self::Fail => "ANSWER_FAIL",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "ANSWER_UNSPECIFIED" => Some(Self::Unspecified),
+            "ANSWER_PASS" => Some(Self::Pass),
+            "ANSWER_FAIL" => Some(Self::Fail),
+            _ => None,
+        }
+    }
+}
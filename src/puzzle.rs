@@ -0,0 +1,170 @@
+//! The definition of a puzzle as a value: its criteria cards with their
+//! verifier letters, the tests known beforehand and the options for solving
+//! it.
+//!
+//! Puzzles are kept in a canonical form, so two puzzles are equal exactly if
+//! they describe the same game, no matter in which order their verifiers or
+//! known tests were given. That makes them usable as cache keys:
+//!
+//! ```
+//! use std::collections::HashMap;
+//! use tm_solver::puzzle::Puzzle;
+//!
+//! let puzzle = Puzzle::new(&[4, 9, 11, 14])?;
+//! let same = Puzzle::with_letters(&[('D', 14), ('B', 9), ('A', 4), ('C', 11)])?;
+//! assert_eq!(puzzle, same);
+//!
+//! let mut strategies = HashMap::new();
+//! strategies.insert(puzzle.clone(), puzzle.solve()?);
+//! assert!(strategies.contains_key(&same));
+//! assert!(!strategies.contains_key(&same.lookahead(true)));
+//! # Ok::<(), tm_solver::error::TmSolverError>(())
+//! ```
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::construct_tree::{Answer, Objective};
+use crate::error::TmSolverError;
+use crate::rules::Code;
+use crate::solver::{self, Solver, SolverBuilder, Strategy};
+
+/// A puzzle and how to solve it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Puzzle {
+    /// the letter and criteria card number of each verifier, ordered by
+    /// letter.
+    verifiers: Vec<(char, usize)>,
+    /// the tests performed beforehand, by verifier letter, ordered and
+    /// without repetitions.
+    known: Vec<(Code, char, Answer)>,
+    tests_per_round: u8,
+    max_questions: Option<u8>,
+    lookahead: bool,
+    objective: Objective,
+}
+
+impl Puzzle {
+
+    /// A puzzle with the given criteria card numbers, e. g.
+    /// `&[4, 9, 11, 14]`, for verifiers A, B, C and so on.
+    pub fn new(cards: &[usize]) -> Result<Puzzle, TmSolverError> {
+        let verifiers: Vec<(char, usize)> = cards.iter().enumerate()
+            .map(|(i, c)| ((b'A' + i as u8) as char, *c))
+            .collect();
+        Self::with_letters(&verifiers)
+    }
+
+    /// A puzzle with the given verifier letters and criteria card numbers,
+    /// in any order.
+    pub fn with_letters(verifiers: &[(char, usize)]) -> Result<Puzzle, TmSolverError> {
+        let mut verifiers: Vec<(char, usize)> = verifiers.iter().map(|(l, c)| (l.to_ascii_uppercase(), *c)).collect();
+        let cards: Vec<usize> = verifiers.iter().map(|(_, c)| *c).collect();
+        solver::validate(&cards)?;
+        verifiers.sort_unstable();
+        if let Some(pair) = verifiers.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(TmSolverError::DuplicateVerifier(pair[0].0));
+        }
+        Ok(Puzzle {
+            verifiers,
+            known: Vec::new(),
+            tests_per_round: 3,
+            max_questions: None,
+            lookahead: false,
+            objective: Objective::WorstCase,
+        })
+    }
+
+    /// The same puzzle with a test that was already performed: the code, the
+    /// letter of the verifier and its answer. Can be repeated.
+    pub fn known(mut self, code: Code, letter: char, answer: Answer) -> Self {
+        let test = (code, letter.to_ascii_uppercase(), answer);
+        let key = |(c, l, a): &(Code, char, Answer)| (c.blue, c.yellow, c.purple, *l, a.passed());
+        if let Err(i) = self.known.binary_search_by_key(&key(&test), key) {
+            self.known.insert(i, test);
+        }
+        self
+    }
+
+    /// How many tests can be performed with the same code. Defaults to 3.
+    /// Zero makes [`Puzzle::builder`] fail with
+    /// [`TmSolverError::EmptyRound`].
+    pub fn tests_per_round(mut self, tests: u8) -> Self {
+        self.tests_per_round = tests;
+        self
+    }
+
+    /// Reject strategies that might need more tests than this.
+    pub fn max_questions(mut self, questions: u8) -> Self {
+        self.max_questions = Some(questions);
+        self
+    }
+
+    /// Rank candidate tests by looking two tests ahead.
+    pub fn lookahead(mut self, lookahead: bool) -> Self {
+        self.lookahead = lookahead;
+        self
+    }
+
+    /// What the strategy minimizes first. Defaults to the worst case.
+    pub fn objective(mut self, objective: Objective) -> Self {
+        self.objective = objective;
+        self
+    }
+
+    /// The letter of each verifier, in order.
+    pub fn letters(&self) -> Vec<char> {
+        self.verifiers.iter().map(|(l, _)| *l).collect()
+    }
+
+    /// The criteria card number of each verifier, in the order of
+    /// [`letters`](Puzzle::letters).
+    pub fn cards(&self) -> Vec<usize> {
+        self.verifiers.iter().map(|(_, c)| *c).collect()
+    }
+
+    /// The tests performed beforehand.
+    pub fn known_tests(&self) -> &[(Code, char, Answer)] {
+        &self.known
+    }
+
+    /// A solver for the puzzle, e. g. to report its progress as well.
+    pub fn builder(&self) -> Result<SolverBuilder, TmSolverError> {
+        if self.tests_per_round == 0 {
+            return Err(TmSolverError::EmptyRound);
+        }
+        let mut builder = Solver::builder()
+            .rules(&self.cards())
+            .tests_per_round(self.tests_per_round)
+            .lookahead(self.lookahead)
+            .objective(self.objective);
+        if let Some(questions) = self.max_questions {
+            builder = builder.max_questions(questions);
+        }
+        for (code, letter, answer) in &self.known {
+            let i = self.verifiers.iter().position(|(l, _)| l == letter)
+                .ok_or(TmSolverError::UnknownVerifier(*letter))?;
+            builder = builder.known(*code, i, *answer);
+        }
+        Ok(builder)
+    }
+
+    /// Construct the optimal strategy for the puzzle.
+    pub fn solve(&self) -> Result<Strategy, TmSolverError> {
+        self.builder()?.solve()
+    }
+}
+
+impl fmt::Display for Puzzle {
+
+    /// The verifiers and their criteria cards, e. g. A=4 B=9 C=11 D=14.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (letter, card)) in self.verifiers.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{}={}", letter, card)?;
+        }
+        Ok(())
+    }
+}
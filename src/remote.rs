@@ -0,0 +1,153 @@
+//! Games driven by other programs, shared by the JSON-RPC mode and the HTTP
+//! server. A game follows the optimal strategy with the answers it's given
+//! and reports its state as JSON:
+//!
+//! ```text
+//! {"id":1,"node":0,"remaining":8,
+//!  "question":{"code":"241","verifier":"D","card":17,"new_round":true},
+//!  "solution":null}
+//! ```
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use tm_solver::construct_tree::Answer;
+use tm_solver::error::TmSolverError;
+use tm_solver::export;
+use tm_solver::session::GameSession;
+use tm_solver::solver::{Solver, Strategy};
+
+/// A game in progress.
+pub struct Game {
+    strategy: Strategy,
+    letters: Vec<char>,
+    answers: Vec<Answer>,
+}
+
+/// The next test to perform.
+#[derive(Serialize)]
+pub struct Question {
+    code: String,
+    verifier: char,
+    card: usize,
+    new_round: bool,
+}
+
+/// What a client needs to know about a game.
+#[derive(Serialize)]
+pub struct State {
+    id: u64,
+    /// the id of the current node of the strategy, see the `traverse` module
+    /// of the library.
+    node: usize,
+    /// the number of codes that are still possible.
+    remaining: usize,
+    question: Option<Question>,
+    /// the code, once the answers identify it.
+    solution: Option<String>,
+}
+
+impl Game {
+
+    /// Construct the optimal strategy for a puzzle and start a game with it.
+    pub fn new(rules: &[usize]) -> Result<Game, TmSolverError> {
+        let strategy = Solver::builder().rules(rules).solve()?;
+        let letters = (0..rules.len()).map(|i| (b'A' + i as u8) as char).collect();
+        Ok(Game { strategy, letters, answers: Vec::new() })
+    }
+
+    /// The session reached with the answers so far.
+    fn session(&self) -> GameSession<'_> {
+        let mut session = self.strategy.session();
+        for answer in &self.answers {
+            session.answer(*answer);
+        }
+        session
+    }
+
+    /// The current state of the game.
+    fn state(&self, id: u64) -> State {
+        let session = self.session();
+        State {
+            id,
+            node: session.node_id(),
+            remaining: session.remaining().len(),
+            question: session.next_question().map(|q| Question {
+                code: q.code.to_string(),
+                verifier: self.letters[q.test.0],
+                card: self.strategy.rules[q.test.0] + 1,
+                new_round: q.new_round,
+            }),
+            solution: session.solution().map(|c| c.to_string()),
+        }
+    }
+}
+
+/// All games of a client, by their ids.
+#[derive(Default)]
+pub struct Games {
+    games: HashMap<u64, Game>,
+    next_id: u64,
+    /// how many games are kept at most, if limited.
+    max_games: Option<usize>,
+}
+
+impl Games {
+
+    /// Keep at most the given number of games: starting another one forgets
+    /// the oldest.
+    #[cfg(feature = "server")]
+    pub fn limited(max_games: usize) -> Games {
+        Games { max_games: Some(max_games), ..Games::default() }
+    }
+
+    /// Construct the optimal strategy for a puzzle and start a game with it.
+    pub fn solve(&mut self, rules: &[usize]) -> Result<State, TmSolverError> {
+        Ok(self.start(Game::new(rules)?))
+    }
+
+    /// Keep track of a new game.
+    pub fn start(&mut self, game: Game) -> State {
+        if let Some(max) = self.max_games {
+            while self.games.len() >= max.max(1) {
+                let Some(oldest) = self.games.keys().min().copied() else {
+                    break;
+                };
+                self.games.remove(&oldest);
+            }
+        }
+        self.next_id += 1;
+        let state = game.state(self.next_id);
+        self.games.insert(self.next_id, game);
+        state
+    }
+
+    /// Answer the current question of a game. Ignored once the code is
+    /// known.
+    pub fn answer(&mut self, id: u64, answer: Answer) -> Option<State> {
+        let game = self.games.get_mut(&id)?;
+        if game.session().answer(answer) {
+            game.answers.push(answer);
+        }
+        Some(game.state(id))
+    }
+
+    /// Take back the last answer of a game.
+    pub fn undo(&mut self, id: u64) -> Option<State> {
+        let game = self.games.get_mut(&id)?;
+        game.answers.pop();
+        Some(game.state(id))
+    }
+
+    /// The whole strategy of a game as JSON, see the `export` module.
+    pub fn tree(&self, id: u64) -> Option<String> {
+        let game = self.games.get(&id)?;
+        Some(export::strategy(&game.letters, &game.strategy.rules, &game.strategy.tree).to_json())
+    }
+
+    /// Forget a game. Returns whether it existed.
+    pub fn close(&mut self, id: u64) -> bool {
+        self.games.remove(&id).is_some()
+    }
+}
@@ -0,0 +1,122 @@
+//! A JSON-RPC 2.0 mode over stdin and stdout, for wrapping the solver in
+//! bots or GUIs. Every line of input is a request, every line of output the
+//! response to one, e. g.
+//!
+//! ```text
+//! --> {"jsonrpc":"2.0","id":1,"method":"solve","params":{"rules":[4,9,11,14]}}
+//! <-- {"jsonrpc":"2.0","id":1,"result":{"id":1,"remaining":2,"question":{...},"solution":null}}
+//! --> {"jsonrpc":"2.0","id":2,"method":"answer","params":{"game":1,"passed":true}}
+//! <-- {"jsonrpc":"2.0","id":2,"result":{"id":1,"remaining":1,"question":null,"solution":"221"}}
+//! ```
+//!
+//! The methods are:
+//!
+//! - `solve` with the criteria card numbers as `rules` starts a game and
+//!   returns its state, see the `remote` module.
+//! - `answer` with the `game` id and whether the test `passed` answers the
+//!   current question and returns the new state.
+//! - `undo` with the `game` id takes back the last answer.
+//! - `tree` with the `game` id returns the whole strategy, see the `export`
+//!   module.
+//! - `close` with the `game` id forgets a game.
+//!
+//! Requests without an id are notifications and don't get a response.
+
+use std::io::{self, BufRead, Write};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::remote::Games;
+
+/// The error codes defined by JSON-RPC.
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// The puzzle couldn't be solved or the game doesn't exist.
+const SOLVER_ERROR: i64 = -32000;
+
+#[derive(Deserialize)]
+struct Call {
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Deserialize)]
+struct SolveParams {
+    rules: Vec<usize>,
+}
+
+#[derive(Deserialize)]
+struct GameParams {
+    game: u64,
+}
+
+#[derive(Deserialize)]
+struct AnswerParams {
+    game: u64,
+    passed: bool,
+}
+
+/// Run a single call, returning its result or an error code and message.
+fn call(games: &mut Games, method: &str, params: Value) -> Result<Value, (i64, String)> {
+    let invalid = |e: serde_json::Error| (INVALID_PARAMS, e.to_string());
+    let unknown_game = || (SOLVER_ERROR, String::from("There is no game with this id."));
+    let game = |params: Value| serde_json::from_value::<GameParams>(params).map(|p| p.game).map_err(invalid);
+    match method {
+        "solve" => {
+            let SolveParams { rules } = serde_json::from_value(params).map_err(invalid)?;
+            games.solve(&rules)
+                .map(|state| json!(state))
+                .map_err(|e| (SOLVER_ERROR, e.to_string()))
+        },
+        "answer" => {
+            let AnswerParams { game, passed } = serde_json::from_value(params).map_err(invalid)?;
+            games.answer(game, passed.into()).map(|state| json!(state)).ok_or_else(unknown_game)
+        },
+        "undo" => games.undo(game(params)?).map(|state| json!(state)).ok_or_else(unknown_game),
+        "tree" => games.tree(game(params)?)
+            .map(|tree| serde_json::from_str(&tree).unwrap())
+            .ok_or_else(unknown_game),
+        "close" => Ok(json!(games.close(game(params)?))),
+        _ => Err((METHOD_NOT_FOUND, format!("Unknown method {}.", method))),
+    }
+}
+
+/// Answer requests from stdin until it's closed.
+pub fn run() {
+    let mut games = Games::default();
+    let mut stdout = io::stdout();
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Call>(&line) {
+            Err(e) => json!({
+                "jsonrpc": "2.0", "id": null,
+                "error": { "code": PARSE_ERROR, "message": e.to_string() },
+            }),
+            Ok(Call { id, method, params }) => {
+                let result = call(&mut games, &method, params);
+                let Some(id) = id else {
+                    continue;
+                };
+                match result {
+                    Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                    Err((code, message)) => json!({
+                        "jsonrpc": "2.0", "id": id,
+                        "error": { "code": code, "message": message },
+                    }),
+                }
+            },
+        };
+        if writeln!(stdout, "{}", response).and_then(|_| stdout.flush()).is_err() {
+            return;
+        }
+    }
+}
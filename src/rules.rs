@@ -6,32 +6,233 @@
 //! 
 //! Criteria cards that can have multiple rulesets are not yet implemented.
 
-/// A three-digit code
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::TmSolverError;
+use crate::space::CodeSpace;
+#[cfg(feature = "std")]
+use crate::style::ColoredCode;
+
+/// A three-digit code. It only takes three bytes, so it's simply copied
+/// around.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct Code {
     pub blue: u8,
     pub yellow: u8,
     pub purple: u8,
 }
 
-impl ToString for Code {
+impl fmt::Display for Code {
+
+    /// The plain digits of the code, e. g. 241.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}", self.blue, self.yellow, self.purple)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Code {
+
+    /// A nice representation of the code to print to the console, colored or
+    /// spelled out depending on the settings of the [`style`](crate::style)
+    /// module.
+    pub fn colored(&self) -> ColoredCode<'_> {
+        ColoredCode(self)
+    }
+}
+
+impl FromStr for Code {
+    type Err = TmSolverError;
+
+    /// Parse a code given either as three digits from 1 to 5, e. g. 415, or
+    /// as one digit per color, e. g. "b4 y1 p3". The colors can be given in
+    /// any order and in upper or lower case. See [`CodeSpace::parse`] for
+    /// codes with other digits.
+    ///
+    /// ```
+    /// use tm_solver::rules::Code;
+    ///
+    /// let code = Code { blue: 4, yellow: 1, purple: 3 };
+    /// assert_eq!("413".parse::<Code>().unwrap(), code);
+    /// assert_eq!("b4 y1 p3".parse::<Code>().unwrap(), code);
+    /// assert_eq!("P3 B4 Y1".parse::<Code>().unwrap(), code);
+    /// assert!("416".parse::<Code>().is_err());
+    /// ```
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        CodeSpace::standard().parse(input)
+    }
+}
+
+/// Define a category enum, whose variants are encoded as their position,
+/// along with the conversions from and to that encoding.
+macro_rules! category {
+    ($(#[$meta:meta])* $name:ident { $($(#[$vmeta:meta])* $variant:ident => $label:literal),+ $(,)? }) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $($(#[$vmeta])* $variant),+
+        }
+
+        impl $name {
+            const ALL: &'static [$name] = &[$($name::$variant),+];
+        }
+
+        impl From<$name> for u8 {
+            fn from(category: $name) -> u8 {
+                category as u8
+            }
+        }
+
+        impl TryFrom<u8> for $name {
+            type Error = u8;
+
+            fn try_from(value: u8) -> Result<Self, u8> {
+                $name::ALL.get(value as usize).copied().ok_or(value)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(match self {
+                    $($name::$variant => $label),+
+                })
+            }
+        }
+    };
+}
 
-    /// A nice representation of the code to print to the console.
-    fn to_string(&self) -> String {
-        format!("\x1b[34m{}\x1b[33m{}\x1b[35m{}\x1b[0m", self.blue, self.yellow, self.purple)
+category! {
+    /// How a value compares to a target, see rules 1 - 4, 11 - 13, 19 and 23.
+    Comparison {
+        Less => "less",
+        Equal => "equal",
+        Greater => "greater",
+    }
+}
+
+impl From<core::cmp::Ordering> for Comparison {
+    fn from(ordering: core::cmp::Ordering) -> Self {
+        match ordering {
+            core::cmp::Ordering::Less => Comparison::Less,
+            core::cmp::Ordering::Equal => Comparison::Equal,
+            core::cmp::Ordering::Greater => Comparison::Greater,
+        }
+    }
+}
+
+category! {
+    /// Whether a value, or the majority of the digits, is even or odd, see
+    /// rules 5 - 7, 16 and 18.
+    Parity {
+        Even => "even",
+        Odd => "odd",
+    }
+}
+
+category! {
+    /// A color of the code, see rules 14 and 15.
+    Color {
+        Blue => "blue",
+        Yellow => "yellow",
+        Purple => "purple",
+    }
+}
+
+category! {
+    /// How often the most common digit appears, see rule 20.
+    Repetition {
+        Triple => "three times",
+        Double => "twice",
+        Single => "once",
+    }
+}
+
+category! {
+    /// Whether the digits are in order, see rule 22.
+    Order {
+        Ascending => "ascending",
+        Descending => "descending",
+        Unordered => "no order",
+    }
+}
+
+/// The result of a rule in its typed form. The rules themselves, and the
+/// search, use the compact `u8` encoding of these.
+///
+/// ```
+/// use tm_solver::rules::{Category, Code, Comparison, RULES};
+///
+/// let code = Code { blue: 2, yellow: 4, purple: 1 };
+/// let value = RULES[0](&code).unwrap();
+/// assert_eq!(Category::of(0, value), Some(Category::Comparison(Comparison::Greater)));
+/// assert_eq!(Category::of(7, 1), Some(Category::Count(1)));
+/// assert_eq!(Category::of(0, 3), None);
+/// assert_eq!(u8::from(Category::Comparison(Comparison::Greater)), value);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
+    Comparison(Comparison),
+    Parity(Parity),
+    Color(Color),
+    Repetition(Repetition),
+    Order(Order),
+    /// how many digits fit the rule.
+    Count(u8),
+}
+
+impl Category {
+
+    /// Decode a result of the rule with the given (zero-based) index into
+    /// [`RULES`]. Returns None if the rule can't yield that value.
+    pub fn of(rule: usize, value: u8) -> Option<Category> {
+        match rule + 1 {
+            1..=4 | 11..=13 | 19 | 23 => value.try_into().ok().map(Category::Comparison),
+            5..=7 | 16 | 18 => value.try_into().ok().map(Category::Parity),
+            14 | 15 => value.try_into().ok().map(Category::Color),
+            20 => value.try_into().ok().map(Category::Repetition),
+            22 => value.try_into().ok().map(Category::Order),
+            8..=10 | 17 | 24 | 25 if value <= 3 => Some(Category::Count(value)),
+            21 if value <= 1 => Some(Category::Count(value)),
+            _ => None,
+        }
+    }
+}
+
+impl From<Category> for u8 {
+    fn from(category: Category) -> u8 {
+        match category {
+            Category::Comparison(c) => c.into(),
+            Category::Parity(p) => p.into(),
+            Category::Color(c) => c.into(),
+            Category::Repetition(r) => r.into(),
+            Category::Order(o) => o.into(),
+            Category::Count(n) => n,
+        }
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Category::Comparison(c) => c.fmt(f),
+            Category::Parity(p) => p.fmt(f),
+            Category::Color(c) => c.fmt(f),
+            Category::Repetition(r) => r.fmt(f),
+            Category::Order(o) => o.fmt(f),
+            Category::Count(n) => n.fmt(f),
+        }
     }
 }
 
 /// RULESET 1: Compara a single value of the code to a fixed target value.
 /// 
-/// Returns 0 if the value is smaller than the target, 1 if the value is
-/// equal to the target and 2 if the value is greater than the target.
+/// Returns how the value compares to the target, see [`Comparison`].
 fn compare_values(value: u8, target: u8) -> Option<u8> {
-    match value.cmp(&target) {
-        std::cmp::Ordering::Less => Some(0),
-        std::cmp::Ordering::Equal => Some(1),
-        std::cmp::Ordering::Greater => Some(2),
-    }
+    Some(Comparison::from(value.cmp(&target)).into())
 }
 
 /// compare blue to 1
@@ -45,11 +246,11 @@ fn rule_4(input: &Code) -> Option<u8> { compare_values(input.yellow, 4) }
 
 /// RULESET 2: Check a single value's parity.
 /// 
-/// Returns 0 if the value is even and 1 if the value is odd.
+/// Returns the value's [`Parity`].
 fn single_parity(value: u8) -> Option<u8> {
-    match value % 2 == 0 {
-        true => Some(0),
-        false => Some(1),
+    match value.is_multiple_of(2) {
+        true => Some(Parity::Even.into()),
+        false => Some(Parity::Odd.into()),
     }
 }
 
@@ -88,44 +289,44 @@ fn rule_13(input: &Code) -> Option<u8> { compare_values(input.yellow, input.purp
 
 /// Look for the smallest value
 /// 
-/// 0 -> blue, 1 -> yellow, 2 -> purple, None if there's no single smallest value
+/// Returns its [`Color`], None if there's no single smallest value
 fn rule_14(input: &Code) -> Option<u8> {
     if input.blue < input.yellow && input.blue < input.purple {
-        return Some(0);
+        return Some(Color::Blue.into());
     }
     if input.yellow < input.blue && input.yellow < input.purple {
-        return Some(1);
+        return Some(Color::Yellow.into());
     }
     if input.purple < input.yellow && input.purple < input.blue {
-        return Some(2);
+        return Some(Color::Purple.into());
     }
     None
 }
 
 /// Look for the greatest value
 /// 
-/// 0 => blue, 1 => yellow, 2 => purple, None if there's no single greatest value
+/// Returns its [`Color`], None if there's no single greatest value
 fn rule_15(input: &Code) -> Option<u8> {
     if input.blue > input.yellow && input.blue > input.purple {
-        return Some(0);
+        return Some(Color::Blue.into());
     }
     if input.yellow > input.blue && input.yellow > input.purple {
-        return Some(1);
+        return Some(Color::Yellow.into());
     }
     if input.purple > input.yellow && input.purple > input.blue {
-        return Some(2);
+        return Some(Color::Purple.into());
     }
     None
 }
 
 /// are there more odd or even digits?
 /// 
-/// even => 0, odd => 1
+/// Returns the [`Parity`] of the majority.
 fn rule_16(input: &Code) -> Option<u8> {
     let odd = input.blue % 2 + input.yellow % 2 + input.purple % 2;
     match odd >= 2 {
-        true => Some(1),
-        false => Some(0),
+        true => Some(Parity::Odd.into()),
+        false => Some(Parity::Even.into()),
     }
 }
 
@@ -138,12 +339,9 @@ fn rule_17(input: &Code) -> Option<u8> {
 
 /// is the digit sum odd or even?
 /// 
-/// even => 0, odd => 1
+/// Returns the sum's [`Parity`].
 fn rule_18(input: &Code) -> Option<u8> {
-    match (input.blue + input.yellow + input.purple) % 2 == 0 {
-        true => Some(0),
-        false => Some(1),
-    }
+    single_parity(input.blue + input.yellow + input.purple)
 }
 
 // RULE 19 is a rehash of RULESET 1
@@ -152,15 +350,15 @@ fn rule_19(input: &Code) -> Option<u8> { compare_values(input.blue + input.yello
 
 /// how many times does the most common digits appear?
 /// 
-/// returns the amount - 1.
+/// Returns the [`Repetition`].
 fn rule_20(input: &Code) -> Option<u8> {
     let no_pairs = (input.blue == input.yellow) as usize
         + (input.blue == input.purple) as usize
         + (input.yellow == input.purple) as usize;
     match no_pairs {
-        0 => Some(2),
-        1 => Some(1),
-        _ => Some(0),
+        0 => Some(Repetition::Single.into()),
+        1 => Some(Repetition::Double.into()),
+        _ => Some(Repetition::Triple.into()),
     }
 }
 
@@ -169,20 +367,20 @@ fn rule_20(input: &Code) -> Option<u8> {
 /// 
 /// returns 1 if yes, 0 otherwise.
 fn rule_21(input: &Code) -> Option<u8> {
-    Some(rule_20(input).unwrap() % 2)
+    Some((rule_20(input) == Some(Repetition::Double.into())) as u8)
 }
 
 /// are the digits ordered?
 /// 
-/// 0 => ascending order, 1 => descending order, 2 => no order.
+/// Returns the [`Order`].
 fn rule_22(input: &Code) -> Option<u8> {
     if input.blue < input.yellow && input.yellow < input.purple {
-        return Some(0);
+        return Some(Order::Ascending.into());
     }
     if input.blue > input.yellow && input.yellow > input.purple {
-        return Some(1);
+        return Some(Order::Descending.into());
     }
-    return Some(2);
+    Some(Order::Unordered.into())
 }
 
 // another rehash of RULESET 1
@@ -212,4 +410,200 @@ pub const RULES: [fn(input: &Code) -> Option<u8>; 25] = [
     rule_1, rule_2, rule_3, rule_4, rule_5, rule_6, rule_7, rule_8, rule_9,
     rule_10, rule_11, rule_12, rule_13, rule_14, rule_15, rule_16, rule_17,
     rule_18, rule_19, rule_20, rule_21, rule_22, rule_23, rule_24, rule_25,
-];
\ No newline at end of file
+];
+
+/// The digits of many codes, one column per color, for evaluating a rule for
+/// all of them at once with [`CodeColumns::evaluate`].
+///
+/// Every rule has a branch-free kernel that works on the digits of a single
+/// lane, and the same kernel runs over every lane of the columns, so the
+/// compiler turns the loop into SIMD instructions. This gives the same
+/// results as [`RULES`], just for many codes at a time:
+///
+/// ```
+/// use tm_solver::rules::{CodeColumns, RULES};
+/// use tm_solver::space::CodeSpace;
+///
+/// let space = CodeSpace::standard();
+/// let columns: CodeColumns = space.codes().collect();
+/// let mut results = vec![None; columns.len()];
+/// for rule in 0..RULES.len() {
+///     columns.evaluate(rule, &mut results);
+///     assert!(space.codes().zip(&results).all(|(code, result)| RULES[rule](&code) == *result));
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CodeColumns {
+    blue: Vec<u8>,
+    yellow: Vec<u8>,
+    purple: Vec<u8>,
+}
+
+/// What a kernel returns for codes a rule can't be applied to.
+const NO_CATEGORY: u8 = u8::MAX;
+
+/// [`compare_values`] as a kernel.
+fn compare_lane(value: u8, target: u8) -> u8 {
+    (value >= target) as u8 + (value > target) as u8
+}
+
+/// [`count_digit`] as a kernel.
+fn count_lane(b: u8, y: u8, p: u8, digit: u8) -> u8 {
+    (b == digit) as u8 + (y == digit) as u8 + (p == digit) as u8
+}
+
+/// The unique color for which `before` holds compared to both others, see
+/// rules 14 and 15, as a kernel.
+fn color_lane(b: u8, y: u8, p: u8, before: impl Fn(u8, u8) -> bool) -> u8 {
+    let blue = before(b, y) & before(b, p);
+    let yellow = before(y, b) & before(y, p);
+    let purple = before(p, y) & before(p, b);
+    // at most one of them holds.
+    yellow as u8 + purple as u8 * 2 + !(blue | yellow | purple) as u8 * NO_CATEGORY
+}
+
+/// The number of pairs of equal digits, see rules 20 and 21.
+fn pairs_lane(b: u8, y: u8, p: u8) -> u8 {
+    (b == y) as u8 + (b == p) as u8 + (y == p) as u8
+}
+
+/// [`rule_24`] as a kernel.
+fn ascending_lane(b: u8, y: u8, p: u8) -> u8 {
+    (b.wrapping_add(1) == y) as u8 + (y.wrapping_add(1) == p) as u8
+}
+
+impl CodeColumns {
+
+    /// The number of codes.
+    pub fn len(&self) -> usize {
+        self.blue.len()
+    }
+
+    /// Whether there are no codes.
+    pub fn is_empty(&self) -> bool {
+        self.blue.is_empty()
+    }
+
+    /// Add a code.
+    pub fn push(&mut self, code: Code) {
+        self.blue.push(code.blue);
+        self.yellow.push(code.yellow);
+        self.purple.push(code.purple);
+    }
+
+    /// Run a kernel over every lane.
+    fn lanes(&self, out: &mut [u8], kernel: impl Fn(u8, u8, u8) -> u8) {
+        let lanes = out.iter_mut().zip(&self.blue).zip(&self.yellow).zip(&self.purple);
+        for (((out, b), y), p) in lanes {
+            *out = kernel(*b, *y, *p);
+        }
+    }
+
+    /// The category the (zero-based) rule puts every code in, in the order
+    /// the codes were added, see [`RULES`]. Every rule gets a loop of its
+    /// own, so that its kernel is inlined into it.
+    fn evaluate_lanes(&self, rule: usize, out: &mut [u8]) {
+        match rule + 1 {
+            1 => self.lanes(out, |b, _, _| compare_lane(b, 1)),
+            2 => self.lanes(out, |b, _, _| compare_lane(b, 3)),
+            3 => self.lanes(out, |_, y, _| compare_lane(y, 3)),
+            4 => self.lanes(out, |_, y, _| compare_lane(y, 4)),
+            5 => self.lanes(out, |b, _, _| b & 1),
+            6 => self.lanes(out, |_, y, _| y & 1),
+            7 => self.lanes(out, |_, _, p| p & 1),
+            8 => self.lanes(out, |b, y, p| count_lane(b, y, p, 1)),
+            9 => self.lanes(out, |b, y, p| count_lane(b, y, p, 3)),
+            10 => self.lanes(out, |b, y, p| count_lane(b, y, p, 4)),
+            11 => self.lanes(out, |b, y, _| compare_lane(b, y)),
+            12 => self.lanes(out, |b, _, p| compare_lane(b, p)),
+            13 => self.lanes(out, |_, y, p| compare_lane(y, p)),
+            14 => self.lanes(out, |b, y, p| color_lane(b, y, p, |a, c| a < c)),
+            15 => self.lanes(out, |b, y, p| color_lane(b, y, p, |a, c| a > c)),
+            16 => self.lanes(out, |b, y, p| ((b & 1) + (y & 1) + (p & 1) >= 2) as u8),
+            17 => self.lanes(out, |b, y, p| 3 - (b & 1) - (y & 1) - (p & 1)),
+            18 => self.lanes(out, |b, y, p| b.wrapping_add(y).wrapping_add(p) & 1),
+            19 => self.lanes(out, |b, y, _| compare_lane(b.wrapping_add(y), 6)),
+            // 0, 1 or 3 pairs make a single, double or triple.
+            20 => self.lanes(out, |b, y, p| 2u8.saturating_sub(pairs_lane(b, y, p))),
+            21 => self.lanes(out, |b, y, p| (pairs_lane(b, y, p) == 1) as u8),
+            22 => self.lanes(out, |b, y, p| {
+                let ascending = (b < y) & (y < p);
+                let descending = (b > y) & (y > p);
+                descending as u8 + !(ascending | descending) as u8 * 2
+            }),
+            23 => self.lanes(out, |b, y, p| compare_lane(b.wrapping_add(y).wrapping_add(p), 6)),
+            24 => self.lanes(out, ascending_lane),
+            25 => self.lanes(out, |b, y, p| {
+                let ascending = ascending_lane(b, y, p);
+                let descending = (b == y.wrapping_add(1)) as u8 + (y == p.wrapping_add(1)) as u8;
+                ascending + (ascending == 0) as u8 * descending
+            }),
+            _ => panic!("there is no rule {}", rule + 1),
+        }
+    }
+
+    /// Put the category the (zero-based) rule puts every code in into `out`,
+    /// in the order the codes were added, see [`RULES`]. `out` has to hold
+    /// as many results as there are codes.
+    pub fn evaluate(&self, rule: usize, out: &mut [Option<u8>]) {
+        let mut lanes = alloc::vec![0; self.len()];
+        self.evaluate_lanes(rule, &mut lanes);
+        for (out, lane) in out.iter_mut().zip(lanes) {
+            *out = Some(lane).filter(|l| *l != NO_CATEGORY);
+        }
+    }
+}
+
+impl FromIterator<Code> for CodeColumns {
+    fn from_iter<I: IntoIterator<Item = Code>>(codes: I) -> Self {
+        let mut columns = CodeColumns::default();
+        for code in codes {
+            columns.push(code);
+        }
+        columns
+    }
+}
+
+/// How many categories each of the simple rules (1 - 25) has, in the same
+/// order as `RULES`. Their results are always below that.
+///
+/// ```
+/// use tm_solver::rules::{CATEGORY_COUNTS, RULES};
+/// use tm_solver::space::CodeSpace;
+///
+/// for (rule, count) in RULES.iter().zip(CATEGORY_COUNTS) {
+///     assert!(CodeSpace::standard().codes().all(|code| rule(&code).is_none_or(|c| c < count)));
+/// }
+/// ```
+pub const CATEGORY_COUNTS: [u8; 25] = [
+    3, 3, 3, 3, 2, 2, 2, 4, 4, 4, 3, 3, 3, 3, 3, 2, 4, 2, 3, 3, 2, 3, 3, 3, 3,
+];
+/// Short descriptions of the simple rules (1 - 25), in the same order as
+/// `RULES`.
+pub const RULE_DESCRIPTIONS: [&str; 25] = [
+    "blue compared to 1",
+    "blue compared to 3",
+    "yellow compared to 3",
+    "yellow compared to 4",
+    "blue is even or odd",
+    "yellow is even or odd",
+    "purple is even or odd",
+    "how many 1s the code contains",
+    "how many 3s the code contains",
+    "how many 4s the code contains",
+    "blue compared to yellow",
+    "blue compared to purple",
+    "yellow compared to purple",
+    "which color has the smallest number",
+    "which color has the greatest number",
+    "more even or more odd numbers",
+    "how many even numbers the code contains",
+    "the digit sum is even or odd",
+    "blue + yellow compared to 6",
+    "how often the most common number repeats",
+    "whether a number appears exactly twice",
+    "ascending, descending or no order",
+    "the digit sum compared to 6",
+    "how many numbers are in ascending order",
+    "how many numbers are in ascending or descending order",
+];
@@ -0,0 +1,106 @@
+//! Saving and restoring guided sessions.
+//!
+//! A saved session contains the puzzle, the tests known beforehand, the
+//! solution tree and all answers given so far. The tree has to be stored as
+//! well, since constructing it again might yield a different (but equally
+//! good) tree that the answers don't fit.
+//!
+//! Sessions are stored as versioned files, with the tree in its exported
+//! form, see the `format` and `export` modules of the library.
+
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::cli::{CardsArg, PuzzleArgs, Query, RuleArg};
+use tm_solver::construct_tree::{Answer, BinaryTree};
+use tm_solver::error::TmSolverError;
+use tm_solver::export::{self, Strategy};
+use tm_solver::format::{self, Versioned};
+use tm_solver::rules::Code;
+
+/// A half-finished guided session.
+pub struct SavedGame {
+    /// the verifier letter and criteria card number of each verifier.
+    pub cards: Vec<(char, u8)>,
+    /// the tests performed before the session.
+    pub known: Vec<Query>,
+    /// the full solution tree.
+    pub tree: BinaryTree<Code>,
+    /// the answers given so far, starting at the root of the tree.
+    pub answers: Vec<Answer>,
+}
+
+impl SavedGame {
+
+    /// Write the session to a file.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let stored = StoredGame::new(&self.cards, self.known.clone(), &self.tree, self.answers.clone());
+        std::fs::write(path, format::to_string_pretty(&stored))
+    }
+
+    /// Read a session from a file, written by this or any earlier version.
+    pub fn load(path: &Path) -> io::Result<SavedGame> {
+        let invalid = |e: TmSolverError| io::Error::new(io::ErrorKind::InvalidData, e.to_string());
+        let stored: StoredGame = format::from_str(&std::fs::read_to_string(path)?).map_err(invalid)?;
+        Ok(SavedGame {
+            cards: stored.strategy.cards.iter().map(|c| (c.letter, c.card as u8)).collect(),
+            known: stored.known,
+            tree: stored.strategy.to_tree().map_err(invalid)?,
+            answers: stored.answers,
+        })
+    }
+
+    /// The puzzle this session is about.
+    pub fn puzzle(&self) -> PuzzleArgs {
+        PuzzleArgs {
+            rules: self.cards.iter().map(|(l, r)| CardsArg::Card(RuleArg { letter: Some(*l), rule: *r })).collect(),
+            known: self.known.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+/// A session as stored in the file.
+#[derive(Serialize, Deserialize)]
+struct StoredGame {
+    known: Vec<Query>,
+    /// the puzzle and the full solution tree.
+    strategy: Strategy,
+    answers: Vec<Answer>,
+}
+
+impl StoredGame {
+    fn new(cards: &[(char, u8)], known: Vec<Query>, tree: &BinaryTree<Code>, answers: Vec<Answer>) -> Self {
+        let (letters, rules): (Vec<char>, Vec<usize>) = cards.iter()
+            .map(|(l, r)| (*l, (*r as usize).saturating_sub(1)))
+            .unzip();
+        StoredGame { known, strategy: export::strategy(&letters, &rules, tree), answers }
+    }
+}
+
+/// A session as stored before files had a header, with the tree as the
+/// solver represented it back then.
+#[derive(Deserialize)]
+struct UnversionedGame {
+    cards: Vec<(char, u8)>,
+    known: Vec<Query>,
+    tree: BinaryTree<Code>,
+    answers: Vec<Answer>,
+}
+
+impl Versioned for StoredGame {
+    const KIND: &'static str = "saved_game";
+
+    fn upgrade(version: u32, content: Value) -> Result<Value, TmSolverError> {
+        if version > 0 {
+            return Ok(content);
+        }
+        let old: UnversionedGame = serde_json::from_value(content)
+            .map_err(|e| TmSolverError::InvalidFile(e.to_string()))?;
+        let stored = StoredGame::new(&old.cards, old.known, &old.tree, old.answers);
+        Ok(serde_json::to_value(stored).unwrap())
+    }
+}
@@ -0,0 +1,157 @@
+//! A small HTTP server for front-ends driving the solver remotely. Only
+//! available with the `server` feature.
+//!
+//! All requests and responses are JSON:
+//!
+//! - `POST /solve` with `{"rules":[4,9,11,14]}` constructs the optimal
+//!   strategy and starts a game. The response contains the game's `id` and
+//!   its state, see the `remote` module.
+//! - `POST /answer` with `{"id":1,"passed":true}` answers the current
+//!   question and returns the new state. `{"id":1,"undo":true}` takes back
+//!   the last answer instead.
+//! - `GET /tree?id=1` returns the whole strategy, see the `export` module.
+//! - `POST /close` with `{"id":1}` forgets a game once the client is done
+//!   with it.
+//!
+//! Errors are returned as `{"error":"..."}` with a 4xx status.
+//!
+//! Several requests are handled at once, see `--workers`, so one puzzle
+//! taking long to solve doesn't hold up the other games. Bodies are limited
+//! to [`MAX_BODY`] bytes, and at most `--max-games` games are kept.
+
+use std::io::Read;
+use std::sync::{Mutex, MutexGuard};
+
+use serde::Deserialize;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::cli::ServeArgs;
+use crate::failure::{report, Failure};
+use crate::remote::{Game, Games};
+
+/// The largest request body accepted, in bytes. Every request fits easily.
+const MAX_BODY: usize = 64 * 1024;
+
+#[derive(Deserialize)]
+struct SolveRequest {
+    rules: Vec<usize>,
+}
+
+#[derive(Deserialize)]
+struct AnswerRequest {
+    id: u64,
+    /// required unless the answer is taken back.
+    passed: Option<bool>,
+    #[serde(default)]
+    undo: bool,
+}
+
+#[derive(Deserialize)]
+struct CloseRequest {
+    id: u64,
+}
+
+/// A response with a JSON body, readable by browsers on other origins.
+fn json_response(status: u16, body: String) -> Response<std::io::Cursor<Vec<u8>>> {
+    Response::from_string(body)
+        .with_status_code(status)
+        .with_header(Header::from_bytes("Content-Type", "application/json").unwrap())
+        .with_header(Header::from_bytes("Access-Control-Allow-Origin", "*").unwrap())
+        .with_header(Header::from_bytes("Access-Control-Allow-Headers", "Content-Type").unwrap())
+}
+
+fn error(status: u16, message: &str) -> (u16, String) {
+    (status, serde_json::json!({ "error": message }).to_string())
+}
+
+/// The games, even if another request panicked while using them.
+fn lock(games: &Mutex<Games>) -> MutexGuard<'_, Games> {
+    games.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Handle a single request, returning the status code and body.
+fn handle(request: &mut Request, games: &Mutex<Games>) -> (u16, String) {
+    let too_large = || error(413, &format!("The request is larger than {} bytes.", MAX_BODY));
+    if request.body_length().is_some_and(|l| l > MAX_BODY) {
+        return too_large();
+    }
+    let mut body = String::new();
+    if request.as_reader().take(MAX_BODY as u64 + 1).read_to_string(&mut body).is_err() {
+        return error(400, "Could not read the request.");
+    }
+    if body.len() > MAX_BODY {
+        return too_large();
+    }
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    let unknown_game = || error(404, "There is no game with this id.");
+    match (request.method(), path) {
+        // preflight requests of browsers.
+        (Method::Options, _) => (204, String::new()),
+        (Method::Post, "/solve") => {
+            let Ok(SolveRequest { rules }) = serde_json::from_str(&body) else {
+                return error(400, "Expected the criteria card numbers, e. g. {\"rules\":[4,9,11,14]}.");
+            };
+            // solving takes a while, so the other games stay available.
+            match Game::new(&rules) {
+                Ok(game) => (200, serde_json::to_string(&lock(games).start(game)).unwrap()),
+                Err(e) => error(422, &e.to_string()),
+            }
+        },
+        (Method::Post, "/answer") => {
+            let expected = || error(400, "Expected a game id and an answer, e. g. {\"id\":1,\"passed\":true}.");
+            let Ok(answer) = serde_json::from_str::<AnswerRequest>(&body) else {
+                return expected();
+            };
+            let state = match (answer.undo, answer.passed) {
+                (true, _) => lock(games).undo(answer.id),
+                (false, Some(passed)) => lock(games).answer(answer.id, passed.into()),
+                (false, None) => return expected(),
+            };
+            match state {
+                Some(state) => (200, serde_json::to_string(&state).unwrap()),
+                None => unknown_game(),
+            }
+        },
+        (Method::Get, "/tree") => {
+            let id = query.split('&').find_map(|p| p.strip_prefix("id=")).and_then(|id| id.parse().ok());
+            match id.and_then(|id| lock(games).tree(id)) {
+                Some(tree) => (200, tree),
+                None => unknown_game(),
+            }
+        },
+        (Method::Post, "/close") => {
+            let Ok(CloseRequest { id }) = serde_json::from_str(&body) else {
+                return error(400, "Expected a game id, e. g. {\"id\":1}.");
+            };
+            match lock(games).close(id) {
+                true => (200, String::from("true")),
+                false => unknown_game(),
+            }
+        },
+        _ => error(404, "Unknown endpoint: use POST /solve, POST /answer, GET /tree or POST /close."),
+    }
+}
+
+/// Serve requests with a few workers until the process is stopped.
+pub fn run(args: ServeArgs) {
+    let address = format!("{}:{}", args.host, args.port);
+    let server = match Server::http(&address) {
+        Ok(s) => s,
+        Err(e) => {
+            return report(Failure::Internal, &format!("Could not start the server: {}", e));
+        },
+    };
+    println!("Listening on http://{}", address);
+    let games = Mutex::new(Games::limited(args.max_games as usize));
+    std::thread::scope(|scope| {
+        for _ in 0..args.workers {
+            scope.spawn(|| {
+                while let Ok(mut request) = server.recv() {
+                    let (status, body) = handle(&mut request, &games);
+                    let _ = request.respond(json_response(status, body));
+                }
+            });
+        }
+    });
+}
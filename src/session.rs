@@ -0,0 +1,182 @@
+//! Following a solution tree while playing: [`GameSession`] keeps track of
+//! the current node, the questions answered so far and the codes that are
+//! still possible, so every interface asks the same questions in the same
+//! order.
+//!
+//! ```
+//! use tm_solver::construct_tree::Answer;
+//! use tm_solver::solver::Solver;
+//!
+//! let strategy = Solver::builder().rules(&[4, 9, 11, 14]).solve()?;
+//! let mut session = strategy.session();
+//! while let Some(question) = session.next_question() {
+//!     // test question.code on verifier question.test.0 with the real game.
+//!     session.answer(Answer::Pass);
+//! }
+//! assert!(session.solution().is_some());
+//! # Ok::<(), tm_solver::error::TmSolverError>(())
+//! ```
+
+use alloc::vec::Vec;
+
+use crate::construct_tree::{Answer, BinaryTree, Feasible, Test};
+use crate::rules::Code;
+
+/// A question answered during a session: the branch it was asked at, the
+/// test and the answer.
+pub type Answered<'a> = (&'a BinaryTree<Code>, Test, Answer);
+
+/// A test to perform with the physical game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Question {
+    /// the code to test.
+    pub code: Code,
+    /// the (zero-based) verifier to test it on and the category the tree
+    /// checks for.
+    pub test: Test,
+    /// whether this test starts a new round, i. e. a new code.
+    pub new_round: bool,
+}
+
+/// A game in progress, following a solution tree with the answers given so
+/// far.
+#[derive(Debug, Clone)]
+pub struct GameSession<'a> {
+    tree: &'a BinaryTree<Code>,
+    candidates: &'a [Feasible<Code>],
+    history: Vec<Answered<'a>>,
+    node: &'a BinaryTree<Code>,
+}
+
+impl<'a> GameSession<'a> {
+
+    /// Start following a tree. `candidates` are the codes it was constructed
+    /// for, e. g. the unique solutions of a puzzle.
+    pub fn new(tree: &'a BinaryTree<Code>, candidates: &'a [Feasible<Code>]) -> Self {
+        GameSession { tree, candidates, history: Vec::new(), node: tree }
+    }
+
+    /// The next test to perform, or None once the code is known.
+    ///
+    /// ```
+    /// use tm_solver::solver::Solver;
+    ///
+    /// let strategy = Solver::builder().rules(&[4, 9, 11, 14]).solve()?;
+    /// let question = strategy.session().next_question().unwrap();
+    /// assert!(question.new_round);
+    /// # Ok::<(), tm_solver::error::TmSolverError>(())
+    /// ```
+    pub fn next_question(&self) -> Option<Question> {
+        let BinaryTree::Branch(b) = self.node else {
+            return None;
+        };
+        Some(Question { code: b.code.or(self.round_code())?, test: b.test, new_round: b.code.is_some() })
+    }
+
+    /// Answer the current question with the verifier's result. Returns
+    /// false, without doing anything, once the code is known.
+    ///
+    /// ```
+    /// use tm_solver::construct_tree::Answer;
+    /// use tm_solver::solver::Solver;
+    ///
+    /// let strategy = Solver::builder().rules(&[4, 9, 11, 14]).solve()?;
+    /// let mut session = strategy.session();
+    /// assert!(session.answer(Answer::Fail));
+    /// assert!(session.solution().is_some());
+    /// assert!(!session.answer(Answer::Pass));
+    /// assert_eq!(session.history().len(), 1);
+    /// # Ok::<(), tm_solver::error::TmSolverError>(())
+    /// ```
+    pub fn answer(&mut self, answer: Answer) -> bool {
+        let BinaryTree::Branch(b) = self.node else {
+            return false;
+        };
+        self.history.push((self.node, b.test, answer));
+        self.node = b.child(answer);
+        true
+    }
+
+    /// Take back the last answer. Returns false if there was none.
+    ///
+    /// ```
+    /// use tm_solver::construct_tree::Answer;
+    /// use tm_solver::solver::Solver;
+    ///
+    /// let strategy = Solver::builder().rules(&[4, 9, 11, 14]).solve()?;
+    /// let mut session = strategy.session();
+    /// let first = session.next_question();
+    /// session.answer(Answer::Pass);
+    /// assert!(session.undo());
+    /// assert_eq!(session.next_question(), first);
+    /// assert!(!session.undo());
+    /// # Ok::<(), tm_solver::error::TmSolverError>(())
+    /// ```
+    pub fn undo(&mut self) -> bool {
+        match self.history.pop() {
+            Some((previous, _, _)) => {
+                self.node = previous;
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// The candidates consistent with all answers so far. Empty if the
+    /// answers contradict each other, e. g. because one was mis-read.
+    ///
+    /// ```
+    /// use tm_solver::construct_tree::Answer;
+    /// use tm_solver::solver::Solver;
+    ///
+    /// let strategy = Solver::builder().rules(&[3, 9, 12, 17, 20]).solve()?;
+    /// let mut session = strategy.session();
+    /// assert_eq!(session.remaining().len(), strategy.unique_solutions.len());
+    /// session.answer(Answer::Pass);
+    /// assert_eq!(session.remaining().len(), session.node().leaf_count());
+    /// # Ok::<(), tm_solver::error::TmSolverError>(())
+    /// ```
+    pub fn remaining(&self) -> Vec<&'a Feasible<Code>> {
+        self.candidates.iter()
+            .filter(|(results, _)| self.history.iter().all(|(_, (i, v), answer)| (results[*i] == *v) == answer.passed()))
+            .collect()
+    }
+
+    /// The code, once the answers identify it.
+    pub fn solution(&self) -> Option<&'a Code> {
+        match self.node {
+            BinaryTree::Leaf(c) => Some(c),
+            BinaryTree::Branch(_) => None,
+        }
+    }
+
+    /// The node reached with the answers so far.
+    pub fn node(&self) -> &'a BinaryTree<Code> {
+        self.node
+    }
+
+    /// The id of the current node, see the [`traverse`](crate::traverse)
+    /// module.
+    pub fn node_id(&self) -> usize {
+        // the history always follows the tree.
+        self.tree.node_id(&self.answers()).unwrap()
+    }
+
+    /// Every question answered so far, in order.
+    pub fn history(&self) -> &[Answered<'a>] {
+        &self.history
+    }
+
+    /// The answers given so far, in order.
+    pub fn answers(&self) -> Vec<Answer> {
+        self.history.iter().map(|(_, _, a)| *a).collect()
+    }
+
+    /// The code of the current round, if one has started.
+    pub fn round_code(&self) -> Option<Code> {
+        self.history.iter().rev().find_map(|(node, _, _)| match node {
+            BinaryTree::Branch(b) => b.code,
+            BinaryTree::Leaf(_) => None,
+        })
+    }
+}
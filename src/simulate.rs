@@ -0,0 +1,65 @@
+//! Playing through a solution tree for a known code, e. g. to check a
+//! strategy or to gather statistics about it.
+//!
+//! ```
+//! use tm_solver::simulate::{self, simulate};
+//! use tm_solver::solver::Solver;
+//!
+//! let strategy = Solver::builder().rules(&[3, 9, 12, 17, 20]).solve()?;
+//! for solution in &strategy.unique_solutions {
+//!     let path = simulate(&strategy.tree, solution).unwrap();
+//!     assert_eq!(path[0].round, 1);
+//!     assert!(simulate::rounds(&path) <= path.len());
+//!     assert!(path.len() <= strategy.tree.max_depth() as usize);
+//! }
+//!
+//! // the results of one solution don't lead to another.
+//! let [(results, _), (_, other), ..] = &strategy.unique_solutions[..] else { unreachable!() };
+//! assert_eq!(simulate(&strategy.tree, &(*results, *other)), None);
+//! # Ok::<(), tm_solver::error::TmSolverError>(())
+//! ```
+
+use alloc::vec::Vec;
+
+use crate::construct_tree::{Answer, BinaryTree, Feasible, Test};
+use crate::rules::Code;
+
+/// A single test performed while following a solution tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Step {
+    /// the round the test belongs to, starting at 1. Trees without codes
+    /// don't have rounds, so it's 0 then.
+    pub round: usize,
+    /// the code to test with, i. e. the code of the round.
+    pub code: Option<Code>,
+    pub test: Test,
+    pub answer: Answer,
+}
+
+/// All tests performed to find a code, in order.
+pub type Path = Vec<Step>;
+
+/// Follow a solution tree as if `code` was the solution, i. e. answer every
+/// question the way the verifiers would for its test results. Returns None
+/// if the tree doesn't lead to the code this way, e. g. because it isn't one
+/// of the tree's solutions.
+pub fn simulate(tree: &BinaryTree<Code>, (results, code): &Feasible<Code>) -> Option<Path> {
+    let mut node = tree;
+    let mut round = (0, None);
+    let mut steps = Vec::new();
+    while let BinaryTree::Branch(b) = node {
+        if let Some(c) = b.code {
+            round = (round.0 + 1, Some(c));
+        }
+        let (i, value) = b.test;
+        let answer = Answer::from(*results.get(i)? == value);
+        steps.push(Step { round: round.0, code: round.1, test: b.test, answer });
+        node = b.child(answer);
+    }
+    matches!(node, BinaryTree::Leaf(leaf) if leaf == code).then_some(steps)
+}
+
+/// How many rounds a path takes.
+pub fn rounds(path: &[Step]) -> usize {
+    path.last().map_or(0, |s| s.round)
+}
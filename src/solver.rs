@@ -0,0 +1,521 @@
+//! A high-level interface to the whole solving pipeline.
+//!
+//! ```
+//! use tm_solver::solver::Solver;
+//!
+//! let strategy = Solver::builder()
+//!     .rules(&[4, 9, 11, 14])
+//!     .tests_per_round(3)
+//!     .solve()?;
+//! assert_eq!(strategy.tree.leaf_count(), strategy.unique_solutions.len());
+//! # Ok::<(), tm_solver::error::TmSolverError>(())
+//! ```
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+
+use tracing::{debug, debug_span, trace};
+
+use crate::collections::{HashMap, HashSet};
+use crate::construct_tree::{self, Answer, BinaryTree, Feasible, Improved, Objective, Results, TreeSettings};
+use crate::error::TmSolverError;
+use crate::history::TestHistory;
+use crate::progress::ProgressSink;
+use crate::session::GameSession;
+use crate::matrix::RuleMatrix;
+use crate::rules::{Code, RULES};
+use crate::space::CodeSpace;
+#[cfg(feature = "std")]
+use crate::find_super_unique_parallel;
+use crate::{categorize_codes, find_super_unique, find_unique, RuleSet};
+
+/// Entry point for solving puzzles, see [`Solver::builder`].
+pub struct Solver;
+
+impl Solver {
+
+    /// Start configuring a puzzle to solve.
+    pub fn builder() -> SolverBuilder {
+        SolverBuilder {
+            cards: Vec::new(),
+            space: CodeSpace::standard(),
+            known: Vec::new(),
+            tests_per_round: 3,
+            max_questions: None,
+            lookahead: false,
+            objective: Objective::WorstCase,
+            progress: None,
+            threads: None,
+            history: None,
+            max_memory: None,
+            deepening: false,
+        }
+    }
+}
+
+/// The configuration of a puzzle and of how to solve it.
+#[derive(Clone)]
+pub struct SolverBuilder {
+    cards: Vec<usize>,
+    space: CodeSpace,
+    known: Vec<(Code, usize, Answer)>,
+    tests_per_round: u8,
+    max_questions: Option<u8>,
+    lookahead: bool,
+    objective: Objective,
+    progress: Option<Arc<dyn ProgressSink>>,
+    threads: Option<usize>,
+    history: Option<TestHistory>,
+    max_memory: Option<usize>,
+    deepening: bool,
+}
+
+impl fmt::Debug for SolverBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SolverBuilder")
+            .field("cards", &self.cards)
+            .field("space", &self.space)
+            .field("known", &self.known)
+            .field("tests_per_round", &self.tests_per_round)
+            .field("max_questions", &self.max_questions)
+            .field("lookahead", &self.lookahead)
+            .field("objective", &self.objective)
+            .field("progress", &self.progress.is_some())
+            .field("threads", &self.threads)
+            .field("history", &self.history.as_ref().map(TestHistory::len))
+            .field("max_memory", &self.max_memory)
+            .field("deepening", &self.deepening)
+            .finish()
+    }
+}
+
+impl SolverBuilder {
+
+    /// The numbers of the puzzle's criteria cards, e. g. `&[4, 9, 11, 14]`.
+    pub fn rules(mut self, cards: &[usize]) -> Self {
+        self.cards = cards.to_vec();
+        self
+    }
+
+    /// The codes the solution is drawn from. Defaults to the codes of the
+    /// game, see [`CodeSpace::standard`].
+    pub fn space(mut self, space: CodeSpace) -> Self {
+        self.space = space;
+        self
+    }
+
+    /// A test that was already performed: the code, the (zero-based) index of
+    /// the verifier and its answer. Can be repeated.
+    pub fn known(mut self, code: Code, verifier: usize, answer: Answer) -> Self {
+        self.known.push((code, verifier, answer));
+        self
+    }
+
+    /// How many tests can be performed with the same code. Defaults to 3.
+    /// A round needs at least one test, so constructing a tree fails with
+    /// [`TmSolverError::EmptyRound`] otherwise.
+    ///
+    /// ```
+    /// use tm_solver::error::TmSolverError;
+    /// use tm_solver::solver::Solver;
+    ///
+    /// let builder = Solver::builder().rules(&[4, 9, 11, 14]).tests_per_round(0);
+    /// assert!(matches!(builder.solve(), Err(TmSolverError::EmptyRound)));
+    /// ```
+    pub fn tests_per_round(mut self, tests: u8) -> Self {
+        self.tests_per_round = tests;
+        self
+    }
+
+    /// How many tests can be performed with the same code, see
+    /// [`SolverBuilder::tests_per_round`].
+    pub fn round_size(&self) -> u8 {
+        self.tests_per_round
+    }
+
+    /// What the strategy minimizes first, see [`SolverBuilder::objective`].
+    pub fn optimizes(&self) -> Objective {
+        self.objective
+    }
+
+    /// Reject strategies that might need more tests than this.
+    pub fn max_questions(mut self, questions: u8) -> Self {
+        self.max_questions = Some(questions);
+        self
+    }
+
+    /// Rank candidate tests by looking two tests ahead.
+    pub fn lookahead(mut self, lookahead: bool) -> Self {
+        self.lookahead = lookahead;
+        self
+    }
+
+    /// What the strategy minimizes first. Defaults to the worst case.
+    pub fn objective(mut self, objective: Objective) -> Self {
+        self.objective = objective;
+        self
+    }
+
+    /// Report the progress of every phase to the given sink.
+    pub fn progress(mut self, sink: Arc<dyn ProgressSink>) -> Self {
+        self.progress = Some(sink);
+        self
+    }
+
+    /// How many threads search for the optimal tree, each trying some of the
+    /// tests at the top of the tree, and look for the codes that don't
+    /// require all verifiers, each leaving out some of them. Defaults to the
+    /// number of cores. Without the `std` feature, everything runs on the
+    /// calling thread.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Try the tests that led to optimal trees of the same or similar puzzles
+    /// first, see [`history`](crate::history). This only changes how fast
+    /// the optimal tree is found, not how good it is.
+    pub fn history(mut self, history: TestHistory) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    /// Roughly how many bytes the search may use, see
+    /// [`TreeSettings::max_memory`]. Unlimited by default. Beyond the cap,
+    /// the strategy found may not be optimal.
+    pub fn max_memory(mut self, bytes: usize) -> Self {
+        self.max_memory = Some(bytes);
+        self
+    }
+
+    /// Look for trees of ever larger depth, see
+    /// [`construct_tree::optimal_tree_deepening`].
+    pub fn deepening(mut self, deepening: bool) -> Self {
+        self.deepening = deepening;
+        self
+    }
+
+    /// Find the optimal tree, by iterative deepening if requested.
+    fn optimal_tree(&self, entries: &[Feasible<Code>], settings: TreeSettings<Code>) -> Option<BinaryTree<Code>> {
+        match self.deepening {
+            true => construct_tree::deepen(entries.len(), settings, |s| self.search_once(entries, s)),
+            false => self.search_once(entries, settings),
+        }
+    }
+
+    /// How many threads to run on.
+    #[cfg(feature = "std")]
+    fn thread_count(&self) -> usize {
+        self.threads.unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()))
+    }
+
+    /// Find the codes that don't require all verifiers, on as many threads
+    /// as requested.
+    fn super_unique(&self, codes: &HashSet<Code>, matrix: &RuleMatrix) -> HashMap<Code, usize> {
+        #[cfg(feature = "std")]
+        {
+            let threads = self.thread_count();
+            if threads > 1 {
+                return find_super_unique_parallel(codes, matrix, threads);
+            }
+        }
+        find_super_unique(codes, matrix)
+    }
+
+    /// Find the optimal tree, on as many threads as requested.
+    fn search_once(&self, entries: &[Feasible<Code>], settings: TreeSettings<Code>) -> Option<BinaryTree<Code>> {
+        #[cfg(feature = "std")]
+        {
+            let threads = self.thread_count();
+            if threads > 1 {
+                return construct_tree::optimal_tree_parallel(entries, settings, threads);
+            }
+        }
+        construct_tree::optimal_tree(entries, settings)
+    }
+
+    /// Run a phase of the pipeline, reporting its start and end. Each phase
+    /// also gets a span, so subscribers can time it.
+    fn phase<T>(&self, name: &str, task: impl FnOnce() -> T) -> T {
+        let _span = debug_span!("phase", name).entered();
+        if let Some(p) = &self.progress {
+            p.start(name);
+        }
+        let res = task();
+        if let Some(p) = &self.progress {
+            p.finish();
+        }
+        res
+    }
+
+    /// Find the codes that could be the solution, given the known tests.
+    /// This is the first part of [`SolverBuilder::solve`].
+    pub fn candidates(&self) -> Result<Candidates, TmSolverError> {
+        validate(&self.cards)?;
+        let rules: Vec<usize> = self.cards.iter().map(|c| c - 1).collect();
+        if let Some((_, i, _)) = self.known.iter().find(|(_, i, _)| *i >= rules.len()) {
+            return Err(TmSolverError::UnknownVerifier((b'A' + *i as u8) as char));
+        }
+        let (mut codes, matrix) = self.phase("Generating codes", || (self.space.all(), RuleMatrix::new(&self.space, &rules)));
+        let removed = self.phase("Removing solutions that don't require all tests", || {
+            let removed = self.super_unique(&codes, &matrix);
+            for (code, verifier) in &removed {
+                trace!(%code, verifier, "removed code that doesn't require all verifiers");
+                codes.remove(code);
+            }
+            debug!(removed = removed.len(), remaining = codes.len(), "removed codes that don't require all verifiers");
+            removed
+        });
+        let (solutions, mut unique_solutions) = self.phase("Looking for unique solutions", || {
+            let solutions = categorize_codes(&codes, &matrix);
+            let unique_solutions = find_unique(&solutions);
+            debug!(groups = solutions.len(), unique = unique_solutions.len(), "grouped codes by their results");
+            (solutions, unique_solutions)
+        });
+        if unique_solutions.is_empty() {
+            return Err(TmSolverError::Unsolvable);
+        }
+        unique_solutions.retain(|(results, _)| self.known.iter()
+            .all(|(code, i, answer)| (matrix.category(*i, code) == Some(results[*i])) == answer.passed()));
+        if unique_solutions.is_empty() {
+            return Err(TmSolverError::InconsistentKnown);
+        }
+        debug!(known = self.known.len(), candidates = unique_solutions.len(), "applied the known tests");
+        Ok(Candidates { rules, matrix, removed, solutions, unique_solutions })
+    }
+
+    /// Construct the optimal solution tree for some candidates. This is the
+    /// second part of [`SolverBuilder::solve`].
+    pub fn construct(&self, candidates: &Candidates) -> Result<BinaryTree<Code>, TmSolverError> {
+        self.search(&candidates.rules, &candidates.solutions, &candidates.unique_solutions, None)
+    }
+
+    /// Like [`SolverBuilder::construct`], but only for some of the unique
+    /// solutions of a puzzle with the given rules and codes grouped by their
+    /// results, e. g. the ones still possible after a few answers.
+    ///
+    /// ```
+    /// use tm_solver::solver::Solver;
+    ///
+    /// let builder = Solver::builder().rules(&[3, 9, 12, 17, 20]);
+    /// let candidates = builder.candidates()?;
+    /// let some = &candidates.unique_solutions[..4];
+    /// let tree = builder.construct_among(&candidates.rules, &candidates.solutions, some)?;
+    /// assert_eq!(tree.leaf_count(), 4);
+    /// # Ok::<(), tm_solver::error::TmSolverError>(())
+    /// ```
+    pub fn construct_among(&self, rules: &[usize], solutions: &HashMap<Results, Vec<Code>>,
+        entries: &[Feasible<Code>]) -> Result<BinaryTree<Code>, TmSolverError> {
+        self.search(rules, solutions, entries, None)
+    }
+
+    /// Like [`SolverBuilder::construct`], but calls `improved` with every
+    /// tree that beats the best one found so far. The last of them is the
+    /// optimal tree, which is only known once the search is done.
+    pub fn construct_improving(&self, candidates: &Candidates, improved: &(dyn Fn(&BinaryTree<Code>) + Sync))
+        -> Result<BinaryTree<Code>, TmSolverError> {
+        self.search(&candidates.rules, &candidates.solutions, &candidates.unique_solutions, Some(improved))
+    }
+
+    fn search(&self, rules: &[usize], solutions: &HashMap<Results, Vec<Code>>, entries: &[Feasible<Code>],
+        improved: Option<Improved<Code>>) -> Result<BinaryTree<Code>, TmSolverError> {
+        if self.tests_per_round == 0 {
+            return Err(TmSolverError::EmptyRound);
+        }
+        let order = self.history.as_ref().map(|h| h.order(rules));
+        let settings = TreeSettings {
+            solution_map: Some(solutions),
+            tests_per_round: self.tests_per_round,
+            max_questions: self.max_questions,
+            lookahead: self.lookahead,
+            objective: self.objective,
+            progress: self.progress.as_deref(),
+            improved,
+            order: order.as_ref(),
+            max_memory: self.max_memory,
+        };
+        let before = construct_tree::search_stats();
+        let tree = self.phase("Construct optimal tree", || self.optimal_tree(entries, settings));
+        let after = construct_tree::search_stats();
+        debug!(
+            calls = after.calls - before.calls,
+            tests = after.tests - before.tests,
+            pruned = after.pruned - before.pruned,
+            rejected = after.rejected - before.rejected,
+            memory = after.memory - before.memory,
+            found = tree.is_some(),
+            "searched for the optimal tree",
+        );
+        if let Some(tree) = &tree {
+            debug!(leaves = tree.leaf_count(), depth = tree.max_depth(), "constructed the optimal tree");
+        }
+        tree.ok_or(TmSolverError::NoTree)
+    }
+
+    /// Run the whole pipeline.
+    pub fn solve(&self) -> Result<Strategy, TmSolverError> {
+        let candidates = self.candidates()?;
+        let tree = self.construct(&candidates)?;
+        Ok(Strategy::new(candidates, tree))
+    }
+
+    /// Run the whole pipeline in the background, yielding every strategy
+    /// that beats the best one found so far as soon as it is found. The
+    /// last strategy is the optimal one, see [`Improvement::optimal`].
+    ///
+    /// A front-end can show the first strategies right away while the search
+    /// goes on to prove that the last one is optimal:
+    ///
+    /// ```
+    /// use tm_solver::solver::Solver;
+    ///
+    /// let mut best = None;
+    /// for improvement in Solver::builder().rules(&[3, 9, 12, 17, 20]).stream() {
+    ///     let improvement = improvement?;
+    ///     if let Some(previous) = &best {
+    ///         assert!(improvement.strategy.tree.max_depth() <= *previous);
+    ///     }
+    ///     best = Some(improvement.strategy.tree.max_depth());
+    ///     if improvement.optimal {
+    ///         break;
+    ///     }
+    /// }
+    /// assert!(best.is_some());
+    /// # Ok::<(), tm_solver::error::TmSolverError>(())
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn stream(&self) -> Improvements {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let builder = self.clone();
+        // the receiver may be gone already, but there's no way to stop the
+        // search, so just keep going.
+        std::thread::spawn(move || builder.improve(|found| drop(sender.send(found))));
+        Improvements { receiver }
+    }
+
+    /// Run the whole pipeline, passing every improvement to `report`. The
+    /// last report is the optimal strategy or an error.
+    #[cfg(feature = "std")]
+    pub(crate) fn improve(&self, report: impl Fn(Result<Improvement, TmSolverError>) + Sync) {
+        let candidates = match self.candidates() {
+            Ok(c) => c,
+            Err(e) => return report(Err(e)),
+        };
+        let improved = |tree: &BinaryTree<Code>| {
+            let strategy = Strategy::new(candidates.clone(), tree.clone());
+            report(Ok(Improvement { strategy, optimal: false }));
+        };
+        let found = self.construct_improving(&candidates, &improved)
+            .map(|tree| Improvement { strategy: Strategy::new(candidates.clone(), tree), optimal: true });
+        report(found);
+    }
+}
+
+/// A strategy found while searching for the optimal one, see
+/// [`SolverBuilder::stream`].
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct Improvement {
+    pub strategy: Strategy,
+    /// whether the search is done, so no better strategy exists.
+    pub optimal: bool,
+}
+
+/// The strategies found by a search running in the background, each better
+/// than the previous one, see [`SolverBuilder::stream`]. Ends after the
+/// optimal strategy, or after an error.
+#[cfg(feature = "std")]
+pub struct Improvements {
+    receiver: std::sync::mpsc::Receiver<Result<Improvement, TmSolverError>>,
+}
+
+#[cfg(feature = "std")]
+impl Iterator for Improvements {
+    type Item = Result<Improvement, TmSolverError>;
+
+    /// Wait for the next strategy.
+    fn next(&mut self) -> Option<Self::Item> {
+        self.receiver.recv().ok()
+    }
+}
+
+/// Check that a puzzle has a valid number of distinct, existing criteria
+/// cards.
+pub fn validate(cards: &[usize]) -> Result<(), TmSolverError> {
+    if !(4..=6).contains(&cards.len()) {
+        return Err(TmSolverError::RuleCount(cards.len()));
+    }
+    for (i, card) in cards.iter().enumerate() {
+        if *card == 0 || *card > RULES.len() {
+            return Err(TmSolverError::UnknownRule(*card));
+        }
+        if cards[..i].contains(card) {
+            return Err(TmSolverError::DuplicateRule(*card));
+        }
+    }
+    Ok(())
+}
+
+/// The codes that could be the solution of a puzzle.
+#[derive(Debug, Clone)]
+pub struct Candidates {
+    /// the (zero-based) rules used by the puzzle.
+    pub rules: Vec<usize>,
+    /// the results of the rules for every code, to check tests against.
+    pub matrix: RuleMatrix,
+    /// the codes that were removed because they don't require all tests,
+    /// along with a test they don't require.
+    pub removed: HashMap<Code, usize>,
+    /// all remaining codes, grouped by their test results.
+    pub solutions: HashMap<Results, Vec<Code>>,
+    /// the codes that can be uniquely identified by their test results and
+    /// are consistent with the known tests.
+    pub unique_solutions: Vec<Feasible<Code>>,
+}
+
+/// A solved puzzle: its possible solutions and the optimal strategy to tell
+/// them apart.
+#[derive(Debug, Clone)]
+pub struct Strategy {
+    /// the (zero-based) rules used by the puzzle.
+    pub rules: Vec<usize>,
+    /// the codes that were removed because they don't require all tests,
+    /// along with a test they don't require.
+    pub removed: HashMap<Code, usize>,
+    /// all remaining codes, grouped by their test results.
+    pub solutions: HashMap<Results, Vec<Code>>,
+    /// the codes that can be uniquely identified by their test results.
+    pub unique_solutions: Vec<Feasible<Code>>,
+    /// the optimal solution tree.
+    pub tree: BinaryTree<Code>,
+}
+
+impl Strategy {
+
+    fn new(candidates: Candidates, tree: BinaryTree<Code>) -> Strategy {
+        let Candidates { rules, removed, solutions, unique_solutions, .. } = candidates;
+        Strategy { rules, removed, solutions, unique_solutions, tree }
+    }
+
+    /// Start playing with the strategy.
+    pub fn session(&self) -> GameSession<'_> {
+        GameSession::new(&self.tree, &self.unique_solutions)
+    }
+
+    /// Follow the solution tree with the answers given so far. Returns the
+    /// node reached along with the code of its round, or None if there are
+    /// more answers than questions.
+    pub fn follow(&self, answers: &[Answer]) -> Option<(&BinaryTree<Code>, Option<Code>)> {
+        let mut node = &self.tree;
+        let mut round_code = None;
+        for answer in answers {
+            let BinaryTree::Branch(b) = node else {
+                return None;
+            };
+            round_code = b.code.or(round_code);
+            node = b.child(*answer);
+        }
+        Some((node, round_code))
+    }
+}
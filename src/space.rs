@@ -0,0 +1,156 @@
+//! The codes a puzzle's solution is drawn from.
+//!
+//! The game uses three digits from 1 to 5, i. e. 125 codes, which is
+//! [`CodeSpace::standard`]. Other spaces can allow more digits per color, or
+//! leave out some codes, e. g. for variants or for puzzles where parts of the
+//! code are already known:
+//!
+//! ```
+//! use tm_solver::rules::Code;
+//! use tm_solver::space::CodeSpace;
+//!
+//! let standard = CodeSpace::standard();
+//! assert_eq!(standard.len(), 125);
+//!
+//! let expanded = CodeSpace::with_digits(1..=6);
+//! assert_eq!(expanded.len(), 216);
+//! assert!(expanded.contains(&Code { blue: 6, yellow: 1, purple: 1 }));
+//!
+//! let ascending = standard.constrain(|c| c.blue < c.yellow && c.yellow < c.purple);
+//! assert_eq!(ascending.len(), 10);
+//! assert!(ascending.parse("321").is_err());
+//! ```
+
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+
+use crate::collections::HashSet;
+use crate::error::TmSolverError;
+use crate::rules::Code;
+
+/// A set of codes: the digits each color can take, optionally narrowed down
+/// by filters.
+#[derive(Debug, Clone)]
+pub struct CodeSpace {
+    /// the digits of blue, yellow and purple, in that order.
+    digits: [RangeInclusive<u8>; 3],
+    /// only codes passing all of these are part of the space.
+    filters: Vec<fn(&Code) -> bool>,
+}
+
+impl Default for CodeSpace {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+impl CodeSpace {
+
+    /// The codes of the game: three digits from 1 to 5.
+    pub fn standard() -> Self {
+        Self::with_digits(1..=5)
+    }
+
+    /// All codes with the given digits for every color. Codes are written
+    /// with one decimal digit per color, so anything above 9 is left out.
+    pub fn with_digits(digits: RangeInclusive<u8>) -> Self {
+        let digits = clamp(digits);
+        CodeSpace { digits: [digits.clone(), digits.clone(), digits], filters: Vec::new() }
+    }
+
+    /// The same space with other digits for a single color: 0 for blue, 1
+    /// for yellow and 2 for purple.
+    pub fn color_digits(mut self, color: usize, digits: RangeInclusive<u8>) -> Self {
+        self.digits[color] = clamp(digits);
+        self
+    }
+
+    /// The same space, but only with the codes that also pass `filter`.
+    pub fn constrain(mut self, filter: fn(&Code) -> bool) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    /// The digits a color can take: 0 for blue, 1 for yellow and 2 for
+    /// purple. Filters might rule out some of them.
+    pub fn digits(&self, color: usize) -> RangeInclusive<u8> {
+        self.digits[color].clone()
+    }
+
+    /// Whether the code is part of the space.
+    pub fn contains(&self, code: &Code) -> bool {
+        [code.blue, code.yellow, code.purple].iter().zip(&self.digits).all(|(d, r)| r.contains(d))
+            && self.filters.iter().all(|f| f(code))
+    }
+
+    /// Iterate over the codes of the space, ordered by blue, then yellow,
+    /// then purple.
+    pub fn codes(&self) -> impl Iterator<Item = Code> + '_ {
+        let [blue, yellow, purple] = self.digits.clone();
+        blue.flat_map(move |b| {
+            let purple = purple.clone();
+            yellow.clone().flat_map(move |y| purple.clone().map(move |p| Code { blue: b, yellow: y, purple: p }))
+        }).filter(|c| self.filters.iter().all(|f| f(c)))
+    }
+
+    /// All codes of the space, as expected by the rest of the pipeline.
+    pub fn all(&self) -> HashSet<Code> {
+        self.codes().collect()
+    }
+
+    /// The number of codes in the space.
+    pub fn len(&self) -> usize {
+        self.codes().count()
+    }
+
+    /// Whether the space has no codes at all, e. g. because its filters rule
+    /// out every code.
+    pub fn is_empty(&self) -> bool {
+        self.codes().next().is_none()
+    }
+
+    /// Parse a code of this space, given either as three digits, e. g. 415,
+    /// or as one digit per color, e. g. "b4 y1 p3". The colors can be given
+    /// in any order and in upper or lower case.
+    pub fn parse(&self, input: &str) -> Result<Code, TmSolverError> {
+        let error = || TmSolverError::InvalidCode(input.to_string());
+        let digit = |c: char| c.to_digit(10).map(|d| d as u8);
+        let trimmed = input.trim();
+        let code = if trimmed.len() == 3 && trimmed.chars().all(|c| c.is_ascii_digit()) {
+            let digits: Vec<u8> = trimmed.chars().filter_map(digit).collect();
+            Code { blue: digits[0], yellow: digits[1], purple: digits[2] }
+        } else {
+            let mut digits = [None; 3];
+            for part in trimmed.split(|c: char| c.is_whitespace() || c == ',').filter(|p| !p.is_empty()) {
+                let mut chars = part.chars();
+                let (Some(color), Some(value), None) = (chars.next(), chars.next(), chars.next()) else {
+                    return Err(error());
+                };
+                let slot = match color.to_ascii_lowercase() {
+                    'b' => &mut digits[0],
+                    'y' => &mut digits[1],
+                    'p' => &mut digits[2],
+                    _ => return Err(error()),
+                };
+                if slot.is_some() {
+                    return Err(error());
+                }
+                *slot = Some(digit(value).ok_or_else(error)?);
+            }
+            match digits {
+                [Some(blue), Some(yellow), Some(purple)] => Code { blue, yellow, purple },
+                _ => return Err(error()),
+            }
+        };
+        match self.contains(&code) {
+            true => Ok(code),
+            false => Err(error()),
+        }
+    }
+}
+
+/// Leave out digits that can't be written as a single decimal digit.
+fn clamp(digits: RangeInclusive<u8>) -> RangeInclusive<u8> {
+    *digits.start()..=(*digits.end()).min(9)
+}
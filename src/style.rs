@@ -0,0 +1,264 @@
+//! Styling of the solver's terminal output.
+//!
+//! Colors are used by default, but can be turned off globally, which happens
+//! with the --no-color flag, if the NO_COLOR environment variable is set or
+//! if stdout isn't a terminal. Which colors are used depends on the selected
+//! theme.
+//!
+//! Codes can also be spelled out, which works better with screen readers.
+//!
+//! Spinners are only animated if stdout is a terminal and the
+//! --no-spinner flag isn't given.
+//!
+//! With the --ascii flag, only ASCII characters are used, e. g. + and -
+//! instead of checkmarks and crosses.
+
+use std::fmt;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use crate::construct_tree::Answer;
+use crate::i18n::tr;
+use crate::rules::Code;
+
+/// A set of colors, given as ANSI SGR parameters.
+pub struct Theme {
+    pub name: &'static str,
+    pub blue: &'static str,
+    pub yellow: &'static str,
+    pub purple: &'static str,
+    /// the color of checkmarks.
+    pub pass: &'static str,
+    /// the color of crosses.
+    pub fail: &'static str,
+}
+
+/// All available themes. The first one is the default.
+pub const THEMES: [Theme; 3] = [
+    Theme { name: "default", blue: "34", yellow: "33", purple: "35", pass: "32", fail: "31" },
+    // based on the Okabe-Ito palette, which avoids telling red and green
+    // apart.
+    Theme { name: "colorblind", blue: "38;5;25", yellow: "38;5;220", purple: "38;5;175", pass: "38;5;33", fail: "38;5;208" },
+    Theme { name: "high-contrast", blue: "1;94", yellow: "1;93", purple: "1;95", pass: "1;92", fail: "1;91" },
+];
+
+/// An animation shown while the solver is busy.
+pub struct Spinner {
+    pub name: &'static str,
+    pub frames: &'static [&'static str],
+}
+
+/// All available spinners. The first one is the default.
+pub const SPINNERS: [Spinner; 4] = [
+    Spinner { name: "braille", frames: &["⠇", "⠋", "⠙", "⠸", "⠴", "⠦"] },
+    Spinner { name: "line", frames: &["|", "/", "-", "\\"] },
+    Spinner { name: "dots", frames: &[".  ", ".. ", "...", "   "] },
+    Spinner { name: "moon", frames: &["◐", "◓", "◑", "◒"] },
+];
+
+static COLOR: AtomicBool = AtomicBool::new(true);
+static THEME: AtomicUsize = AtomicUsize::new(0);
+static ANIMATE: AtomicBool = AtomicBool::new(true);
+static SPINNER: AtomicUsize = AtomicUsize::new(0);
+static SPELL_OUT: AtomicBool = AtomicBool::new(false);
+static ASCII: AtomicBool = AtomicBool::new(false);
+
+/// Turn colored output on or off.
+pub fn set_color(enabled: bool) {
+    COLOR.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether output should be colored.
+pub fn color() -> bool {
+    COLOR.load(Ordering::Relaxed)
+}
+
+/// Select the theme with the given name. Returns false if there's no such
+/// theme.
+pub fn set_theme(name: &str) -> bool {
+    match THEMES.iter().position(|t| t.name == name) {
+        Some(i) => {
+            THEME.store(i, Ordering::Relaxed);
+            true
+        },
+        None => false,
+    }
+}
+
+/// Turn animations like spinners on or off.
+pub fn set_animate(enabled: bool) {
+    ANIMATE.store(enabled, Ordering::Relaxed);
+}
+
+/// Select the spinner with the given name. Returns false if there's no such
+/// spinner.
+pub fn set_spinner(name: &str) -> bool {
+    match SPINNERS.iter().position(|s| s.name == name) {
+        Some(i) => {
+            SPINNER.store(i, Ordering::Relaxed);
+            true
+        },
+        None => false,
+    }
+}
+
+/// The frames of the selected spinner, or None if animations are turned off.
+/// In ASCII mode, spinners using other characters are replaced by the line
+/// spinner.
+pub fn spinner() -> Option<&'static [&'static str]> {
+    if !ANIMATE.load(Ordering::Relaxed) {
+        return None;
+    }
+    let frames = SPINNERS[SPINNER.load(Ordering::Relaxed)].frames;
+    match ASCII.load(Ordering::Relaxed) && !frames.iter().all(|f| f.is_ascii()) {
+        true => Some(SPINNERS[1].frames),
+        false => Some(frames),
+    }
+}
+
+/// Print codes as words instead of colored digits.
+pub fn set_spell_out(enabled: bool) {
+    SPELL_OUT.store(enabled, Ordering::Relaxed);
+}
+
+/// Only use ASCII characters.
+pub fn set_ascii(enabled: bool) {
+    ASCII.store(enabled, Ordering::Relaxed);
+}
+
+/// Pick the ASCII replacement of a symbol, if ASCII mode is on.
+pub fn symbol(unicode: &'static str, ascii: &'static str) -> &'static str {
+    match ASCII.load(Ordering::Relaxed) {
+        true => ascii,
+        false => unicode,
+    }
+}
+
+fn theme() -> &'static Theme {
+    &THEMES[THEME.load(Ordering::Relaxed)]
+}
+
+/// Print text in the given color, if colors are turned on.
+fn paint(text: &str, color: &str) -> String {
+    match self::color() {
+        true => format!("\x1b[{}m{}\x1b[0m", color, text),
+        false => text.to_string(),
+    }
+}
+
+/// Enable the processing of ANSI escape sequences on Windows consoles, which
+/// older consoles don't do by default. Returns false if this failed, in which
+/// case colors should be turned off.
+#[cfg(windows)]
+pub fn enable_ansi() -> bool {
+    use std::os::windows::io::AsRawHandle;
+
+    type Handle = *mut std::ffi::c_void;
+    const ENABLE_VIRTUAL_TERMINAL_PROCESSING: u32 = 0x0004;
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetConsoleMode(handle: Handle, mode: *mut u32) -> i32;
+        fn SetConsoleMode(handle: Handle, mode: u32) -> i32;
+    }
+
+    let handle = std::io::stdout().as_raw_handle() as Handle;
+    let mut mode = 0;
+    // SAFETY: the handle belongs to stdout and stays valid, mode is a valid
+    // pointer to write to.
+    unsafe {
+        GetConsoleMode(handle, &mut mode) != 0
+            && SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != 0
+    }
+}
+
+/// Enable the processing of ANSI escape sequences. Other terminals do this by
+/// default.
+#[cfg(not(windows))]
+pub fn enable_ansi() -> bool {
+    true
+}
+
+/// Whether the environment supports animations.
+pub fn detect_animate() -> bool {
+    std::io::stdout().is_terminal()
+}
+
+/// Whether the environment supports colored output, see <https://no-color.org>.
+pub fn detect_color() -> bool {
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    !no_color && std::io::stdout().is_terminal()
+}
+
+/// A (usually green) checkmark.
+pub fn checkmark() -> String {
+    paint(symbol("✓", "+"), theme().pass)
+}
+
+/// A (usually red) cross.
+pub fn cross() -> String {
+    paint(symbol("✗", "-"), theme().fail)
+}
+
+/// A checkmark or a cross, depending on the answer.
+pub fn mark(answer: Answer) -> String {
+    match answer {
+        Answer::Pass => checkmark(),
+        Answer::Fail => cross(),
+    }
+}
+
+/// An arrow pointing to the right.
+pub fn arrow() -> &'static str {
+    symbol("→", "->")
+}
+
+/// A code with each digit in its color, or spelled out, e. g. "blue 4,
+/// yellow 1, purple 3".
+pub fn code(code: &Code) -> String {
+    if SPELL_OUT.load(Ordering::Relaxed) {
+        return tr!(spelled_code, code.blue, code.yellow, code.purple);
+    }
+    let theme = theme();
+    format!("{}{}{}",
+        paint(&code.blue.to_string(), theme.blue),
+        paint(&code.yellow.to_string(), theme.yellow),
+        paint(&code.purple.to_string(), theme.purple))
+}
+
+/// A code displayed with [`code`], see [`Code::colored`].
+pub struct ColoredCode<'a>(pub &'a Code);
+
+impl fmt::Display for ColoredCode<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&code(self.0))
+    }
+}
+
+/// The number of characters of text as shown in the terminal, ignoring
+/// escape sequences.
+pub fn width(text: &str) -> usize {
+    let mut width = 0;
+    let mut escaped = false;
+    for c in text.chars() {
+        match (escaped, c) {
+            (false, '\x1b') => escaped = true,
+            (false, _) => width += 1,
+            (true, 'm') => escaped = false,
+            (true, _) => {},
+        }
+    }
+    width
+}
+
+/// Print text in bold.
+pub fn bold(text: &str) -> String {
+    paint(text, "1")
+}
+
+/// Make text stand out by giving it a light background.
+pub fn highlight(text: &str) -> String {
+    match color() {
+        true => format!("\x1b[47m {} \x1b[0m", text),
+        false => format!("[{}]", text),
+    }
+}
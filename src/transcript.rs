@@ -0,0 +1,147 @@
+//! Transcripts of guided sessions.
+//!
+//! A transcript records the puzzle as well as every round, answer and undo
+//! of a session, one event per line, e. g.
+//!
+//! ```text
+//! puzzle A=3 B=8 C=12 D=17 E=20
+//! known 111:D=n
+//! round 1 241
+//! answer E=y
+//! undo 1
+//! answer E=n
+//! correct D=y
+//! solution 142
+//! ```
+//!
+//! Every line is written right away, so even interrupted sessions can be
+//! reviewed afterwards, e. g. with the replay command. After a correction, the
+//! session starts over, so another puzzle line follows.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+
+use crate::cli::{parse_query, Query};
+use tm_solver::construct_tree::Answer;
+use tm_solver::rules::Code;
+
+/// A transcript of a guided session. Without a file, nothing is recorded.
+#[derive(Default)]
+pub struct Transcript {
+    file: Option<File>,
+}
+
+impl Transcript {
+
+    /// Start a new transcript, writing to the file at the given path (if
+    /// any).
+    pub fn create(path: Option<&Path>) -> io::Result<Transcript> {
+        let file = match path {
+            Some(p) => Some(File::create(p)?),
+            None => None,
+        };
+        Ok(Transcript { file })
+    }
+
+    /// Record an event as a line of the transcript.
+    pub fn record(&mut self, event: &Event) {
+        if let Some(f) = &mut self.file {
+            let _ = writeln!(f, "{}", event);
+        }
+    }
+}
+
+/// How answers are written in transcripts.
+fn yes_no(answer: Answer) -> char {
+    match answer {
+        Answer::Pass => 'y',
+        Answer::Fail => 'n',
+    }
+}
+
+/// A single line of a transcript.
+pub enum Event {
+    /// the verifier letter and criteria card number of each verifier.
+    Puzzle(Vec<(char, u8)>),
+    /// a test the user performed before the session.
+    Known(Query),
+    /// the start of a round and the code used for it.
+    Round(usize, Code),
+    /// the user's answer for a verifier.
+    Answer(char, Answer),
+    /// the user took back their last answers.
+    Undo(usize),
+    /// the user corrected their last answer for a verifier. Afterwards, the
+    /// session starts over with all answers as known tests.
+    Correct(char, Answer),
+    Solution(Code),
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::Puzzle(cards) => {
+                let cards: Vec<String> = cards.iter().map(|(l, r)| format!("{}={}", l, r)).collect();
+                write!(f, "puzzle {}", cards.join(" "))
+            },
+            Event::Known(q) => write!(f, "known {}:{}={}", q.code, q.letter, yes_no(q.answer)),
+            Event::Round(round, code) => write!(f, "round {} {}", round, code),
+            Event::Answer(letter, answer) => write!(f, "answer {}={}", letter, yes_no(*answer)),
+            Event::Undo(answers) => write!(f, "undo {}", answers),
+            Event::Correct(letter, answer) => write!(f, "correct {}={}", letter, yes_no(*answer)),
+            Event::Solution(code) => write!(f, "solution {}", code),
+        }
+    }
+}
+
+/// Parse a verifier letter and answer, e. g. E=y.
+fn parse_answer(input: &str) -> Option<(char, Answer)> {
+    let (letter, answer) = input.split_once('=')?;
+    let mut chars = letter.chars();
+    let (Some(letter), None) = (chars.next(), chars.next()) else {
+        return None;
+    };
+    match answer {
+        "y" => Some((letter, Answer::Pass)),
+        "n" => Some((letter, Answer::Fail)),
+        _ => None,
+    }
+}
+
+/// Parse a single line of a transcript.
+fn parse_event(line: &str) -> Option<Event> {
+    let (kind, rest) = line.split_once(' ')?;
+    match kind {
+        "puzzle" => rest.split(' ').map(|card| {
+            let (letter, rule) = card.split_once('=')?;
+            Some((letter.chars().next()?, rule.parse().ok()?))
+        }).collect::<Option<_>>().map(Event::Puzzle),
+        "known" => parse_query(rest).ok().map(Event::Known),
+        "round" => {
+            let (round, code) = rest.split_once(' ')?;
+            Some(Event::Round(round.parse().ok()?, code.parse().ok()?))
+        },
+        "answer" => parse_answer(rest).map(|(l, p)| Event::Answer(l, p)),
+        "undo" => rest.parse().ok().map(Event::Undo),
+        "correct" => parse_answer(rest).map(|(l, p)| Event::Correct(l, p)),
+        "solution" => rest.parse().ok().map(Event::Solution),
+        _ => None,
+    }
+}
+
+/// Read all events of a transcript file.
+pub fn read(path: &Path) -> io::Result<Vec<Event>> {
+    let mut events = Vec::new();
+    for (i, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let event = parse_event(line.trim()).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData,
+            format!("line {} is not a valid transcript entry: {}", i + 1, line)))?;
+        events.push(event);
+    }
+    Ok(events)
+}
@@ -0,0 +1,171 @@
+//! Ways to walk through a [`BinaryTree`] without matching on it recursively:
+//! iterators over its leaves and over the paths leading to them, and the
+//! [`Visitor`] trait for everything else.
+//!
+//! All of them visit the correct child of a branch before the incorrect one.
+//! In this order, every node also has an id: the root is node 0, its correct
+//! child node 1 and so on. The ids only depend on the shape of the tree, so
+//! they stay the same when it's exported or saved and loaded again, and
+//! other programs can refer to a node by them. See [`BinaryTree::node`] and
+//! [`BinaryTree::node_id`].
+//!
+//! ```
+//! use tm_solver::solver::Solver;
+//!
+//! let strategy = Solver::builder().rules(&[4, 9, 11, 14]).solve()?;
+//! assert_eq!(strategy.tree.leaves().count(), strategy.unique_solutions.len());
+//! for (path, code) in strategy.tree.paths() {
+//!     // every test on the way and whether the code passes it.
+//!     println!("{}: {} tests", code, path.len());
+//! }
+//! # Ok::<(), tm_solver::error::TmSolverError>(())
+//! ```
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::construct_tree::{Answer, BinaryTree, Branch, Test, TreeTest};
+
+/// The tests on the way from the root to a node, along with their answers.
+pub type Path<X = Test> = Vec<(X, Answer)>;
+
+/// Iterator over the leaves of a tree, see [`BinaryTree::leaves`].
+pub struct Leaves<'a, T, X = Test> {
+    stack: Vec<&'a BinaryTree<T, X>>,
+}
+
+impl<'a, T, X> Iterator for Leaves<'a, T, X> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(node) = self.stack.pop() {
+            match node {
+                BinaryTree::Leaf(t) => return Some(t),
+                BinaryTree::Branch(b) => {
+                    self.stack.push(&b.incorrect);
+                    self.stack.push(&b.correct);
+                },
+            }
+        }
+        None
+    }
+}
+
+/// Iterator over the leaves of a tree along with the paths leading to them,
+/// see [`BinaryTree::paths`].
+pub struct Paths<'a, T, X = Test> {
+    stack: Vec<(&'a BinaryTree<T, X>, Path<X>)>,
+}
+
+impl<'a, T, X: TreeTest> Iterator for Paths<'a, T, X> {
+    type Item = (Path<X>, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node, path)) = self.stack.pop() {
+            match node {
+                BinaryTree::Leaf(t) => return Some((path, t)),
+                BinaryTree::Branch(b) => {
+                    let mut incorrect = path.clone();
+                    incorrect.push((b.test, Answer::Fail));
+                    self.stack.push((&b.incorrect, incorrect));
+                    let mut correct = path;
+                    correct.push((b.test, Answer::Pass));
+                    self.stack.push((&b.correct, correct));
+                },
+            }
+        }
+        None
+    }
+}
+
+/// Callbacks for [`BinaryTree::visit`]. Both methods do nothing by default,
+/// so only implement the ones you need.
+pub trait Visitor<T, X = Test> {
+
+    /// Called for every branch before its children, with the number of tests
+    /// performed before it. Return false to skip its children.
+    fn branch(&mut self, _branch: &Branch<T, X>, _depth: u8) -> bool {
+        true
+    }
+
+    /// Called for every leaf, with the number of tests performed to reach
+    /// it.
+    fn leaf(&mut self, _leaf: &T, _depth: u8) {}
+}
+
+impl<T, X: TreeTest> BinaryTree<T, X> {
+
+    /// Iterate over the leaves, i. e. solutions, of the tree.
+    pub fn leaves(&self) -> Leaves<'_, T, X> {
+        Leaves { stack: vec![self] }
+    }
+
+    /// Iterate over the leaves of the tree along with the tests leading to
+    /// them.
+    pub fn paths(&self) -> Paths<'_, T, X> {
+        Paths { stack: vec![(self, Vec::new())] }
+    }
+
+    /// The node with the given id, if the tree has that many nodes.
+    ///
+    /// ```
+    /// use tm_solver::construct_tree::{Answer, BinaryTree};
+    /// use tm_solver::solver::Solver;
+    ///
+    /// let tree = Solver::builder().rules(&[4, 9, 11, 14]).solve()?.tree;
+    /// let id = tree.node_id(&[Answer::Fail]).unwrap();
+    /// assert!(matches!(tree.node(id), Some(BinaryTree::Leaf(_))));
+    /// assert!(tree.node(tree.size()).is_none());
+    /// # Ok::<(), tm_solver::error::TmSolverError>(())
+    /// ```
+    pub fn node(&self, id: usize) -> Option<&BinaryTree<T, X>> {
+        let mut node = self;
+        let mut id = id;
+        while id > 0 {
+            let BinaryTree::Branch(b) = node else {
+                return None;
+            };
+            let correct = b.correct.size();
+            (node, id) = match id - 1 < correct {
+                true => (&b.correct, id - 1),
+                false => (&b.incorrect, id - 1 - correct),
+            };
+        }
+        Some(node)
+    }
+
+    /// The id of the node reached by following the tree with the given
+    /// answers, or None if there are more answers than tests.
+    pub fn node_id(&self, answers: &[Answer]) -> Option<usize> {
+        let mut node = self;
+        let mut id = 0;
+        for answer in answers {
+            let BinaryTree::Branch(b) = node else {
+                return None;
+            };
+            (node, id) = match answer {
+                Answer::Pass => (&b.correct, id + 1),
+                Answer::Fail => (&b.incorrect, id + 1 + b.correct.size()),
+            };
+        }
+        Some(id)
+    }
+
+    /// Walk through the whole tree depth-first, calling the visitor for every
+    /// node.
+    pub fn visit<V: Visitor<T, X>>(&self, visitor: &mut V) {
+        self.visit_rec(visitor, 0);
+    }
+
+    fn visit_rec<V: Visitor<T, X>>(&self, visitor: &mut V, depth: u8) {
+        match self {
+            BinaryTree::Leaf(t) => visitor.leaf(t, depth),
+            BinaryTree::Branch(b) => {
+                if visitor.branch(b, depth) {
+                    b.correct.visit_rec(visitor, depth + 1);
+                    b.incorrect.visit_rec(visitor, depth + 1);
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! A full-screen terminal interface for guided sessions.
+//!
+//! Instead of a scrolling dialog, this shows the current question, the
+//! question history, the remaining candidates and a grid resembling the
+//! game's note sheet all at once. Only available with the `tui` feature.
+
+use std::io;
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Cell, List, Paragraph, Row, Table};
+use ratatui::{DefaultTerminal, Frame};
+
+use tm_solver::construct_tree::{Answer, BinaryTree};
+use tm_solver::rules::{Code, RULE_DESCRIPTIONS};
+use tm_solver::session::GameSession;
+use crate::guide::{note_sheet, SheetRow};
+use crate::transcript::{self, Transcript};
+use crate::{replay, Prepared};
+
+/// The state of a guided session.
+struct Session<'a> {
+    prepared: &'a Prepared,
+    game: GameSession<'a>,
+}
+
+impl<'a> Session<'a> {
+
+    /// Answer the current question.
+    fn answer(&mut self, answer: Answer, transcript: &mut Transcript) {
+        let Some(question) = self.game.next_question() else {
+            return;
+        };
+        if question.new_round {
+            transcript.record(&transcript::Event::Round(self.sheet().len() + 1, question.code));
+        }
+        transcript.record(&transcript::Event::Answer(self.prepared.letters[question.test.0], answer));
+        self.game.answer(answer);
+    }
+
+    /// Take back the last answer.
+    fn undo(&mut self, transcript: &mut Transcript) {
+        if self.game.undo() {
+            transcript.record(&transcript::Event::Undo(1));
+        }
+    }
+
+    /// The rounds played so far, in the form of the game's note sheet.
+    fn sheet(&self) -> Vec<SheetRow<'a>> {
+        note_sheet(self.game.history(), self.prepared.letters.len())
+    }
+}
+
+/// A code with each digit in its color.
+fn code_span(code: &Code) -> Vec<Span<'static>> {
+    vec![
+        Span::styled(code.blue.to_string(), Style::new().fg(Color::Blue)),
+        Span::styled(code.yellow.to_string(), Style::new().fg(Color::Yellow)),
+        Span::styled(code.purple.to_string(), Style::new().fg(Color::Magenta)),
+    ]
+}
+
+fn mark(answer: Answer) -> Span<'static> {
+    match answer {
+        Answer::Pass => Span::styled("✓", Style::new().fg(Color::Green)),
+        Answer::Fail => Span::styled("✗", Style::new().fg(Color::Red)),
+    }
+}
+
+fn draw(frame: &mut Frame, session: &Session) {
+    let letters = &session.prepared.letters;
+    let sheet = session.sheet();
+    let [top, middle, bottom] = Layout::vertical([
+        Constraint::Length(5),
+        Constraint::Min(6),
+        Constraint::Length(sheet.len() as u16 + 4),
+    ]).areas(frame.area());
+    let [left, right] = Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .areas(middle);
+
+    // the current question
+    let question = match session.game.node() {
+        BinaryTree::Leaf(c) => vec![
+            Line::from([vec![Span::raw("Found a solution! Your code is: ")], code_span(c)].concat()),
+            Line::from("Press q to quit, b to go back."),
+        ],
+        BinaryTree::Branch(b) => {
+            let mut lines = Vec::new();
+            if let Some(q) = session.game.next_question() {
+                let round = sheet.len() + q.new_round as usize;
+                lines.push(Line::from([
+                    vec![Span::styled(format!("Round {}", round), Style::new().add_modifier(Modifier::BOLD)),
+                        Span::raw(" - use the combination ")],
+                    code_span(&q.code),
+                ].concat()));
+            }
+            lines.push(Line::from(vec![
+                Span::raw(format!("Does Test {} yield a ", letters[b.test.0])),
+                mark(Answer::Pass),
+                Span::raw("? (y/n, b to go back, q to quit)"),
+            ]));
+            let rule = session.prepared.rules[b.test.0];
+            lines.push(Line::from(format!("Card {}: {}", rule + 1, RULE_DESCRIPTIONS[rule])));
+            lines
+        },
+    };
+    frame.render_widget(Paragraph::new(question).block(Block::bordered().title("Question")), top);
+
+    // the questions answered so far
+    let history: Vec<Line> = session.game.history().iter().map(|(_, (test, _), answer)| {
+        Line::from(vec![Span::raw(format!("Test {}: ", letters[*test])), mark(*answer)])
+    }).collect();
+    frame.render_widget(List::new(history).block(Block::bordered().title("History")), left);
+
+    // the codes still possible
+    let remaining = session.game.remaining();
+    let codes: Vec<Line> = remaining.iter().map(|(_, c)| Line::from(code_span(c))).collect();
+    let title = format!("Remaining codes ({})", remaining.len());
+    frame.render_widget(List::new(codes).block(Block::bordered().title(title)), right);
+
+    // the note sheet
+    let header = Row::new([vec![Cell::from("Round"), Cell::from("Code")],
+        letters.iter().map(|l| Cell::from(l.to_string())).collect()].concat())
+        .style(Style::new().add_modifier(Modifier::BOLD));
+    let rows = sheet.iter().enumerate().map(|(i, row)| {
+        Row::new([
+            vec![Cell::from((i + 1).to_string()), Cell::from(Line::from(code_span(row.code)))],
+            row.results.iter().map(|r| match r {
+                Some(a) => Cell::from(mark(*a)),
+                None => Cell::from(""),
+            }).collect(),
+        ].concat())
+    });
+    let widths = [vec![Constraint::Length(6), Constraint::Length(6)],
+        vec![Constraint::Length(3); letters.len()]].concat();
+    frame.render_widget(
+        Table::new(rows, widths).header(header).block(Block::bordered().title("Note sheet")),
+        bottom);
+}
+
+fn event_loop(terminal: &mut DefaultTerminal, mut session: Session, transcript: &mut Transcript) -> io::Result<()> {
+    loop {
+        terminal.draw(|frame| draw(frame, &session))?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('y') => session.answer(Answer::Pass, transcript),
+            KeyCode::Char('n') => session.answer(Answer::Fail, transcript),
+            KeyCode::Char('b') | KeyCode::Backspace => session.undo(transcript),
+            KeyCode::Char('q') | KeyCode::Esc => {
+                if let Some(c) = session.game.solution() {
+                    transcript.record(&transcript::Event::Solution(*c));
+                }
+                return Ok(());
+            },
+            _ => {},
+        }
+    }
+}
+
+/// Run a guided session in full-screen mode, starting after the given
+/// answers.
+pub fn run(prepared: &Prepared, transcript: &mut Transcript, answers: &[Answer]) -> io::Result<()> {
+    let (game, events) = replay(prepared, answers);
+    events.iter().for_each(|e| transcript.record(e));
+    let session = Session { prepared, game };
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, session, transcript);
+    ratatui::restore();
+    result
+}
@@ -0,0 +1,110 @@
+//! Bindings for running the solver in a browser, enabled with the `wasm`
+//! feature. Build them with e. g.
+//! `wasm-pack build --target web -- --features wasm`.
+//!
+//! ```js
+//! const game = solve([4, 9, 11, 14]);
+//! let question = game.nextQuestion();
+//! while (question) {
+//!     // test question.code on verifier question.verifier
+//!     game.answer(passed);
+//!     question = game.nextQuestion();
+//! }
+//! console.log(game.solution());
+//! ```
+
+use wasm_bindgen::prelude::*;
+
+use crate::construct_tree::Answer;
+use crate::export;
+use crate::session::GameSession;
+use crate::solver::{Solver, Strategy};
+
+/// A test to perform with the physical game.
+#[wasm_bindgen(getter_with_clone)]
+pub struct Question {
+    /// the code to test, e. g. "241".
+    pub code: String,
+    /// the letter of the verifier to test it on.
+    pub verifier: char,
+    /// the number of the verifier's criteria card.
+    pub card: usize,
+    /// whether this test starts a new round, i. e. a new code.
+    #[wasm_bindgen(js_name = newRound)]
+    pub new_round: bool,
+}
+
+/// A puzzle being solved by following its optimal strategy.
+#[wasm_bindgen]
+pub struct Game {
+    strategy: Strategy,
+    letters: Vec<char>,
+    /// the answers given so far.
+    answers: Vec<Answer>,
+}
+
+/// Construct the optimal strategy for a puzzle, given the numbers of its
+/// criteria cards. Throws if the puzzle can't be solved.
+#[wasm_bindgen]
+pub fn solve(cards: Vec<usize>) -> Result<Game, JsError> {
+    let strategy = Solver::builder().rules(&cards).solve()?;
+    let letters = (0..cards.len()).map(|i| (b'A' + i as u8) as char).collect();
+    Ok(Game { strategy, letters, answers: Vec::new() })
+}
+
+#[wasm_bindgen]
+impl Game {
+
+    /// The next test to perform, or nothing once the code is known.
+    #[wasm_bindgen(js_name = nextQuestion)]
+    pub fn next_question(&self) -> Option<Question> {
+        let question = self.session().next_question()?;
+        Some(Question {
+            code: question.code.to_string(),
+            verifier: self.letters[question.test.0],
+            card: self.strategy.rules[question.test.0] + 1,
+            new_round: question.new_round,
+        })
+    }
+
+    /// Answer the current question: whether the verifier yielded a
+    /// checkmark. Ignored once the code is known.
+    pub fn answer(&mut self, passed: bool) {
+        if self.session().answer(passed.into()) {
+            self.answers.push(passed.into());
+        }
+    }
+
+    /// Take back the last answer.
+    pub fn undo(&mut self) {
+        self.answers.pop();
+    }
+
+    /// The code, once the answers identify it.
+    pub fn solution(&self) -> Option<String> {
+        self.session().solution().map(|c| c.to_string())
+    }
+
+    /// The number of codes that are still possible.
+    pub fn remaining(&self) -> usize {
+        self.session().remaining().len()
+    }
+
+    /// The whole strategy as JSON, see the `export` module.
+    #[wasm_bindgen(js_name = strategyJson)]
+    pub fn strategy_json(&self) -> String {
+        export::strategy(&self.letters, &self.strategy.rules, &self.strategy.tree).to_json()
+    }
+}
+
+impl Game {
+
+    /// The session reached with the answers so far.
+    fn session(&self) -> GameSession<'_> {
+        let mut session = self.strategy.session();
+        for answer in &self.answers {
+            session.answer(*answer);
+        }
+        session
+    }
+}
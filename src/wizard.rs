@@ -0,0 +1,76 @@
+//! An interactive setup wizard for users who start the solver without any
+//! arguments.
+//!
+//! The wizard asks for the number of verifiers and lets the user pick each
+//! criteria card, either by number or by searching the card descriptions.
+
+use crate::cli::{CardsArg, PuzzleArgs, RuleArg};
+use crate::frontend::FrontEnd;
+use tm_solver::i18n::tr;
+use tm_solver::rules::{RULES, RULE_DESCRIPTIONS};
+
+/// Read a trimmed line. Returns None once there is no more input.
+fn read_line(front: &mut impl FrontEnd) -> Option<String> {
+    front.read().map(|input| input.trim().to_string())
+}
+
+/// Show all criteria cards whose description contains the search term.
+fn show_matches(front: &mut impl FrontEnd, search: &str) {
+    let search = search.to_lowercase();
+    let mut found = false;
+    for (i, description) in RULE_DESCRIPTIONS.iter().enumerate() {
+        if description.to_lowercase().contains(&search) {
+            front.show(&format!("  {:>2}: {}", i + 1, description));
+            found = true;
+        }
+    }
+    if !found {
+        front.show(&format!("  {}", tr!(no_match, search)));
+    }
+}
+
+/// Ask the user for the number of verifiers.
+fn ask_verifiers(front: &mut impl FrontEnd) -> Option<usize> {
+    front.show(&tr!(ask_verifiers));
+    loop {
+        match read_line(front)?.parse::<usize>() {
+            Ok(n) if (4..=6).contains(&n) => return Some(n),
+            _ => front.show(&tr!(number_range, 4, 6)),
+        }
+    }
+}
+
+/// Ask the user for the criteria card of a verifier. Any input that isn't a
+/// card number is used to search the card descriptions.
+fn ask_rule(front: &mut impl FrontEnd, letter: char, used: &[RuleArg]) -> Option<u8> {
+    front.show(&tr!(ask_rule, letter));
+    loop {
+        let input = read_line(front)?;
+        match input.parse::<u8>() {
+            Ok(r) if r == 0 || r as usize > RULES.len() => {
+                front.show(&tr!(number_range, 1, RULES.len()));
+            },
+            Ok(r) if used.iter().any(|u| u.rule == r) => {
+                front.show(&tr!(rule_used, r));
+            },
+            Ok(r) => {
+                front.show(&format!("  {}: {}", letter, RULE_DESCRIPTIONS[r as usize - 1]));
+                return Some(r);
+            },
+            Err(_) => show_matches(front, &input),
+        }
+    }
+}
+
+/// Run the wizard. Returns None if the input runs out before finishing.
+pub fn run(front: &mut impl FrontEnd) -> Option<PuzzleArgs> {
+    front.show(&tr!(wizard_start));
+    let verifiers = ask_verifiers(front)?;
+    let mut rules = Vec::new();
+    for i in 0..verifiers {
+        let letter = (b'A' + i as u8) as char;
+        let rule = ask_rule(front, letter, &rules)?;
+        rules.push(RuleArg { letter: Some(letter), rule });
+    }
+    Some(PuzzleArgs { rules: rules.into_iter().map(CardsArg::Card).collect(), ..Default::default() })
+}